@@ -1,15 +1,53 @@
-use std::net::SocketAddr;
+use std::{
+    net::{
+        IpAddr,
+        SocketAddr,
+    },
+    num::NonZeroUsize,
+    path::PathBuf,
+};
 
 use clap::{
     Parser,
+    Subcommand,
+    ValueEnum,
     value_parser,
 };
+use docker_registry_client::Image;
+use ipnet::IpNet;
 use tracing::Level;
 
+use crate::{
+    handler,
+    tls,
+};
+
 /// Simple uploading service
 #[derive(Parser, Debug)]
 #[clap()]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "command line switches are plain flags"
+)]
 pub(super) struct Args {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
+    /// JSON file with settings keyed by their flag name, flags and
+    /// environment variables take precedence over the file
+    #[clap(long, value_name = "path", env = "TRIVY_WEB_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Profile of the config file whose settings are merged over the base
+    /// settings, e.g. `prod`
+    #[clap(
+        long,
+        value_name = "name",
+        requires = "config",
+        env = "TRIVY_WEB_PROFILE"
+    )]
+    pub profile: Option<String>,
+
     /// Loglevel to run under
     #[clap(
         long,
@@ -29,11 +67,509 @@ pub(super) struct Args {
     )]
     pub binding: SocketAddr,
 
-    /// When set use a redis server for caching
+    /// Serve HTTPS with this PEM certificate chain instead of plain HTTP
+    #[clap(
+        long,
+        value_name = "path",
+        requires = "tls_key",
+        env = "TRIVY_WEB_TLS_CERTIFICATE"
+    )]
+    pub tls_certificate: Option<PathBuf>,
+
+    /// PEM private key of the TLS certificate
+    #[clap(
+        long,
+        value_name = "path",
+        requires = "tls_certificate",
+        env = "TRIVY_WEB_TLS_KEY"
+    )]
+    pub tls_key: Option<PathBuf>,
+
+    /// Lowest TLS version accepted from clients
+    #[clap(
+        long,
+        value_name = "version",
+        value_enum,
+        default_value = "1.2",
+        env = "TRIVY_WEB_TLS_MIN_VERSION"
+    )]
+    pub tls_min_version: tls::TlsVersion,
+
+    /// TLS cipher suites offered to clients, e.g. `TLS13_AES_256_GCM_SHA384`,
+    /// all suites supported by rustls are offered when not set
+    #[clap(
+        long,
+        value_name = "suite",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_TLS_CIPHER_SUITES"
+    )]
+    pub tls_cipher_suites: Vec<String>,
+
+    /// Also serve HTTP/2, negotiated through ALPN with TLS and as h2c
+    /// without
+    #[clap(long, env = "TRIVY_WEB_HTTP2")]
+    pub http2: bool,
+
+    /// Maximum number of requests handled at the same time, further requests
+    /// are answered with 503 Service Unavailable
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_MAX_CONNECTIONS")]
+    pub max_connections: Option<NonZeroUsize>,
+
+    /// Reverse proxies whose X-Forwarded-For and X-Forwarded-Proto headers
+    /// are trusted
+    #[clap(
+        long,
+        value_name = "address/prefix",
+        value_parser = parse_trusted_proxy,
+        value_delimiter = ',',
+        env = "TRIVY_WEB_TRUSTED_PROXIES"
+    )]
+    pub trusted_proxies: Vec<IpNet>,
+
+    /// When set use a redis server for caching, scan results are cached in
+    /// memory otherwise
     #[clap(long, value_name = "redis://address:port", env = "TRIVY_REDIS_SERVER")]
     pub redis_server: Option<String>,
 
-    /// Optionally use an trivy server for scanning
-    #[clap(long, value_name = "address:port", env = "TRIVY_SERVER")]
-    pub server: Option<String>,
+    /// How long scan results are cached in redis or memory
+    #[clap(
+        long,
+        value_name = "seconds",
+        default_value = "86400",
+        value_parser = value_parser!(i64).range(1..),
+        env = "TRIVY_WEB_REDIS_TTL"
+    )]
+    pub redis_ttl: i64,
+
+    /// Collect cache writes for this long and store them in a single redis
+    /// pipeline to save round trips under load
+    #[clap(
+        long,
+        value_name = "milliseconds",
+        requires = "redis_server",
+        env = "TRIVY_WEB_CACHE_BATCH"
+    )]
+    pub cache_batch: Option<u64>,
+
+    /// Serve cached results older than this while refreshing them in the
+    /// background
+    #[clap(long, value_name = "seconds", env = "TRIVY_WEB_CACHE_FRESH_FOR")]
+    pub cache_fresh_for: Option<i64>,
+
+    /// Rescan an image at most once per this many seconds, requests within
+    /// the cooldown are served the cached result
+    #[clap(
+        long,
+        value_name = "seconds",
+        value_parser = value_parser!(i64).range(1..),
+        requires = "redis_server",
+        env = "TRIVY_WEB_REFRESH_COOLDOWN"
+    )]
+    pub refresh_cooldown: Option<i64>,
+
+    /// Keep compact trend records of scans for this long after the full
+    /// results expired, they are kept indefinitely when not set
+    #[clap(long, value_name = "seconds", env = "TRIVY_WEB_TREND_RETENTION")]
+    pub trend_retention: Option<i64>,
+
+    /// Redis stream on the redis server scan-completed events are published
+    /// to, only fresh scans are published
+    #[clap(long, value_name = "stream", env = "TRIVY_WEB_EVENT_STREAM")]
+    pub event_stream: Option<String>,
+
+    /// Only serve cached results and answer cache misses with 503 Service
+    /// Unavailable instead of scanning, can be toggled at runtime through
+    /// the admin endpoint
+    #[clap(long, env = "TRIVY_WEB_READ_ONLY")]
+    pub read_only: bool,
+
+    /// Bearer token required by the admin endpoints, they are disabled when
+    /// not set
+    #[clap(
+        long,
+        value_name = "token",
+        env = "TRIVY_WEB_ADMIN_TOKEN",
+        hide_env_values = true
+    )]
+    pub admin_token: Option<String>,
+
+    /// User HTTP Basic auth requires for every route but `/healthz`, the
+    /// service is open when not set
+    #[clap(
+        long,
+        value_name = "user",
+        env = "TRIVY_WEB_AUTH_USER",
+        requires = "auth_password"
+    )]
+    pub auth_user: Option<String>,
+
+    /// Password HTTP Basic auth requires together with `--auth-user`
+    #[clap(
+        long,
+        value_name = "password",
+        env = "TRIVY_WEB_AUTH_PASSWORD",
+        hide_env_values = true,
+        requires = "auth_user"
+    )]
+    pub auth_password: Option<String>,
+
+    /// Key the JSON scan results are signed with, the HMAC-SHA256 signature
+    /// is returned in the `X-Signature` header
+    #[clap(
+        long,
+        value_name = "key",
+        env = "TRIVY_WEB_RESULT_SIGNING_KEY",
+        hide_env_values = true
+    )]
+    pub result_signing_key: Option<String>,
+
+    /// Skip fetching the docker manifest and cosign information for images
+    #[clap(long, env = "TRIVY_WEB_DISABLE_DOCKER_INFO")]
+    pub disable_docker_info: bool,
+
+    /// Pass `--private-infrastructure` to cosign verify which skips the Rekor
+    /// transparency log, set to false to enforce transparency log
+    /// verification against the public sigstore instance
+    #[clap(
+        long,
+        value_name = "bool",
+        default_value_t = true,
+        action = clap::ArgAction::Set,
+        env = "TRIVY_WEB_COSIGN_PRIVATE_INFRASTRUCTURE"
+    )]
+    pub cosign_private_infrastructure: bool,
+
+    /// Cosign executable used to verify signatures
+    #[clap(
+        long,
+        value_name = "path",
+        default_value = "cosign",
+        env = "TRIVY_WEB_COSIGN_BINARY"
+    )]
+    pub cosign_binary: PathBuf,
+
+    /// Fail docker registry requests that take longer than this
+    #[clap(long, value_name = "seconds", env = "TRIVY_WEB_REGISTRY_TIMEOUT")]
+    pub registry_timeout: Option<u64>,
+
+    /// Maximum number of concurrent docker registry requests
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_REGISTRY_CONCURRENCY")]
+    pub registry_concurrency: Option<NonZeroUsize>,
+
+    /// Maximum number of concurrent cosign signature manifest fetches,
+    /// concurrent requests for the same image share one fetch
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_COSIGN_CONCURRENCY")]
+    pub cosign_concurrency: Option<NonZeroUsize>,
+
+    /// Maximum number of image manifest lookups and cosign verifications
+    /// running at once across all requests, further requests wait for a free
+    /// slot
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_FAN_OUT_LIMIT")]
+    pub fan_out_limit: Option<NonZeroUsize>,
+
+    /// Look up the manifest of a canary image at startup to verify docker
+    /// registry access
+    #[clap(long, env = "TRIVY_WEB_STARTUP_REGISTRY_CHECK")]
+    pub startup_registry_check: bool,
+
+    /// Image used by the startup registry check
+    #[clap(
+        long,
+        value_name = "image",
+        default_value = "docker.io/library/alpine:latest",
+        env = "TRIVY_WEB_STARTUP_REGISTRY_CANARY"
+    )]
+    pub startup_registry_canary: Image,
+
+    /// Download the trivy vulnerability DB at startup so the first scan does
+    /// not have to wait for it
+    #[clap(long, env = "TRIVY_WEB_WARMUP_DB")]
+    pub warmup_db: bool,
+
+    /// Refuse to start when the startup registry check fails
+    #[clap(long, env = "TRIVY_WEB_FAIL_FAST")]
+    pub fail_fast: bool,
+
+    /// Report advisories with a CVE alias under the CVE and merge duplicate
+    /// findings of the same vulnerability
+    #[clap(long, env = "TRIVY_WEB_CANONICAL_IDS")]
+    pub canonical_ids: bool,
+
+    /// Weights of the severities in the risk score of a scan, severities
+    /// that are not given keep their default weight
+    #[clap(
+        long,
+        value_name = "severity=weight",
+        default_value = "critical=10,high=5,medium=2,low=1,unknown=0",
+        env = "TRIVY_WEB_SEVERITY_WEIGHTS"
+    )]
+    pub severity_weights: handler::SeverityWeights,
+
+    /// Reclassify severities after scanning, e.g. `medium=high` to treat
+    /// medium vulnerabilities as high. Counts follow the reclassified
+    /// severities
+    #[clap(
+        long,
+        value_name = "severity=severity",
+        env = "TRIVY_WEB_SEVERITY_MAPPING"
+    )]
+    pub severity_mapping: Option<handler::SeverityMapping>,
+
+    /// CVSS version whose score is shown when a source reports both, the
+    /// other version is only shown as a fallback
+    #[clap(
+        long,
+        value_name = "version",
+        value_enum,
+        default_value = "v3",
+        env = "TRIVY_WEB_CVSS_VERSION"
+    )]
+    pub cvss_version: handler::CvssVersion,
+
+    /// CVSS sources in the order their score is used to derive the severity
+    /// of vulnerabilities trivy reports as unknown, e.g. `nvd,redhat,ghsa`.
+    /// Nothing is derived when empty
+    #[clap(
+        long,
+        value_name = "source",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_SEVERITY_FALLBACK"
+    )]
+    pub severity_fallback: Vec<String>,
+
+    /// Casing of the field names returned by the JSON API endpoints
+    #[clap(
+        long,
+        value_name = "case",
+        value_enum,
+        default_value = "snake_case",
+        env = "TRIVY_WEB_API_JSON_CASE"
+    )]
+    pub api_json_case: handler::JsonCase,
+
+    /// Scan output formats whose endpoints are served, endpoints of other
+    /// formats return 404
+    #[clap(
+        long,
+        value_name = "format",
+        value_enum,
+        value_delimiter = ',',
+        default_value = "json,sarif,sbom,markdown,github-annotations",
+        env = "TRIVY_WEB_ENABLED_FORMATS"
+    )]
+    pub enabled_formats: Vec<handler::OutputFormat>,
+
+    /// Maximum number of references shown per vulnerability before the rest
+    /// are collapsed
+    #[clap(
+        long,
+        value_name = "count",
+        default_value = "5",
+        env = "TRIVY_WEB_MAX_REFERENCES"
+    )]
+    pub max_references: usize,
+
+    /// Maximum size in bytes of trivy reports submitted to the render
+    /// endpoints
+    #[clap(
+        long,
+        value_name = "bytes",
+        default_value = "67108864",
+        env = "TRIVY_WEB_MAX_REPORT_SIZE"
+    )]
+    pub max_report_size: usize,
+
+    /// Trivy executable used for scans
+    #[clap(
+        long,
+        value_name = "path",
+        default_value = "trivy",
+        env = "TRIVY_WEB_TRIVY_BINARY"
+    )]
+    pub trivy_binary: PathBuf,
+
+    /// Optionally use trivy servers for scanning, scans are routed to the
+    /// least loaded server
+    #[clap(
+        long,
+        value_name = "address:port",
+        value_delimiter = ',',
+        env = "TRIVY_SERVER"
+    )]
+    pub server: Vec<String>,
+
+    /// Trivy servers a request may pick instead of the configured ones, e.g.
+    /// scanners with access to internal registries
+    #[clap(
+        long,
+        value_name = "address:port",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_ALLOWED_TRIVY_SERVERS"
+    )]
+    pub allowed_trivy_server: Vec<String>,
+
+    /// Registry domains images may be scanned from, e.g. `ghcr.io`. Images
+    /// of every registry are scanned when none is given
+    #[clap(
+        long,
+        value_name = "domain",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_ALLOWED_REGISTRIES"
+    )]
+    pub allowed_registry: Vec<String>,
+
+    /// Maximum number of concurrent scans per trivy server
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_SERVER_CONCURRENCY")]
+    pub server_concurrency: Option<NonZeroUsize>,
+
+    /// Maximum number of trivy scans running at once, further scans wait
+    /// in a queue
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_SCAN_CONCURRENCY")]
+    pub scan_concurrency: Option<NonZeroUsize>,
+
+    /// Value passed to trivy's `--parallel` flag to limit its concurrency
+    #[clap(long, value_name = "count", env = "TRIVY_WEB_TRIVY_PARALLEL")]
+    pub trivy_parallel: Option<usize>,
+
+    /// Maximum number of SBOMs generated at once for the platforms of a
+    /// multi-arch image
+    #[clap(
+        long,
+        value_name = "count",
+        default_value = "2",
+        env = "TRIVY_WEB_SBOM_CONCURRENCY"
+    )]
+    pub sbom_concurrency: NonZeroUsize,
+
+    /// Scanners trivy should run, uses trivy's defaults when not set
+    #[clap(
+        long,
+        value_name = "scanner",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_TRIVY_SCANNERS"
+    )]
+    pub trivy_scanners: Vec<handler::Scanner>,
+
+    /// Trivy plugins requests may run against an image, e.g. custom
+    /// reporters
+    #[clap(
+        long,
+        value_name = "plugin",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_TRIVY_PLUGINS"
+    )]
+    pub trivy_plugin: Vec<String>,
+
+    /// Compliance specs requests may check an image against, e.g.
+    /// `docker-cis-1.6.0`
+    #[clap(
+        long,
+        value_name = "spec",
+        value_delimiter = ',',
+        env = "TRIVY_WEB_TRIVY_COMPLIANCE"
+    )]
+    pub trivy_compliance: Vec<String>,
+
+    /// Docker config JSON trivy uses for registry credentials, e.g. a
+    /// mounted `dockerconfigjson` secret. The file has to be named
+    /// `config.json` as trivy looks it up through `DOCKER_CONFIG`
+    #[clap(long, value_name = "path", env = "TRIVY_WEB_PULL_SECRET")]
+    pub pull_secret: Option<PathBuf>,
+
+    /// Trivy DBs older than this are reported as stale by the health
+    /// endpoint
+    #[clap(
+        long,
+        value_name = "seconds",
+        default_value = "86400",
+        env = "TRIVY_WEB_TRIVY_DB_MAX_AGE"
+    )]
+    pub trivy_db_max_age: i64,
+
+    /// Trivy and cosign runs that take longer than this are killed
+    #[clap(
+        long,
+        value_name = "seconds",
+        default_value = "120",
+        env = "TRIVY_WEB_SCAN_TIMEOUT"
+    )]
+    pub scan_timeout: u64,
+
+    /// Rego policy passed to trivy's `--ignore-policy` flag
+    #[clap(long, value_name = "path", env = "TRIVY_WEB_IGNORE_POLICY")]
+    pub ignore_policy: Option<PathBuf>,
+
+    /// `.trivyignore` with accepted vulnerability IDs passed to trivy's
+    /// `--ignorefile` flag
+    #[clap(long, value_name = "path", env = "TRIVY_WEB_TRIVYIGNORE")]
+    pub trivyignore: Option<PathBuf>,
+
+    /// File with one image digest per line, images with these digests are
+    /// not scanned. Reloaded by the `/api/admin/trusted-digests/reload`
+    /// endpoint
+    #[clap(long, value_name = "path", env = "TRIVY_WEB_TRUSTED_DIGESTS")]
+    pub trusted_digests: Option<PathBuf>,
+
+    /// Append a JSON record of every scan request to this file, `-` writes
+    /// the records to stdout. Credentials are not recorded
+    #[clap(long, value_name = "path", env = "TRIVY_WEB_AUDIT_LOG")]
+    pub audit_log: Option<PathBuf>,
+
+    /// Docker credential helper used to get short-lived tokens for a
+    /// registry
+    #[clap(
+        long,
+        value_name = "registry=binary",
+        value_parser = parse_credential_helper,
+        value_delimiter = ',',
+        env = "TRIVY_WEB_CREDENTIAL_HELPER"
+    )]
+    pub credential_helper: Vec<(String, PathBuf)>,
+
+    /// How long tokens from credential helpers are cached
+    #[clap(
+        long,
+        value_name = "seconds",
+        default_value = "3600",
+        env = "TRIVY_WEB_CREDENTIAL_HELPER_TTL"
+    )]
+    pub credential_helper_ttl: i64,
+}
+
+#[derive(Subcommand, Debug)]
+pub(super) enum Command {
+    /// Scan images read from stdin or a file, print a summary and exit
+    Scan(ScanArgs),
+}
+
+#[derive(clap::Args, Debug)]
+pub(super) struct ScanArgs {
+    /// File with one image per line, reads from stdin when not set
+    #[clap(long, value_name = "path")]
+    pub input: Option<PathBuf>,
+
+    /// Format of the printed summary
+    #[clap(long, value_enum, default_value = "table")]
+    pub format: OutputFormat,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub(super) enum OutputFormat {
+    Table,
+    Json,
+}
+
+fn parse_credential_helper(value: &str) -> Result<(String, PathBuf), String> {
+    let (registry, binary) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected registry=binary but got {value}"))?;
+
+    Ok((registry.to_string(), PathBuf::from(binary)))
+}
+
+fn parse_trusted_proxy(value: &str) -> Result<IpNet, String> {
+    value
+        .parse::<IpNet>()
+        .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|err| format!("invalid address or network {value}: {err}"))
 }
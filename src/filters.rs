@@ -5,13 +5,133 @@
     reason = "generated helper code from askama::filter_fn intentionally triggers these lints"
 )]
 
+use crate::handler::Severity;
+
+/// Colors of the standard and bright SGR color codes, e.g. `31` and `91` for
+/// red.
+const ANSI_COLORS: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// Escapes the text and turns SGR color escape sequences into styled spans and
+/// newlines into line breaks. Other escape sequences are dropped.
 #[askama::filter_fn]
 pub fn ansi_to_html<T: std::fmt::Display>(
     s: T,
     _: &dyn askama::Values,
 ) -> ::askama::Result<String> {
-    let s = s.to_string();
-    Ok(s.replace('\n', "<br />"))
+    Ok(ansi_html(&s.to_string()))
+}
+
+/// Graphic rendition set by SGR escape sequences.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct AnsiStyle {
+    bold: bool,
+    foreground: Option<&'static str>,
+    background: Option<&'static str>,
+}
+
+impl AnsiStyle {
+    fn apply(&mut self, parameters: &str) {
+        // an empty parameter list is a reset
+        for parameter in parameters.split(';') {
+            match parameter.parse::<usize>().unwrap_or(0) {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                22 => self.bold = false,
+                code @ 30..=37 => self.foreground = Some(ANSI_COLORS[code - 30]),
+                39 => self.foreground = None,
+                code @ 40..=47 => self.background = Some(ANSI_COLORS[code - 40]),
+                49 => self.background = None,
+                code @ 90..=97 => self.foreground = Some(ANSI_COLORS[code - 90 + 8]),
+                code @ 100..=107 => self.background = Some(ANSI_COLORS[code - 100 + 8]),
+                _ => {}
+            }
+        }
+    }
+
+    fn open(self, html: &mut String) {
+        if self == Self::default() {
+            return;
+        }
+
+        let mut style = Vec::new();
+
+        if self.bold {
+            style.push("font-weight:bold".to_string());
+        }
+
+        if let Some(color) = self.foreground {
+            style.push(format!("color:{color}"));
+        }
+
+        if let Some(color) = self.background {
+            style.push(format!("background-color:{color}"));
+        }
+
+        html.push_str("<span style=\"");
+        html.push_str(&style.join(";"));
+        html.push_str("\">");
+    }
+
+    fn close(self, html: &mut String) {
+        if self != Self::default() {
+            html.push_str("</span>");
+        }
+    }
+}
+
+fn ansi_html(s: &str) -> String {
+    let mut html = String::with_capacity(s.len());
+    let mut style = AnsiStyle::default();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                if chars.next_if_eq(&'[').is_none() {
+                    continue;
+                }
+
+                let mut parameters = String::new();
+                let mut command = None;
+
+                for c in chars.by_ref() {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        command = Some(c);
+                        break;
+                    }
+
+                    parameters.push(c);
+                }
+
+                if command == Some('m') {
+                    style.close(&mut html);
+                    style.apply(&parameters);
+                    style.open(&mut html);
+                }
+            }
+
+            // spans never cross line breaks
+            '\n' => {
+                style.close(&mut html);
+                html.push_str("<br />");
+                style.open(&mut html);
+            }
+
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#x27;"),
+            c => html.push(c),
+        }
+    }
+
+    style.close(&mut html);
+
+    html
 }
 
 #[askama::filter_fn]
@@ -19,3 +139,227 @@ pub fn format_error(err: &eyre::Error, _: &dyn askama::Values) -> ::askama::Resu
     let s = format!("{err:?}");
     Ok(s)
 }
+
+#[askama::filter_fn]
+pub fn human_bytes<T: std::borrow::Borrow<u64>>(
+    size: T,
+    _: &dyn askama::Values,
+) -> ::askama::Result<String> {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+
+    let size = *size.borrow();
+
+    #[expect(
+        clippy::cast_precision_loss,
+        reason = "sizes are only displayed with two decimal places"
+    )]
+    let mut value = size as f64;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        Ok(format!("{size} B"))
+    } else {
+        Ok(format!("{value:.2} {}", UNITS[unit]))
+    }
+}
+
+/// Stable CSS class of a severity, e.g. `sev-critical`.
+#[askama::filter_fn]
+pub fn severity_class<T: std::borrow::Borrow<Severity>>(
+    severity: T,
+    _: &dyn askama::Values,
+) -> ::askama::Result<&'static str> {
+    Ok(match severity.borrow() {
+        Severity::Critical => "sev-critical",
+        Severity::High => "sev-high",
+        Severity::Medium => "sev-medium",
+        Severity::Low => "sev-low",
+        Severity::Unknown => "sev-unknown",
+    })
+}
+
+/// Color of a severity, matches the colors of the stylesheet.
+#[askama::filter_fn]
+pub fn severity_color<T: std::borrow::Borrow<Severity>>(
+    severity: T,
+    _: &dyn askama::Values,
+) -> ::askama::Result<&'static str> {
+    Ok(match severity.borrow() {
+        Severity::Critical => "#FF6F6F",
+        Severity::High => "#FFA500",
+        Severity::Medium => "#FFD700",
+        Severity::Low => "#00BFFF",
+        Severity::Unknown => "#BEBEBE",
+    })
+}
+
+#[askama::filter_fn]
+pub fn markdown_cell<T: std::fmt::Display>(
+    s: T,
+    _: &dyn askama::Values,
+) -> ::askama::Result<String> {
+    let s = s.to_string();
+    Ok(s.replace('|', "\\|").replace(['\r', '\n'], " "))
+}
+
+/// Durations as produced by `fetch_duration` and `expires_duration`, positive
+/// durations lie in the past and negative ones in the future.
+#[askama::filter_fn]
+pub fn humanize_duration<T: std::borrow::Borrow<chrono::Duration>>(
+    duration: T,
+    _: &dyn askama::Values,
+) -> ::askama::Result<String> {
+    Ok(humanize(*duration.borrow()))
+}
+
+fn humanize(duration: chrono::Duration) -> String {
+    const UNITS: [(i64, &str); 4] = [
+        (24 * 60 * 60, "day"),
+        (60 * 60, "hour"),
+        (60, "minute"),
+        (1, "second"),
+    ];
+
+    let seconds = duration.num_seconds();
+
+    let Some((count, unit)) = UNITS
+        .iter()
+        .find(|(length, _)| seconds.abs() >= *length)
+        .map(|(length, unit)| (seconds.abs() / length, unit))
+    else {
+        return "just now".to_string();
+    };
+
+    let plural = if count == 1 { "" } else { "s" };
+
+    if seconds > 0 {
+        format!("{count} {unit}{plural} ago")
+    } else {
+        format!("in {count} {unit}{plural}")
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use askama::Template;
+    use chrono::Duration;
+
+    use super::{
+        ansi_html,
+        humanize,
+    };
+    use crate::{
+        filters,
+        handler::Severity,
+    };
+
+    #[derive(Template)]
+    #[template(
+        source = "{{ severity|severity_class }} {{ severity|severity_color }}",
+        ext = "txt"
+    )]
+    struct SeverityTemplate {
+        severity: Severity,
+    }
+
+    fn severity_style(severity: Severity) -> String {
+        SeverityTemplate { severity }.render().unwrap()
+    }
+
+    #[test]
+    fn severity_styles() {
+        assert_eq!("sev-critical #FF6F6F", severity_style(Severity::Critical));
+        assert_eq!("sev-high #FFA500", severity_style(Severity::High));
+        assert_eq!("sev-medium #FFD700", severity_style(Severity::Medium));
+        assert_eq!("sev-low #00BFFF", severity_style(Severity::Low));
+        assert_eq!("sev-unknown #BEBEBE", severity_style(Severity::Unknown));
+    }
+
+    #[test]
+    fn ansi_plain() {
+        assert_eq!(
+            "failed to scan &lt;image&gt;<br />exit status: 1",
+            ansi_html("failed to scan <image>\nexit status: 1")
+        );
+    }
+
+    #[test]
+    fn ansi_colors() {
+        assert_eq!(
+            "Total: 2 (<span style=\"color:#cd0000\">CRITICAL: 1</span>, <span \
+             style=\"color:#cdcd00\">MEDIUM: 1</span>)",
+            ansi_html("Total: 2 (\x1b[31mCRITICAL: 1\x1b[0m, \x1b[33mMEDIUM: 1\x1b[m)")
+        );
+    }
+
+    #[test]
+    fn ansi_trivy_log() {
+        const LOG: &str = "2024-06-05T07:35:01Z\t\x1b[1;91mFATAL\x1b[0m\tFatal error\n  \
+                           \x1b[2mimage scan error\x1b[0m";
+
+        assert_eq!(
+            "2024-06-05T07:35:01Z\t<span \
+             style=\"font-weight:bold;color:#ff0000\">FATAL</span>\tFatal error<br />  image scan \
+             error",
+            ansi_html(LOG)
+        );
+    }
+
+    #[test]
+    fn ansi_spans_closed_at_line_end() {
+        assert_eq!(
+            "<span style=\"color:#00cd00;background-color:#000000\">ok</span><br /><span \
+             style=\"color:#00cd00;background-color:#000000\">still green</span>",
+            ansi_html("\x1b[32;40mok\nstill green")
+        );
+    }
+
+    #[test]
+    fn ansi_other_sequences_dropped() {
+        assert_eq!("progress done", ansi_html("progress\x1b[2K done\x1b[1G"));
+    }
+
+    #[test]
+    fn zero() {
+        assert_eq!("just now", humanize(Duration::zero()));
+        assert_eq!("just now", humanize(Duration::milliseconds(-999)));
+    }
+
+    #[test]
+    fn seconds() {
+        assert_eq!("1 second ago", humanize(Duration::seconds(1)));
+        assert_eq!("59 seconds ago", humanize(Duration::seconds(59)));
+        assert_eq!("in 59 seconds", humanize(Duration::seconds(-59)));
+    }
+
+    #[test]
+    fn minutes() {
+        assert_eq!("1 minute ago", humanize(Duration::seconds(60)));
+        assert_eq!("3 minutes ago", humanize(Duration::seconds(3 * 60 + 59)));
+        assert_eq!("59 minutes ago", humanize(Duration::seconds(60 * 60 - 1)));
+        assert_eq!("in 1 minute", humanize(Duration::seconds(-60)));
+    }
+
+    #[test]
+    fn hours() {
+        assert_eq!("1 hour ago", humanize(Duration::hours(1)));
+        assert_eq!(
+            "23 hours ago",
+            humanize(Duration::days(1) - Duration::seconds(1))
+        );
+        assert_eq!("in 2 hours", humanize(Duration::hours(-2)));
+    }
+
+    #[test]
+    fn days() {
+        assert_eq!("1 day ago", humanize(Duration::days(1)));
+        assert_eq!("30 days ago", humanize(Duration::days(30)));
+        assert_eq!("in 7 days", humanize(Duration::days(-7)));
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        LazyLock,
+        Mutex,
+        PoisonError,
+    },
+};
+
+use axum::{
+    Json,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use serde::{
+    Serialize,
+    Serializer,
+    ser::{
+        SerializeMap,
+        SerializeSeq,
+        SerializeStruct,
+        SerializeStructVariant,
+        SerializeTuple,
+        SerializeTupleStruct,
+        SerializeTupleVariant,
+    },
+};
+
+/// Casing of the field names in JSON returned by the API endpoints.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+pub(crate) enum JsonCase {
+    #[default]
+    #[value(name = "snake_case")]
+    #[serde(rename = "snake_case")]
+    Snake,
+
+    #[value(name = "camelCase")]
+    #[serde(rename = "camelCase")]
+    Camel,
+}
+
+/// JSON response with the field names in the configured casing.
+#[derive(Debug)]
+pub(super) struct ApiJson<T>(pub(super) JsonCase, pub(super) T);
+
+impl<T: Serialize> ApiJson<T> {
+    /// The value as JSON in the configured casing, e.g. for event streams.
+    pub(super) fn to_json(&self) -> serde_json::Result<String> {
+        match self.0 {
+            JsonCase::Snake => serde_json::to_string(&self.1),
+            JsonCase::Camel => serde_json::to_string(&CamelCase(&self.1)),
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for ApiJson<T> {
+    fn into_response(self) -> Response {
+        match self.0 {
+            JsonCase::Snake => Json(self.1).into_response(),
+            JsonCase::Camel => Json(CamelCase(&self.1)).into_response(),
+        }
+    }
+}
+
+/// Serializes the value as if every struct had `#[serde(rename_all =
+/// "camelCase")]`. Map keys, e.g. of labels, are data and kept as they are.
+struct CamelCase<'a, T: ?Sized>(&'a T);
+
+impl<T: Serialize + ?Sized> Serialize for CamelCase<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(CamelCaseSerializer(serializer))
+    }
+}
+
+/// Field names are static in serde so every converted name is leaked once,
+/// there is only a fixed number of field names.
+fn camel_case(field: &'static str) -> &'static str {
+    static NAMES: LazyLock<Mutex<HashMap<&'static str, &'static str>>> =
+        LazyLock::new(Mutex::default);
+
+    if !field.contains('_') {
+        return field;
+    }
+
+    let mut names = NAMES.lock().unwrap_or_else(PoisonError::into_inner);
+
+    names.entry(field).or_insert_with(|| {
+        let mut converted = String::with_capacity(field.len());
+        let mut upper = false;
+
+        for character in field.chars() {
+            if character == '_' {
+                upper = !converted.is_empty();
+            } else if upper {
+                converted.extend(character.to_uppercase());
+                upper = false;
+            } else {
+                converted.push(character);
+            }
+        }
+
+        Box::leak(converted.into_boxed_str())
+    })
+}
+
+struct CamelCaseSerializer<S>(S);
+
+struct Compound<C>(C);
+
+impl<S: Serializer> Serializer for CamelCaseSerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeStruct>;
+    type SerializeStructVariant = Compound<S::SerializeStructVariant>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_i64(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_u64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_some(&CamelCase(value))
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.0.serialize_newtype_struct(name, &CamelCase(value))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.0
+            .serialize_newtype_variant(name, variant_index, variant, &CamelCase(value))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.0.serialize_seq(len).map(Compound)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.0.serialize_tuple(len).map(Compound)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.0.serialize_tuple_struct(name, len).map(Compound)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.0
+            .serialize_tuple_variant(name, variant_index, variant, len)
+            .map(Compound)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        self.0.serialize_map(len).map(Compound)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        self.0.serialize_struct(name, len).map(Compound)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        self.0
+            .serialize_struct_variant(name, variant_index, variant, len)
+            .map(Compound)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+}
+
+impl<C: SerializeSeq> SerializeSeq for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.serialize_element(&CamelCase(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeTuple> SerializeTuple for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.serialize_element(&CamelCase(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeTupleStruct> SerializeTupleStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.serialize_field(&CamelCase(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeTupleVariant> SerializeTupleVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.serialize_field(&CamelCase(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeMap> SerializeMap for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Self::Error> {
+        self.0.serialize_key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Self::Error> {
+        self.0.serialize_value(&CamelCase(value))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeStruct> SerializeStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.0.serialize_field(camel_case(key), &CamelCase(value))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.0.skip_field(camel_case(key))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+impl<C: SerializeStructVariant> SerializeStructVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Self::Error> {
+        self.0.serialize_field(camel_case(key), &CamelCase(value))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Self::Error> {
+        self.0.skip_field(camel_case(key))
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        self.0.end()
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use crate::handler::{
+        labels::{
+            LabeledScan,
+            Labels,
+        },
+        tags::TagSummary,
+        trivy::SeverityCount,
+    };
+
+    use super::CamelCase;
+
+    #[test]
+    fn casing() {
+        let summary = TagSummary {
+            tag: "0.52.0".to_string(),
+            severity_count: Some(SeverityCount::default()),
+            risk_score: Some(0),
+            error: None,
+        };
+
+        let snake = serde_json::to_value(&summary).unwrap();
+        let camel = serde_json::to_value(CamelCase(&summary)).unwrap();
+
+        assert!(snake.get("severity_count").is_some());
+        assert!(snake.get("risk_score").is_some());
+        assert!(camel.get("severity_count").is_none());
+        assert_eq!(0, camel["severityCount"]["critical"]);
+        assert_eq!(0, camel["riskScore"]);
+        assert_eq!("0.52.0", camel["tag"]);
+        assert!(camel.get("error").is_none());
+
+        let scan = LabeledScan {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".to_string(),
+            labels: "owning_team=payments".parse::<Labels>().unwrap(),
+        };
+
+        // label keys are data and not renamed
+        let camel = serde_json::to_value(CamelCase(&scan)).unwrap();
+        assert_eq!("payments", camel["labels"]["owning_team"]);
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    net::IpAddr,
+    path::Path,
+    sync::Arc,
+};
+
+use chrono::{
+    DateTime,
+    Utc,
+};
+use eyre::{
+    Context,
+    Result,
+};
+use serde::Serialize;
+use tokio::{
+    fs::{
+        File,
+        OpenOptions,
+    },
+    io::{
+        AsyncWrite,
+        AsyncWriteExt,
+    },
+    sync::Mutex,
+};
+
+use crate::handler::{
+    response::TrivyInformation,
+    trivy::SeverityCount,
+};
+
+/// Destination that writes the audit log to stdout instead of a file.
+const STDOUT: &str = "-";
+
+/// Replaces credentials in audit records.
+const REDACTED: &str = "[redacted]";
+
+/// Append-only log of scan requests kept apart from the tracing logs, one
+/// JSON record per line.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AuditLog {
+    sink: Option<Arc<Mutex<Sink>>>,
+}
+
+#[derive(Debug)]
+enum Sink {
+    Stdout(tokio::io::Stdout),
+    File(File),
+}
+
+impl Sink {
+    fn writer(&mut self) -> &mut (dyn AsyncWrite + Unpin + Send) {
+        match self {
+            Self::Stdout(stdout) => stdout,
+            Self::File(file) => file,
+        }
+    }
+}
+
+/// Who scanned which image when and how the scan ended.
+#[derive(Debug, Serialize)]
+pub(super) struct AuditRecord {
+    pub(super) time: DateTime<Utc>,
+    pub(super) kind: &'static str,
+
+    /// Endpoint the scan was requested through, e.g. `api`.
+    pub(super) endpoint: &'static str,
+
+    pub(super) client_ip: Option<IpAddr>,
+
+    /// Registry user the image was pulled as, passwords are never recorded.
+    pub(super) user: Option<String>,
+
+    pub(super) image: String,
+
+    #[serde(flatten)]
+    pub(super) outcome: AuditOutcome,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub(super) enum AuditOutcome {
+    Scanned {
+        digest: Option<String>,
+
+        /// Served from the cache instead of scanning the image.
+        cached: bool,
+
+        severity_count: SeverityCount,
+    },
+
+    /// The scan ran but has no vulnerability summary, e.g. SBOMs, compliance
+    /// reports and plugin runs.
+    Completed,
+
+    Failed {
+        error: String,
+    },
+
+    /// The request was refused before scanning, e.g. a registry missing
+    /// from the allowlist.
+    Rejected {
+        status: u16,
+    },
+}
+
+impl AuditOutcome {
+    /// Vulnerability scan that finished, results older than `started` came
+    /// from the cache.
+    pub(super) fn scanned(information: &TrivyInformation, started: DateTime<Utc>) -> Self {
+        let point = information.trend_point();
+
+        Self::Scanned {
+            digest: point.digest,
+            cached: point.scan_time < started,
+            severity_count: point.severity_count,
+        }
+    }
+
+    /// Failure whose message does not contain the password.
+    pub(super) fn failed(error: &eyre::Report, password: &str) -> Self {
+        let error = format!("{error:#}");
+
+        Self::Failed {
+            error: if password.is_empty() {
+                error
+            } else {
+                error.replace(password, REDACTED)
+            },
+        }
+    }
+}
+
+impl AuditLog {
+    /// Appends to the file at the path or writes to stdout for `-`, without
+    /// a destination nothing is recorded.
+    pub(crate) async fn open(destination: Option<&Path>) -> Result<Self> {
+        let Some(destination) = destination else {
+            return Ok(Self::default());
+        };
+
+        let sink = if destination == Path::new(STDOUT) {
+            Sink::Stdout(tokio::io::stdout())
+        } else {
+            Sink::File(
+                OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(destination)
+                    .await
+                    .with_context(|| {
+                        format!("failed to open audit log {}", destination.display())
+                    })?,
+            )
+        };
+
+        Ok(Self {
+            sink: Some(Arc::new(Mutex::new(sink))),
+        })
+    }
+
+    /// Writes the record, failures are logged as the scan itself succeeded.
+    pub(super) async fn record(&self, record: &AuditRecord) {
+        let Some(sink) = &self.sink else {
+            return;
+        };
+
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+
+            Err(err) => {
+                tracing::error!("failed to serialize audit record: {err}");
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        let mut sink = sink.lock().await;
+        let writer = sink.writer();
+
+        if let Err(err) = async {
+            writer.write_all(&line).await?;
+            writer.flush().await
+        }
+        .await
+        {
+            tracing::error!("failed to write audit record: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use chrono::Utc;
+
+    use super::{
+        AuditLog,
+        AuditOutcome,
+        AuditRecord,
+    };
+
+    #[tokio::test]
+    async fn append() {
+        let path = std::env::temp_dir().join(format!(
+            "trivy-web-audit-{pid}.log",
+            pid = std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+
+        let record = |image: &str| AuditRecord {
+            time: Utc::now(),
+            kind: "scan",
+            endpoint: "api",
+            client_ip: Some("10.0.0.1".parse().unwrap()),
+            user: None,
+            image: image.to_string(),
+            outcome: AuditOutcome::failed(
+                &eyre::eyre!("unauthorized: hunter2 was rejected"),
+                "hunter2",
+            ),
+        };
+
+        AuditLog::open(Some(&path))
+            .await
+            .unwrap()
+            .record(&record("ghcr.io/aquasecurity/trivy:0.52.0"))
+            .await;
+
+        // reopening appends instead of truncating
+        AuditLog::open(Some(&path))
+            .await
+            .unwrap()
+            .record(&record("ghcr.io/aquasecurity/trivy:0.53.0"))
+            .await;
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines = content.lines().collect::<Vec<_>>();
+
+        assert_eq!(2, lines.len());
+
+        let first: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+
+        assert_eq!("scan", first["kind"]);
+        assert_eq!("10.0.0.1", first["client_ip"]);
+        assert_eq!("ghcr.io/aquasecurity/trivy:0.52.0", first["image"]);
+        assert_eq!("failed", first["outcome"]);
+        assert_eq!("unauthorized: [redacted] was rejected", first["error"]);
+        assert!(!content.contains("hunter2"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
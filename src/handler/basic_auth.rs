@@ -0,0 +1,102 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    http::{
+        HeaderMap,
+        HeaderValue,
+        Request,
+        Response,
+        StatusCode,
+        header::{
+            AUTHORIZATION,
+            WWW_AUTHENTICATE,
+        },
+    },
+};
+use base64::{
+    Engine,
+    engine::general_purpose::STANDARD,
+};
+use subtle::ConstantTimeEq;
+use tower_http::validate_request::ValidateRequest;
+
+/// Route that stays reachable without credentials so liveness probes keep
+/// working.
+const UNAUTHENTICATED_PATH: &str = "/healthz";
+
+/// Routes guarded by the admin bearer token, which uses the same
+/// `Authorization` header and so can not be combined with Basic auth.
+const ADMIN_PATHS: &[&str] = &["/debug/config"];
+const ADMIN_PREFIX: &str = "/api/admin/";
+
+const CHALLENGE: &str = r#"Basic realm="trivy-web""#;
+
+/// Credentials HTTP Basic auth requires for every route but `/healthz` and
+/// the admin endpoints.
+#[derive(Clone)]
+pub(crate) struct BasicAuth {
+    user: Arc<str>,
+    password: Arc<str>,
+}
+
+impl std::fmt::Debug for BasicAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BasicAuth")
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BasicAuth {
+    pub(crate) fn new(user: &str, password: &str) -> Self {
+        Self {
+            user: user.into(),
+            password: password.into(),
+        }
+    }
+
+    fn authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(credentials) = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split_once(' '))
+            .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("basic"))
+            .and_then(|(_, credentials)| STANDARD.decode(credentials.trim()).ok())
+        else {
+            return false;
+        };
+
+        String::from_utf8_lossy(&credentials)
+            .split_once(':')
+            .is_some_and(|(user, password)| {
+                // compare both fields in constant time so timing does not
+                // reveal how much of the credentials matched
+                (user.as_bytes().ct_eq(self.user.as_bytes())
+                    & password.as_bytes().ct_eq(self.password.as_bytes()))
+                .into()
+            })
+    }
+}
+
+fn exempt(path: &str) -> bool {
+    path == UNAUTHENTICATED_PATH || ADMIN_PATHS.contains(&path) || path.starts_with(ADMIN_PREFIX)
+}
+
+impl<B> ValidateRequest<B> for BasicAuth {
+    type ResponseBody = Body;
+
+    fn validate(&mut self, request: &mut Request<B>) -> Result<(), Response<Body>> {
+        if exempt(request.uri().path()) || self.authorized(request.headers()) {
+            return Ok(());
+        }
+
+        let mut response = Response::new(Body::empty());
+        *response.status_mut() = StatusCode::UNAUTHORIZED;
+        response
+            .headers_mut()
+            .insert(WWW_AUTHENTICATE, HeaderValue::from_static(CHALLENGE));
+
+        Err(response)
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    process::Output,
+    time::Duration,
+};
+
+use tokio::process::Command;
+
+/// Returned when an external command did not finish in time and was killed.
+#[derive(Debug)]
+pub(crate) struct CommandTimeout {
+    program: String,
+    timeout: Duration,
+}
+
+impl std::fmt::Display for CommandTimeout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{program} did not finish within {timeout}s and was killed",
+            program = self.program,
+            timeout = self.timeout.as_secs_f64()
+        )
+    }
+}
+
+impl std::error::Error for CommandTimeout {}
+
+impl CommandTimeout {
+    pub(crate) fn new(command: &Command, timeout: Duration) -> Self {
+        Self {
+            program: command
+                .as_std()
+                .get_program()
+                .to_string_lossy()
+                .into_owned(),
+            timeout,
+        }
+    }
+}
+
+/// Runs the command to completion, the child is killed when it does not
+/// finish within the timeout.
+pub(crate) async fn output(
+    command: &mut Command,
+    timeout: Option<Duration>,
+) -> eyre::Result<Output> {
+    command.kill_on_drop(true);
+
+    let Some(timeout) = timeout else {
+        return Ok(command.output().await?);
+    };
+
+    match tokio::time::timeout(timeout, command.output()).await {
+        Ok(output) => Ok(output?),
+
+        Err(_) => Err(CommandTimeout::new(command, timeout).into()),
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+pub(crate) mod test {
+    use std::{
+        os::unix::fs::PermissionsExt,
+        path::PathBuf,
+        time::Duration,
+    };
+
+    use tokio::process::Command;
+
+    use super::CommandTimeout;
+
+    /// Writes an executable shell script standing in for trivy or cosign.
+    pub(crate) fn fake_binary(name: &str, script: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("trivy-web-{name}-{pid}", pid = std::process::id()));
+
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        path
+    }
+
+    /// Fake trivy printing the fixture from `src/handler/resources/tests` as
+    /// the result for the image it scans, which is its last argument.
+    pub(crate) fn fake_trivy(name: &str, fixture: &str) -> PathBuf {
+        fake_binary(
+            name,
+            &format!(
+                r#"for image; do :; done
+sed 's|"ArtifactName": "[^"]*"|"ArtifactName": "'"$image"'"|' {manifest}/src/handler/resources/tests/{fixture}"#,
+                manifest = env!("CARGO_MANIFEST_DIR"),
+            ),
+        )
+    }
+
+    /// Executable that sleeps longer than any test timeout, standing in for
+    /// a hanging trivy or cosign.
+    pub(crate) fn sleeping_binary(name: &str) -> PathBuf {
+        fake_binary(name, "exec sleep 30")
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let binary = sleeping_binary("command");
+
+        let started = std::time::Instant::now();
+        let err = super::output(&mut Command::new(&binary), Some(Duration::from_millis(100)))
+            .await
+            .unwrap_err();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(err.downcast_ref::<CommandTimeout>().is_some());
+        assert!(err.to_string().contains("did not finish within 0.1s"));
+    }
+
+    #[tokio::test]
+    async fn finishes() {
+        let output = super::output(&mut Command::new("true"), Some(Duration::from_secs(5)))
+            .await
+            .unwrap();
+
+        assert!(output.status.success());
+    }
+}
@@ -0,0 +1,146 @@
+use std::collections::BTreeSet;
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use utoipa::ToSchema;
+
+use crate::handler::response::TrivyInformation;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct CompareRequest {
+    pub(super) source: String,
+    pub(super) mirror: String,
+}
+
+/// Whether a mirrored image is the same as its source, and if not how their
+/// vulnerabilities differ.
+#[derive(Debug, Serialize, PartialEq, ToSchema)]
+pub(super) struct Comparison {
+    pub(super) source: String,
+    pub(super) mirror: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) source_digest: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) mirror_digest: Option<String>,
+
+    /// Both references resolved to the same digest, the vulnerabilities are
+    /// not compared then.
+    pub(super) identical: bool,
+
+    pub(super) only_in_source: Vec<Finding>,
+    pub(super) only_in_mirror: Vec<Finding>,
+}
+
+/// A vulnerability of a package, findings of both scans are matched by it.
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord, ToSchema)]
+pub(super) struct Finding {
+    pub(super) id: String,
+    pub(super) pkg_name: String,
+    pub(super) installed_version: String,
+}
+
+fn findings(information: &TrivyInformation) -> BTreeSet<Finding> {
+    information
+        .vulnerabilities()
+        .iter()
+        .map(|vulnerability| Finding {
+            id: vulnerability.id.clone(),
+            pkg_name: vulnerability.pkg_name.clone(),
+            installed_version: vulnerability.installed_version.clone(),
+        })
+        .collect()
+}
+
+pub(super) fn compare(
+    request: CompareRequest,
+    source: &TrivyInformation,
+    mirror: &TrivyInformation,
+) -> Comparison {
+    let source_digest = source.digest().map(ToString::to_string);
+    let mirror_digest = mirror.digest().map(ToString::to_string);
+
+    let identical = source_digest.is_some() && source_digest == mirror_digest;
+
+    let (only_in_source, only_in_mirror) = if identical {
+        (Vec::new(), Vec::new())
+    } else {
+        let source = findings(source);
+        let mut mirror = findings(mirror);
+
+        let only_in_source = source
+            .into_iter()
+            .filter(|finding| !mirror.remove(finding))
+            .collect();
+
+        (only_in_source, mirror.into_iter().collect())
+    };
+
+    Comparison {
+        source: request.source,
+        mirror: request.mirror,
+        source_digest,
+        mirror_digest,
+        identical,
+        only_in_source,
+        only_in_mirror,
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use super::{
+        CompareRequest,
+        compare,
+    };
+    use crate::handler::{
+        response::TrivyInformation,
+        trivy::TrivyResult,
+    };
+
+    fn information(data: &str) -> TrivyInformation {
+        TrivyInformation::new(serde_json::from_str::<TrivyResult>(data).unwrap(), &[])
+    }
+
+    fn request() -> CompareRequest {
+        CompareRequest {
+            source: "ghcr.io/aquasecurity/trivy:0.50.0".to_string(),
+            mirror: "registry.example.com/mirror/trivy:0.50.0".to_string(),
+        }
+    }
+
+    #[test]
+    fn identical() {
+        let source = information(include_str!("resources/tests/trivy_output.json"));
+        let mirror = information(include_str!("resources/tests/trivy_output.json"));
+
+        let comparison = compare(request(), &source, &mirror);
+
+        assert!(comparison.identical);
+        assert_eq!(comparison.source_digest, comparison.mirror_digest);
+        assert!(comparison.only_in_source.is_empty());
+        assert!(comparison.only_in_mirror.is_empty());
+    }
+
+    #[test]
+    fn different_digests() {
+        let source = information(include_str!("resources/tests/trivy_output.json"));
+        let mirror = information(include_str!("resources/tests/trivy_output2.json"));
+
+        let comparison = compare(request(), &source, &mirror);
+
+        assert!(!comparison.identical);
+        assert_ne!(comparison.source_digest, comparison.mirror_digest);
+        assert!(!comparison.only_in_source.is_empty());
+        assert!(
+            comparison
+                .only_in_source
+                .iter()
+                .all(|finding| !comparison.only_in_mirror.contains(finding))
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    num::NonZeroUsize,
+    path::PathBuf,
+};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+use super::{
+    JsonCase,
+    OutputFormat,
+    Password,
+    trivy::{
+        CvssVersion,
+        Scanner,
+        SeverityMapping,
+        SeverityWeights,
+    },
+};
+use crate::args::Args;
+
+/// Configuration resolved from flags and environment variables as served by
+/// the debug endpoint. Secrets are shown as `REDACTED`.
+#[derive(Debug, Default, Clone, Serialize)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "mirrors the command line switches"
+)]
+pub(crate) struct EffectiveConfig {
+    config: Option<PathBuf>,
+    profile: Option<String>,
+    log_level: String,
+    binding: String,
+    tls_certificate: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    tls_min_version: String,
+    tls_cipher_suites: Vec<String>,
+    http2: bool,
+    max_connections: Option<usize>,
+    trusted_proxies: Vec<String>,
+    redis_server: Option<String>,
+    cache_ttl: i64,
+    cache_batch: Option<u64>,
+    cache_fresh_for: Option<i64>,
+    refresh_cooldown: Option<i64>,
+    trend_retention: Option<i64>,
+    event_stream: Option<String>,
+    read_only: bool,
+    admin_token: Option<Password>,
+    auth_user: Option<String>,
+    auth_password: Option<Password>,
+    result_signing_key: Option<Password>,
+    disable_docker_info: bool,
+    cosign_private_infrastructure: bool,
+    cosign_binary: PathBuf,
+    registry_timeout: Option<u64>,
+    registry_concurrency: Option<usize>,
+    cosign_concurrency: Option<usize>,
+    fan_out_limit: Option<usize>,
+    startup_registry_check: bool,
+    startup_registry_canary: String,
+    warmup_db: bool,
+    fail_fast: bool,
+    canonical_ids: bool,
+    severity_weights: SeverityWeights,
+    severity_mapping: Option<SeverityMapping>,
+    cvss_version: CvssVersion,
+    severity_fallback: Vec<String>,
+    api_json_case: JsonCase,
+    enabled_formats: Vec<OutputFormat>,
+    max_references: usize,
+    max_report_size: usize,
+    trivy_binary: PathBuf,
+    scan_timeout: u64,
+    servers: Vec<String>,
+    allowed_trivy_servers: Vec<String>,
+    allowed_registries: Vec<String>,
+    server_concurrency: Option<usize>,
+    scan_concurrency: Option<usize>,
+    trivy_parallel: Option<usize>,
+    sbom_concurrency: usize,
+    trivy_scanners: Vec<Scanner>,
+    trivy_plugins: Vec<String>,
+    trivy_compliance: Vec<String>,
+    ignore_policy: Option<PathBuf>,
+    trivyignore: Option<PathBuf>,
+    trusted_digests: Option<PathBuf>,
+    audit_log: Option<PathBuf>,
+    pull_secret: Option<PathBuf>,
+    trivy_db_max_age: i64,
+    credential_helpers: BTreeMap<String, PathBuf>,
+    credential_helper_ttl: i64,
+}
+
+impl EffectiveConfig {
+    pub(crate) fn new(args: &Args) -> Self {
+        Self {
+            config: args.config.clone(),
+            profile: args.profile.clone(),
+            log_level: args.log_level.to_string(),
+            binding: args.binding.to_string(),
+            tls_certificate: args.tls_certificate.clone(),
+            tls_key: args.tls_key.clone(),
+            tls_min_version: args
+                .tls_min_version
+                .to_possible_value()
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            tls_cipher_suites: args.tls_cipher_suites.clone(),
+            http2: args.http2,
+            max_connections: args.max_connections.map(NonZeroUsize::get),
+            trusted_proxies: args
+                .trusted_proxies
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            redis_server: args.redis_server.as_deref().map(redact_url),
+            cache_ttl: args.redis_ttl,
+            cache_batch: args.cache_batch,
+            cache_fresh_for: args.cache_fresh_for,
+            refresh_cooldown: args.refresh_cooldown,
+            trend_retention: args.trend_retention,
+            event_stream: args.event_stream.clone(),
+            read_only: args.read_only,
+            admin_token: args.admin_token.clone().map(Password),
+            auth_user: args.auth_user.clone(),
+            auth_password: args.auth_password.clone().map(Password),
+            result_signing_key: args.result_signing_key.clone().map(Password),
+            disable_docker_info: args.disable_docker_info,
+            cosign_private_infrastructure: args.cosign_private_infrastructure,
+            cosign_binary: args.cosign_binary.clone(),
+            registry_timeout: args.registry_timeout,
+            registry_concurrency: args.registry_concurrency.map(NonZeroUsize::get),
+            cosign_concurrency: args.cosign_concurrency.map(NonZeroUsize::get),
+            fan_out_limit: args.fan_out_limit.map(NonZeroUsize::get),
+            startup_registry_check: args.startup_registry_check,
+            startup_registry_canary: args.startup_registry_canary.to_string(),
+            warmup_db: args.warmup_db,
+            fail_fast: args.fail_fast,
+            canonical_ids: args.canonical_ids,
+            severity_weights: args.severity_weights,
+            severity_mapping: args.severity_mapping.clone(),
+            cvss_version: args.cvss_version,
+            severity_fallback: args.severity_fallback.clone(),
+            api_json_case: args.api_json_case,
+            enabled_formats: args.enabled_formats.clone(),
+            max_references: args.max_references,
+            max_report_size: args.max_report_size,
+            trivy_binary: args.trivy_binary.clone(),
+            scan_timeout: args.scan_timeout,
+            servers: args.server.clone(),
+            allowed_trivy_servers: args.allowed_trivy_server.clone(),
+            allowed_registries: args.allowed_registry.clone(),
+            server_concurrency: args.server_concurrency.map(NonZeroUsize::get),
+            scan_concurrency: args.scan_concurrency.map(NonZeroUsize::get),
+            trivy_parallel: args.trivy_parallel,
+            sbom_concurrency: args.sbom_concurrency.get(),
+            trivy_scanners: args.trivy_scanners.clone(),
+            trivy_plugins: args.trivy_plugin.clone(),
+            trivy_compliance: args.trivy_compliance.clone(),
+            ignore_policy: args.ignore_policy.clone(),
+            trivyignore: args.trivyignore.clone(),
+            trusted_digests: args.trusted_digests.clone(),
+            audit_log: args.audit_log.clone(),
+            pull_secret: args.pull_secret.clone(),
+            trivy_db_max_age: args.trivy_db_max_age,
+            credential_helpers: args.credential_helper.iter().cloned().collect(),
+            credential_helper_ttl: args.credential_helper_ttl,
+        }
+    }
+
+    /// The read-only mode can be toggled at runtime so the current mode is
+    /// reported instead of the configured one.
+    pub(super) fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+}
+
+/// Replaces the password of a url, e.g. of the redis server, unparseable
+/// urls are redacted completely.
+fn redact_url(url: &str) -> String {
+    let Ok(mut url) = url::Url::parse(url) else {
+        return "REDACTED".to_string();
+    };
+
+    if url.password().is_some() && url.set_password(Some("REDACTED")).is_err() {
+        return "REDACTED".to_string();
+    }
+
+    url.to_string()
+}
@@ -1,11 +1,16 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    num::NonZeroUsize,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
 
 use chrono::{
     DateTime,
     Utc,
 };
 use docker_registry_client::{
-    Client as DockerRegistryClient,
     ClientError as DockerClientError,
     Image,
     Manifest as DockerManifest,
@@ -18,7 +23,10 @@ use serde::{
     Deserialize,
     Serialize,
 };
-use tokio::process::Command;
+use tokio::{
+    process::Command,
+    sync::Semaphore,
+};
 use tracing::{
     Instrument,
     info_span,
@@ -29,18 +37,106 @@ use x509_parser::{
     certificate::X509Certificate,
     parse_x509_certificate,
     pem::parse_x509_pem,
+    prelude::FromDer,
+    x509::SubjectPublicKeyInfo,
+};
+
+use crate::handler::{
+    command,
+    registry_client::RegistryClient,
+    single_flight::SingleFlight,
 };
 
+/// Key references cosign resolves itself and that can not be checked locally.
+const REMOTE_KEY_PREFIXES: &[&str] = &[
+    "awskms://",
+    "azurekms://",
+    "gcpkms://",
+    "gitlab://",
+    "hashivault://",
+    "k8s://",
+    "pkcs11:",
+];
+
+/// Extensions Fulcio adds to its certificates, see
+/// <https://github.com/sigstore/fulcio/blob/main/docs/oid-info.md>.
+const FULCIO_EXTENSIONS: &[(&str, &str)] = &[
+    ("1.3.6.1.4.1.57264.1.1", "Issuer"),
+    ("1.3.6.1.4.1.57264.1.2", "GitHub Workflow Trigger"),
+    ("1.3.6.1.4.1.57264.1.3", "GitHub Workflow SHA"),
+    ("1.3.6.1.4.1.57264.1.4", "GitHub Workflow Name"),
+    ("1.3.6.1.4.1.57264.1.5", "GitHub Workflow Repository"),
+    ("1.3.6.1.4.1.57264.1.6", "GitHub Workflow Ref"),
+    ("1.3.6.1.4.1.57264.1.7", "OtherName SAN"),
+    ("1.3.6.1.4.1.57264.1.8", "Issuer (V2)"),
+    ("1.3.6.1.4.1.57264.1.9", "Build Signer URI"),
+    ("1.3.6.1.4.1.57264.1.10", "Build Signer Digest"),
+    ("1.3.6.1.4.1.57264.1.11", "Runner Environment"),
+    ("1.3.6.1.4.1.57264.1.12", "Source Repository URI"),
+    ("1.3.6.1.4.1.57264.1.13", "Source Repository Digest"),
+    ("1.3.6.1.4.1.57264.1.14", "Source Repository Ref"),
+    ("1.3.6.1.4.1.57264.1.15", "Source Repository Identifier"),
+    ("1.3.6.1.4.1.57264.1.16", "Source Repository Owner URI"),
+    (
+        "1.3.6.1.4.1.57264.1.17",
+        "Source Repository Owner Identifier",
+    ),
+    ("1.3.6.1.4.1.57264.1.18", "Build Config URI"),
+    ("1.3.6.1.4.1.57264.1.19", "Build Config Digest"),
+    ("1.3.6.1.4.1.57264.1.20", "Build Trigger"),
+    ("1.3.6.1.4.1.57264.1.21", "Run Invocation URI"),
+    (
+        "1.3.6.1.4.1.57264.1.22",
+        "Source Repository Visibility At Signing",
+    ),
+];
+
+/// Friendly name of a Fulcio certificate extension, other OIDs are returned
+/// as they are.
+pub(crate) fn extension_name(oid: &str) -> &str {
+    FULCIO_EXTENSIONS
+        .iter()
+        .find(|(known, _)| *known == oid)
+        .map_or(oid, |(_, name)| name)
+}
+
 #[derive(Debug)]
 pub(crate) enum CertificateError {
     InvalidNotBefore,
     InvalidNotAfter,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum KeyValidationError {
+    MissingKeyOrIdentity,
+    MissingIssuer,
+    InvalidIssuer,
+    InvalidPublicKey,
+    UnusableKeyReference,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub(crate) struct Cosign {
     pub(crate) manifest_location: Url,
     pub(crate) signatures: Vec<Signature>,
+
+    /// PEM encoded signing certificates of keyless signatures as found in
+    /// the signature manifest.
+    #[serde(default)]
+    pub(crate) certificates: Vec<String>,
+}
+
+impl Cosign {
+    /// All signing certificates as one PEM bundle.
+    pub(crate) fn certificate_bundle(&self) -> String {
+        self.certificates
+            .iter()
+            .fold(String::new(), |mut bundle, certificate| {
+                bundle.push_str(certificate.trim_end());
+                bundle.push('\n');
+                bundle
+            })
+    }
 }
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Clone)]
@@ -54,9 +150,12 @@ pub(crate) struct Certificate {
     pub(crate) not_after: DateTime<Utc>,
 
     pub(crate) extensions: BTreeMap<String, String>,
+
+    /// The extensions keyed by their friendly name for display.
+    pub(crate) named_extensions: BTreeMap<String, String>,
 }
 
-#[derive(Debug, PartialEq, Ord, Eq, PartialOrd, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Ord, Eq, PartialOrd, Serialize, Deserialize)]
 pub(crate) struct Signature {
     pub(crate) issuer: String,
     pub(crate) identity: String,
@@ -114,7 +213,7 @@ impl TryFrom<X509Certificate<'_>> for Certificate {
             .filter_map(|entry| entry.attr_value().as_str().map(ToString::to_string).ok())
             .collect::<Vec<_>>();
 
-        let extensions = x509
+        let extensions: BTreeMap<String, String> = x509
             .extensions()
             .iter()
             .map(|extension| {
@@ -129,6 +228,11 @@ impl TryFrom<X509Certificate<'_>> for Certificate {
             })
             .collect();
 
+        let named_extensions = extensions
+            .iter()
+            .map(|(oid, value)| (extension_name(oid).to_string(), value.clone()))
+            .collect();
+
         let validity = x509.validity();
 
         let not_before = validity.not_before.timestamp();
@@ -147,6 +251,7 @@ impl TryFrom<X509Certificate<'_>> for Certificate {
             not_before,
             not_after,
             extensions,
+            named_extensions,
         })
     }
 }
@@ -162,6 +267,42 @@ impl std::fmt::Display for CertificateError {
 
 impl std::error::Error for CertificateError {}
 
+impl std::fmt::Display for KeyValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MissingKeyOrIdentity => write!(f, "Either a key or an identity is required"),
+            Self::MissingIssuer => write!(f, "An issuer is required when using an identity"),
+            Self::InvalidIssuer => write!(f, "Issuer is not a valid url"),
+            Self::InvalidPublicKey => write!(f, "Key is not a PEM encoded public key"),
+            Self::UnusableKeyReference => write!(f, "Key reference can not be loaded by cosign"),
+        }
+    }
+}
+
+impl std::error::Error for KeyValidationError {}
+
+/// Raw PEM certificates from the layer annotations of a signature manifest,
+/// duplicates are removed.
+fn certificate_pems(manifest: &DockerManifest) -> Vec<String> {
+    let DockerManifest::Image(manifest) = manifest else {
+        return Vec::new();
+    };
+
+    let mut certificates = Vec::new();
+
+    for certificate in manifest
+        .layers
+        .iter()
+        .filter_map(|layer| layer.annotations.get("dev.sigstore.cosign/certificate"))
+    {
+        if !certificates.contains(certificate) {
+            certificates.push(certificate.clone());
+        }
+    }
+
+    certificates
+}
+
 fn signature_from_manifest(manifest: DockerManifest) -> Result<Vec<Signature>, eyre::Error> {
     let DockerManifest::Image(manifest) = manifest else {
         return Err(eyre::Report::msg("Manifest is not a single manifest"));
@@ -220,7 +361,7 @@ fn signature_from_manifest(manifest: DockerManifest) -> Result<Vec<Signature>, e
 
 #[tracing::instrument]
 pub(crate) async fn cosign_manifest(
-    client: &DockerRegistryClient,
+    client: &RegistryClient,
     image: &Image,
     digest: &str,
 ) -> Result<Option<Cosign>, eyre::Error> {
@@ -230,7 +371,11 @@ pub(crate) async fn cosign_manifest(
         .get_manifest_url(&manifest_location, image)
         .instrument(info_span!("get manifest"))
         .await
-        .map(|response| signature_from_manifest(response.manifest));
+        .map(|response| {
+            let certificates = certificate_pems(&response.manifest);
+
+            (signature_from_manifest(response.manifest), certificates)
+        });
 
     let manifest = match manifest {
         Ok(manifest) => Ok(manifest),
@@ -242,39 +387,192 @@ pub(crate) async fn cosign_manifest(
     }
     .context("Failed to get manifest")?;
 
+    let (signatures, certificates) = manifest;
+
     Ok(Some(Cosign {
         manifest_location,
-        signatures: manifest.context("Failed to parse cosign signature from manifest")?,
+        signatures: signatures.context("Failed to parse cosign signature from manifest")?,
+        certificates,
     }))
 }
 
+/// Shares signature manifest fetches between concurrent requests for the
+/// same image and limits how many run at once.
+#[derive(Debug, Clone)]
+pub(crate) struct CosignFetches {
+    in_flight: SingleFlight<Option<Cosign>>,
+    permits: Arc<Semaphore>,
+}
+
+impl Default for CosignFetches {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl CosignFetches {
+    pub(crate) fn new(concurrency: Option<NonZeroUsize>) -> Self {
+        Self {
+            in_flight: SingleFlight::default(),
+            permits: Arc::new(Semaphore::new(
+                concurrency.map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get),
+            )),
+        }
+    }
+
+    /// Runs the fetch for the image digest unless one is already running,
+    /// in which case its output is shared.
+    pub(crate) async fn fetch(
+        &self,
+        image: &Image,
+        digest: &str,
+        fetch: impl Future<Output = Result<Option<Cosign>>>,
+    ) -> Result<Option<Cosign>> {
+        // manifest fetches are large, boxing keeps the futures of all
+        // callers small
+        let fetch = Box::pin(fetch);
+
+        self.in_flight
+            .run(&digest_reference(image, digest), async {
+                let _permit = self
+                    .permits
+                    .acquire()
+                    .instrument(info_span!("wait for cosign slot"))
+                    .await
+                    .context("cosign semaphore closed")?;
+
+                fetch.await
+            })
+            .await
+    }
+}
+
+/// Reference that pins the image to the digest its tag resolved to, e.g.
+/// `ghcr.io/aquasecurity/trivy@sha256:...`.
+pub(crate) fn digest_reference(image: &Image, digest: &str) -> String {
+    format!(
+        "{registry}/{namespace}{repository}{name}@{digest}",
+        registry = image.registry.registry_domain(),
+        namespace = match &image.namespace {
+            Some(namespace) => format!("{namespace}/"),
+            None => String::new(),
+        },
+        repository = match &image.repository {
+            Some(repository) => format!("{repository}/"),
+            None => String::new(),
+        },
+        name = image.image_name.name,
+    )
+}
+
+/// What cosign checks the signatures against.
+#[derive(Debug, PartialEq, Eq)]
+enum Verifier<'a> {
+    Key(&'a str),
+
+    /// Keyless signatures with a certificate issued by the OIDC issuer to an
+    /// identity matching the regexp, e.g. a GitHub Actions workflow.
+    Identity {
+        identity_regexp: &'a str,
+        issuer: &'a str,
+    },
+}
+
+impl<'a> Verifier<'a> {
+    /// A key takes precedence over an identity, an identity is only usable
+    /// together with its issuer.
+    fn new(
+        key: Option<&'a str>,
+        identity: Option<&'a str>,
+        issuer: Option<&'a str>,
+    ) -> Result<Self, KeyValidationError> {
+        let non_empty =
+            |value: Option<&'a str>| value.map(str::trim).filter(|value| !value.is_empty());
+
+        if let Some(key) = non_empty(key) {
+            return Ok(Self::Key(key));
+        }
+
+        let identity_regexp =
+            non_empty(identity).ok_or(KeyValidationError::MissingKeyOrIdentity)?;
+        let issuer = non_empty(issuer).ok_or(KeyValidationError::MissingIssuer)?;
+
+        Ok(Self::Identity {
+            identity_regexp,
+            issuer,
+        })
+    }
+}
+
+fn verify_command(
+    binary: &Path,
+    verifier: &Verifier<'_>,
+    reference: &str,
+    private_infrastructure: bool,
+) -> Command {
+    let mut command = Command::new(binary);
+
+    command
+        .arg("verify")
+        .arg(format!("--private-infrastructure={private_infrastructure}"))
+        .arg("--output=json");
+
+    match verifier {
+        Verifier::Key(key) => {
+            command.arg("--key").arg(key);
+        }
+
+        Verifier::Identity {
+            identity_regexp,
+            issuer,
+        } => {
+            command
+                .arg("--certificate-identity-regexp")
+                .arg(identity_regexp)
+                .arg("--certificate-oidc-issuer")
+                .arg(issuer);
+        }
+    }
+
+    command.arg(reference);
+
+    command
+}
+
+/// Verifies the signatures of the image with a key or, for keyless
+/// signatures, with an identity regexp and its OIDC issuer.
 #[tracing::instrument]
 pub(crate) async fn cosign_verify(
-    cosign_key: &str,
-    image: &Image,
+    cosign_key: Option<&str>,
+    identity: Option<&str>,
+    issuer: Option<&str>,
+    reference: &str,
+    binary: &Path,
+    private_infrastructure: bool,
+    timeout: Option<Duration>,
 ) -> Result<CosignVerify, eyre::Error> {
-    let output = Command::new("cosign")
-        .arg("verify")
-        .arg("--private-infrastructure=true")
-        .arg("--output=json")
-        .arg("--key")
-        .arg(cosign_key)
-        .arg(image.to_string())
-        .output()
-        .instrument(info_span!("running cosign verify"))
-        .await
-        .context("Failed to run cosign verify")?;
+    let verifier = Verifier::new(cosign_key, identity, issuer)?;
 
-    if !output.status.success() {
-        let message =
-            String::from_utf8(output.stderr).context("Failed to convert cosign stderr to utf8")?;
+    let output = command::output(
+        &mut verify_command(binary, &verifier, reference, private_infrastructure),
+        timeout,
+    )
+    .instrument(info_span!("running cosign verify"))
+    .await
+    .context("Failed to run cosign verify")?;
+
+    verify_output(&output)
+}
 
+/// Parses the output of cosign verify. Stderr is only shown to humans so
+/// invalid utf8 is replaced instead of hiding cosign's message.
+fn verify_output(output: &std::process::Output) -> Result<CosignVerify, eyre::Error> {
+    let message = String::from_utf8_lossy(&output.stderr).into_owned();
+
+    if !output.status.success() {
         return Err(eyre::Report::msg(message));
     }
 
-    let message =
-        String::from_utf8(output.stderr).context("Failed to convert cosign stderr utf8")?;
-
     let signature: Vec<VerifySignature> = serde_json::from_slice(output.stdout.as_slice())
         .context("Failed to parse cosign output json")?;
 
@@ -284,6 +582,86 @@ pub(crate) async fn cosign_verify(
     })
 }
 
+/// Checks that a key or an identity/issuer pair is usable by cosign without
+/// verifying an image. Keys are PEM encoded public keys sent with the request
+/// and are never read from the local filesystem. Remote key references are
+/// loaded with `cosign public-key`, `https://` keys are only checked to be
+/// valid urls.
+pub(crate) async fn validate_key(
+    key: Option<&str>,
+    identity: Option<&str>,
+    issuer: Option<&str>,
+    binary: &Path,
+    timeout: Option<Duration>,
+) -> Result<(), KeyValidationError> {
+    let key = key.map(str::trim).filter(|key| !key.is_empty());
+    let identity = identity
+        .map(str::trim)
+        .filter(|identity| !identity.is_empty());
+    let issuer = issuer.map(str::trim).filter(|issuer| !issuer.is_empty());
+
+    if let Some(key) = key {
+        if REMOTE_KEY_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+        {
+            return load_key_reference(key, binary, timeout).await;
+        }
+
+        if key.starts_with("https://") {
+            return Url::parse(key)
+                .map(|_| ())
+                .map_err(|_| KeyValidationError::InvalidPublicKey);
+        }
+
+        return validate_public_key_pem(key.as_bytes());
+    }
+
+    if identity.is_none() {
+        return Err(KeyValidationError::MissingKeyOrIdentity);
+    }
+
+    let issuer = issuer.ok_or(KeyValidationError::MissingIssuer)?;
+
+    Url::parse(issuer).map_err(|_| KeyValidationError::InvalidIssuer)?;
+
+    Ok(())
+}
+
+/// Resolves the public key of a KMS or other remote key reference. Cosign's
+/// output is not logged or returned as it may contain the reference.
+async fn load_key_reference(
+    key: &str,
+    binary: &Path,
+    timeout: Option<Duration>,
+) -> Result<(), KeyValidationError> {
+    let mut command = Command::new(binary);
+    command.arg("public-key").arg("--key").arg(key);
+
+    let output = command::output(&mut command, timeout)
+        .instrument(info_span!("running cosign public-key"))
+        .await;
+
+    match output {
+        Ok(output) if output.status.success() => Ok(()),
+        _ => Err(KeyValidationError::UnusableKeyReference),
+    }
+}
+
+/// Any problem with the key is reported as the same error.
+fn validate_public_key_pem(contents: &[u8]) -> Result<(), KeyValidationError> {
+    let (_, pem) = parse_x509_pem(contents).map_err(|_| KeyValidationError::InvalidPublicKey)?;
+
+    if pem.label != "PUBLIC KEY" {
+        return Err(KeyValidationError::InvalidPublicKey);
+    }
+
+    SubjectPublicKeyInfo::from_der(&pem.contents)
+        .map_err(|_| KeyValidationError::InvalidPublicKey)?;
+
+    Ok(())
+}
+
 #[tracing::instrument]
 fn triangulate(image: &Image, digest: &str) -> Result<Url> {
     // quay.io/jetstack/cert-manager-controller:
@@ -307,18 +685,204 @@ fn triangulate(image: &Image, digest: &str) -> Result<Url> {
 #[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
 #[expect(clippy::todo, reason = "using todo in tests is fine")]
 mod test {
+    use std::{
+        num::NonZeroUsize,
+        path::Path,
+        sync::{
+            Arc,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+        },
+        time::Duration,
+    };
+
     use docker_registry_client::Manifest as DockerManifest;
     use pretty_assertions::assert_eq;
 
-    use crate::handler::cosign::{
-        cosign_manifest,
-        signature_from_manifest,
+    use crate::handler::{
+        command::{
+            CommandTimeout,
+            test::{
+                fake_binary,
+                sleeping_binary,
+            },
+        },
+        cosign::{
+            Cosign,
+            CosignFetches,
+            KeyValidationError,
+            cosign_manifest,
+            digest_reference,
+            signature_from_manifest,
+            validate_key,
+        },
+        registry_client::RegistryClient,
     };
 
+    #[test]
+    fn verify_invalid_utf8_stderr() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let output = std::process::Output {
+            status: std::process::ExitStatus::from_raw(1 << 8),
+            stdout: Vec::new(),
+            stderr: b"Error: no matching signatures: \xff\xfe\n".to_vec(),
+        };
+
+        let err = super::verify_output(&output).unwrap_err();
+
+        assert_eq!(
+            "Error: no matching signatures: \u{fffd}\u{fffd}\n",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn verify_digest_pinned() {
+        const DIGEST: &str =
+            "sha256:4704989dd70b1b8e8a87ee4e1b7b3a9f4aa5d5a7d0c0e4c4d1fa7b9e42f3a1c2";
+
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+        let reference = digest_reference(&image, DIGEST);
+
+        assert_eq!(format!("ghcr.io/aquasecurity/trivy@{DIGEST}"), reference);
+
+        let command = super::verify_command(
+            Path::new("cosign"),
+            &super::Verifier::Key("cosign.pub"),
+            &reference,
+            true,
+        );
+        let args = command.as_std().get_args().collect::<Vec<_>>();
+
+        assert_eq!(Some(&reference.as_ref()), args.last());
+        assert!(
+            !args
+                .iter()
+                .any(|arg| arg.to_string_lossy().contains("0.52.0"))
+        );
+    }
+
+    #[test]
+    fn verify_private_infrastructure() {
+        let args = |private_infrastructure| {
+            super::verify_command(
+                Path::new("cosign"),
+                &super::Verifier::Key("cosign.pub"),
+                "alpine:latest",
+                private_infrastructure,
+            )
+            .as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+        };
+
+        assert!(args(true).contains(&"--private-infrastructure=true".to_string()));
+        assert!(args(false).contains(&"--private-infrastructure=false".to_string()));
+    }
+
+    #[test]
+    fn verify_keyless() {
+        let verifier = super::Verifier::new(
+            Some(" "),
+            Some("^https://github.com/aquasecurity/trivy/"),
+            Some("https://token.actions.githubusercontent.com"),
+        )
+        .unwrap();
+
+        let args = super::verify_command(Path::new("cosign"), &verifier, "alpine:latest", false)
+            .as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "verify",
+                "--private-infrastructure=false",
+                "--output=json",
+                "--certificate-identity-regexp",
+                "^https://github.com/aquasecurity/trivy/",
+                "--certificate-oidc-issuer",
+                "https://token.actions.githubusercontent.com",
+                "alpine:latest",
+            ],
+            args
+        );
+    }
+
+    #[test]
+    fn fulcio_extension_names() {
+        assert_eq!(
+            "GitHub Workflow Trigger",
+            super::extension_name("1.3.6.1.4.1.57264.1.2")
+        );
+        assert_eq!(
+            "Build Signer URI",
+            super::extension_name("1.3.6.1.4.1.57264.1.9")
+        );
+        assert_eq!(
+            "Source Repository URI",
+            super::extension_name("1.3.6.1.4.1.57264.1.12")
+        );
+        assert_eq!("2.5.29.17", super::extension_name("2.5.29.17"));
+        assert_eq!(
+            "1.3.6.1.4.1.57264.1.99",
+            super::extension_name("1.3.6.1.4.1.57264.1.99")
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_timeout() {
+        let binary = sleeping_binary("cosign");
+
+        let err = super::cosign_verify(
+            Some("cosign.pub"),
+            None,
+            None,
+            "alpine:latest",
+            &binary,
+            false,
+            Some(Duration::from_millis(100)),
+        )
+        .await
+        .unwrap_err();
+
+        assert!(err.downcast_ref::<CommandTimeout>().is_some());
+    }
+
+    #[tokio::test]
+    async fn verify_without_key_or_identity() {
+        let err = super::cosign_verify(
+            None,
+            None,
+            Some("https://issuer.example.com"),
+            "alpine:latest",
+            Path::new("cosign"),
+            false,
+            None,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            Some(&KeyValidationError::MissingKeyOrIdentity),
+            err.downcast_ref::<KeyValidationError>()
+        );
+
+        assert_eq!(
+            Err(KeyValidationError::MissingIssuer),
+            super::Verifier::new(None, Some("user@example.com"), None)
+        );
+    }
+
     #[ignore = "need to check why manifest_location is failing because its expecting a url"]
     #[tokio::test]
     async fn exists() {
-        let client = docker_registry_client::Client::new();
+        let client = RegistryClient::new(docker_registry_client::Client::new(), None);
         let image_name = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
         let docker_response = client.get_manifest(&image_name).await.unwrap();
         let got = cosign_manifest(&client, &image_name, &docker_response.digest.unwrap())
@@ -335,11 +899,168 @@ mod test {
                 issuer: "https://token.actions.githubusercontent.com".to_string(),
                 identity: "https://github.com/aquasecurity/trivy/.github/workflows/reusable-release.yaml@refs/tags/v0.52.0".to_string(),
             }],
+            certificates: got
+                .as_ref()
+                .map(|cosign| cosign.certificates.clone())
+                .unwrap_or_default(),
         });
 
         assert_eq!(expected, got);
     }
 
+    #[tokio::test]
+    async fn validate_malformed_key() {
+        let cosign = Path::new("/nonexistent/cosign");
+
+        let got = validate_key(
+            Some("-----BEGIN PUBLIC KEY-----\nbm90IGEga2V5\n-----END PUBLIC KEY-----\n"),
+            None,
+            None,
+            cosign,
+            None,
+        )
+        .await;
+
+        assert_eq!(Err(KeyValidationError::InvalidPublicKey), got);
+
+        // paths are treated as key contents, nothing is read from disk
+        let got = validate_key(Some("/etc/hostname"), None, None, cosign, None).await;
+
+        assert_eq!(Err(KeyValidationError::InvalidPublicKey), got);
+    }
+
+    #[tokio::test]
+    async fn validate_key_reference() {
+        let loaded = fake_binary("cosign-public-key", r#"[ "$1" = public-key ]"#);
+        let failed = fake_binary("cosign-public-key-failed", "exit 1");
+
+        let got = validate_key(Some("awskms:///alias/cosign"), None, None, &loaded, None).await;
+        assert_eq!(Ok(()), got);
+
+        let got = validate_key(Some("awskms:///alias/cosign"), None, None, &failed, None).await;
+        assert_eq!(Err(KeyValidationError::UnusableKeyReference), got);
+    }
+
+    #[tokio::test]
+    async fn validate_identity_without_issuer() {
+        let got = validate_key(
+            None,
+            Some("user@example.com"),
+            None,
+            Path::new("cosign"),
+            None,
+        )
+        .await;
+
+        assert_eq!(Err(KeyValidationError::MissingIssuer), got);
+    }
+
+    #[test]
+    fn certificate_bundle() {
+        const INPUT: &str = include_str!("resources/tests/cosign_manifest.json");
+        let docker_manifest: DockerManifest = serde_json::from_str(INPUT).unwrap();
+
+        let cosign = super::Cosign {
+            manifest_location: "https://ghcr.io/v2/aquasecurity/trivy/manifests/sha256-89fb17b267ef490a4c62d32c949b324a4f3d3b326c2b57d99cffe94547568ef8.sig"
+                .parse()
+                .unwrap(),
+            signatures: Vec::new(),
+            certificates: super::certificate_pems(&docker_manifest),
+        };
+
+        assert_eq!(2, cosign.certificates.len());
+
+        let bundle = cosign.certificate_bundle();
+
+        assert!(
+            bundle.starts_with("-----BEGIN CERTIFICATE-----\nMIIGzDCCBlGgAwIBAgIUXGPhbTS2Ican")
+        );
+        assert!(bundle.ends_with("-----END CERTIFICATE-----\n"));
+        assert_eq!(2, bundle.matches("-----BEGIN CERTIFICATE-----").count());
+
+        for pem in x509_parser::pem::Pem::iter_from_buffer(bundle.as_bytes()) {
+            let pem = pem.unwrap();
+
+            assert_eq!("CERTIFICATE", pem.label);
+            assert!(pem.parse_x509().is_ok());
+        }
+    }
+
+    #[tokio::test]
+    async fn fetch_coalesced() {
+        let fetches = CosignFetches::default();
+        let registry_hits = Arc::new(AtomicUsize::new(0));
+        let image: docker_registry_client::Image =
+            "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let mut tasks = tokio::task::JoinSet::new();
+
+        for _ in 0..8 {
+            let fetches = fetches.clone();
+            let registry_hits = registry_hits.clone();
+            let image = image.clone();
+
+            tasks.spawn(async move {
+                fetches
+                    .fetch(&image, "sha256:abc", async {
+                        registry_hits.fetch_add(1, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(50)).await;
+
+                        Ok(Some(Cosign {
+                            manifest_location:
+                                "https://ghcr.io/v2/aquasecurity/trivy/manifests/sha256-abc.sig"
+                                    .parse()
+                                    .unwrap(),
+                            signatures: Vec::new(),
+                            certificates: Vec::new(),
+                        }))
+                    })
+                    .await
+                    .unwrap()
+            });
+        }
+
+        let outputs = tasks.join_all().await;
+
+        assert_eq!(1, registry_hits.load(Ordering::SeqCst));
+        assert!(outputs.iter().all(Option::is_some));
+    }
+
+    #[tokio::test]
+    async fn fetch_concurrency() {
+        let fetches = CosignFetches::new(Some(NonZeroUsize::MIN));
+        let image: docker_registry_client::Image =
+            "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let held = fetches.clone();
+        let held_image = image.clone();
+        let first = tokio::spawn(async move {
+            held.fetch(&held_image, "sha256:abc", async {
+                tokio::time::sleep(Duration::from_millis(200)).await;
+
+                Ok(None)
+            })
+            .await
+        });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        // a different digest is not coalesced but has to wait for a slot
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(50),
+            fetches.fetch(&image, "sha256:def", async { Ok(None) }),
+        )
+        .await;
+
+        assert!(blocked.is_err(), "second fetch should wait for a slot");
+
+        first.await.unwrap().unwrap();
+        fetches
+            .fetch(&image, "sha256:def", async { Ok(None) })
+            .await
+            .unwrap();
+    }
+
     #[ignore = "incomplete test"]
     #[test]
     fn parse_manifest() {
@@ -0,0 +1,237 @@
+use std::{
+    collections::{
+        BTreeMap,
+        HashMap,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    process::Stdio,
+    sync::Arc,
+};
+
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use docker_registry_client::Image;
+use eyre::{
+    Context,
+    Result,
+};
+use serde::Deserialize;
+use tokio::{
+    io::AsyncWriteExt,
+    process::Command,
+    sync::RwLock,
+};
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+/// Refresh cached credentials this long before they expire.
+const EXPIRY_MARGIN_SECONDS: i64 = 60;
+
+/// Docker credential helpers used to obtain short-lived registry tokens.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CredentialHelpers {
+    helpers: BTreeMap<String, PathBuf>,
+    ttl: Duration,
+    cache: Arc<RwLock<HashMap<String, CachedCredentials>>>,
+}
+
+#[derive(Clone, Deserialize)]
+pub(crate) struct Credentials {
+    #[serde(rename = "Username")]
+    pub(crate) username: String,
+
+    #[serde(rename = "Secret")]
+    pub(crate) secret: String,
+}
+
+#[derive(Debug, Clone)]
+struct CachedCredentials {
+    credentials: Credentials,
+    expires: DateTime<Utc>,
+}
+
+impl CredentialHelpers {
+    pub(crate) fn new(helpers: BTreeMap<String, PathBuf>, ttl: Duration) -> Self {
+        Self {
+            helpers,
+            ttl,
+            cache: Arc::default(),
+        }
+    }
+
+    /// Returns credentials for the registry of the image when a helper is
+    /// configured for it. Credentials are cached until shortly before the
+    /// configured ttl runs out.
+    #[tracing::instrument]
+    pub(crate) async fn credentials(&self, image: &Image) -> Result<Option<Credentials>> {
+        let registry = image.registry.registry_domain();
+
+        let Some(helper) = self.helpers.get(registry) else {
+            return Ok(None);
+        };
+
+        if let Some(cached) = self.cache.read().await.get(registry)
+            && cached.expires - Duration::seconds(EXPIRY_MARGIN_SECONDS) > Utc::now()
+        {
+            return Ok(Some(cached.credentials.clone()));
+        }
+
+        let credentials = run_helper(helper, registry)
+            .instrument(info_span!("run credential helper"))
+            .await
+            .context("failed to get credentials from credential helper")?;
+
+        self.cache.write().await.insert(
+            registry.to_string(),
+            CachedCredentials {
+                credentials: credentials.clone(),
+                expires: Utc::now() + self.ttl,
+            },
+        );
+
+        Ok(Some(credentials))
+    }
+}
+
+async fn run_helper(helper: &Path, registry: &str) -> Result<Credentials> {
+    let mut child = Command::new(helper)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("failed to start credential helper")?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| eyre::eyre!("failed to open credential helper stdin"))?;
+
+    stdin
+        .write_all(registry.as_bytes())
+        .await
+        .context("failed to write registry to credential helper")?;
+
+    drop(stdin);
+
+    let output = child
+        .wait_with_output()
+        .await
+        .context("failed to wait for credential helper")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        return Err(eyre::Report::msg(stderr.to_string()));
+    }
+
+    serde_json::from_slice(&output.stdout).context("failed to parse credential helper output")
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credentials")
+            .field("username", &self.username)
+            .field("secret", &"REDACTED")
+            .finish()
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        collections::BTreeMap,
+        os::unix::fs::PermissionsExt,
+    };
+
+    use chrono::Duration;
+
+    use super::CredentialHelpers;
+    use crate::handler::trivy::{
+        self,
+        TrivyConfig,
+    };
+
+    #[tokio::test]
+    async fn helper_token_forwarded() {
+        let directory = std::env::temp_dir().join("trivy-web-credential-helper");
+        let helper = directory.join("docker-credential-test");
+        let log = directory.join("invocations");
+
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+        let _ = tokio::fs::remove_file(&log).await;
+
+        tokio::fs::write(
+            &helper,
+            format!(
+                "#!/bin/sh\nread registry\necho \"$registry\" >> {log}\nprintf \
+                 '{{\"ServerURL\":\"%s\",\"Username\":\"robot\",\"Secret\":\"short-lived\"}}' \
+                 \"$registry\"\n",
+                log = log.display()
+            ),
+        )
+        .await
+        .unwrap();
+
+        tokio::fs::set_permissions(&helper, std::fs::Permissions::from_mode(0o755))
+            .await
+            .unwrap();
+
+        let helpers = CredentialHelpers::new(
+            BTreeMap::from([("ghcr.io".to_string(), helper)]),
+            Duration::hours(1),
+        );
+
+        let unmatched = helpers
+            .credentials(
+                &"quay.io/jetstack/cert-manager-controller:v1.0.0"
+                    .parse()
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert!(unmatched.is_none());
+
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let credentials = helpers.credentials(&image).await.unwrap().unwrap();
+        let cached = helpers.credentials(&image).await.unwrap().unwrap();
+
+        assert_eq!("short-lived", credentials.secret);
+        assert_eq!("short-lived", cached.secret);
+
+        let invocations = tokio::fs::read_to_string(&log).await.unwrap();
+        assert_eq!("ghcr.io\n", invocations);
+
+        let command = trivy::command(
+            &image,
+            &TrivyConfig::default(),
+            None,
+            trivy::ScanFlags::default(),
+            None,
+            Some(&credentials.username),
+            Some(&credentials.secret),
+        );
+
+        let password = command
+            .as_std()
+            .get_envs()
+            .find(|(key, _)| *key == "TRIVY_PASSWORD")
+            .and_then(|(_, value)| value)
+            .unwrap();
+
+        assert_eq!("short-lived", password);
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+}
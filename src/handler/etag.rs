@@ -0,0 +1,110 @@
+use std::fmt::Write;
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{
+        HeaderValue,
+        Method,
+        StatusCode,
+        header::{
+            ETAG,
+            IF_NONE_MATCH,
+        },
+    },
+    middleware::Next,
+    response::{
+        IntoResponse,
+        Response,
+    },
+};
+use sha2::{
+    Digest,
+    Sha256,
+};
+
+/// Adds a strong `ETag` computed from the response body to successful
+/// responses. Matching `If-None-Match` requests are answered with `304 Not
+/// Modified` for `GET` and `HEAD` and with `412 Precondition Failed` for the
+/// `POST` requests scan results are fetched with (RFC 9110 section 13.1.2).
+/// The handler still runs, only sending the unchanged body is skipped.
+///
+/// The body is buffered to hash it, streamed responses must not be wrapped.
+pub(super) async fn etag(request: Request, next: Next) -> Response {
+    let unchanged_status = match *request.method() {
+        Method::GET | Method::HEAD => StatusCode::NOT_MODIFIED,
+        Method::POST => StatusCode::PRECONDITION_FAILED,
+        _ => return next.run(request).await,
+    };
+
+    let if_none_match = request.headers().get(IF_NONE_MATCH).cloned();
+
+    let response = next.run(request).await;
+
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+
+        Err(err) => {
+            tracing::error!("failed to read response body for etag: {err}");
+
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+
+    let etag = content_etag(&body);
+
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else {
+        return Response::from_parts(parts, Body::from(body));
+    };
+
+    if if_none_match
+        .as_ref()
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| matches_etag(value, &etag))
+    {
+        return (unchanged_status, [(ETAG, etag_value)]).into_response();
+    }
+
+    parts.headers.insert(ETAG, etag_value);
+
+    Response::from_parts(parts, Body::from(body))
+}
+
+fn content_etag(body: &[u8]) -> String {
+    let digest = Sha256::digest(body);
+
+    let mut etag = String::with_capacity(digest.len() * 2 + 2);
+    etag.push('"');
+
+    for byte in digest {
+        let _ = write!(etag, "{byte:02x}");
+    }
+
+    etag.push('"');
+
+    etag
+}
+
+fn matches_etag(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod test {
+    #[test]
+    fn matches_etag() {
+        assert!(super::matches_etag(r#""a", "b""#, r#""b""#));
+        assert!(super::matches_etag(r#"W/"b""#, r#""b""#));
+        assert!(super::matches_etag("*", r#""b""#));
+        assert!(!super::matches_etag(r#""outdated""#, r#""b""#));
+    }
+}
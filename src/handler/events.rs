@@ -0,0 +1,71 @@
+use chrono::SecondsFormat;
+
+use crate::handler::trend::TrendPoint;
+
+/// Published to the event stream after a fresh scan so downstream systems
+/// can react to new results.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ScanEvent {
+    pub(crate) image: String,
+    pub(crate) point: TrendPoint,
+}
+
+impl ScanEvent {
+    /// Flat field/value pairs of the stream entry, the digest is left empty
+    /// when trivy did not report one.
+    pub(crate) fn fields(&self) -> Vec<(&'static str, String)> {
+        let count = &self.point.severity_count;
+
+        vec![
+            ("image", self.image.clone()),
+            ("digest", self.point.digest.clone().unwrap_or_default()),
+            (
+                "scan_time",
+                self.point
+                    .scan_time
+                    .to_rfc3339_opts(SecondsFormat::Secs, true),
+            ),
+            ("critical", count.critical.to_string()),
+            ("high", count.high.to_string()),
+            ("medium", count.medium.to_string()),
+            ("low", count.low.to_string()),
+            ("unknown", count.unknown.to_string()),
+        ]
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use chrono::DateTime;
+
+    use super::ScanEvent;
+    use crate::handler::{
+        trend::TrendPoint,
+        trivy::SeverityCount,
+    };
+
+    #[test]
+    fn fields() {
+        let event = ScanEvent {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".to_string(),
+            point: TrendPoint {
+                digest: None,
+                scan_time: DateTime::from_timestamp(1_700_000_000, 0).unwrap(),
+                severity_count: SeverityCount {
+                    critical: 1,
+                    high: 2,
+                    ..SeverityCount::default()
+                },
+            },
+        };
+
+        let fields = event.fields();
+
+        assert!(fields.contains(&("image", "ghcr.io/aquasecurity/trivy:0.52.0".to_string())));
+        assert!(fields.contains(&("digest", String::new())));
+        assert!(fields.contains(&("scan_time", "2023-11-14T22:13:20Z".to_string())));
+        assert!(fields.contains(&("critical", "1".to_string())));
+        assert!(fields.contains(&("high", "2".to_string())));
+    }
+}
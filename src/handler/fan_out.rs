@@ -0,0 +1,122 @@
+use std::{
+    num::NonZeroUsize,
+    sync::Arc,
+};
+
+use eyre::{
+    Context,
+    Result,
+};
+use tokio::{
+    sync::Semaphore,
+    task::JoinHandle,
+};
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+/// Overall ceiling for the manifest lookups and cosign verifications of the
+/// image panel, on top of the limits per registry and trivy server.
+#[derive(Debug, Clone)]
+pub(crate) struct FanOutLimit {
+    permits: Arc<Semaphore>,
+}
+
+impl Default for FanOutLimit {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl FanOutLimit {
+    pub(crate) fn new(limit: Option<NonZeroUsize>) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(
+                limit.map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get),
+            )),
+        }
+    }
+
+    /// Spawns the task once a slot is free, the slot is held until the task
+    /// finished. Waiting for the slot happens in the caller, so a request
+    /// handler waits on the request path while all slots are taken.
+    pub(crate) async fn spawn<F>(&self, task: F) -> Result<JoinHandle<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .instrument(info_span!("wait for fan-out slot"))
+            .await
+            .context("fan-out semaphore closed")?;
+
+        Ok(tokio::spawn(async move {
+            let output = task.await;
+            drop(permit);
+
+            output
+        }))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn available_permits(&self) -> usize {
+        self.permits.available_permits()
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        num::NonZeroUsize,
+        sync::{
+            Arc,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+        },
+        time::Duration,
+    };
+
+    use super::FanOutLimit;
+
+    #[tokio::test]
+    async fn limit() {
+        let limit = FanOutLimit::new(NonZeroUsize::new(2));
+        let running = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let spawns = (0..8).map(|_| {
+            let limit = limit.clone();
+            let running = running.clone();
+            let peak = peak.clone();
+
+            tokio::spawn(async move {
+                limit
+                    .spawn(async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        peak.fetch_max(now, Ordering::SeqCst);
+
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    })
+                    .await
+                    .unwrap()
+                    .await
+                    .unwrap();
+            })
+        });
+
+        for spawn in spawns.collect::<Vec<_>>() {
+            spawn.await.unwrap();
+        }
+
+        assert_eq!(2, peak.load(Ordering::SeqCst));
+    }
+}
@@ -0,0 +1,23 @@
+use serde::Serialize;
+
+/// Scan output formats that can be turned off, e.g. SBOM generation in
+/// minimal deployments. Routes of disabled formats are not registered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OutputFormat {
+    /// `/api/trivy` and `/api/trivy.json`.
+    Json,
+
+    /// `/api/trivy/sarif`.
+    Sarif,
+
+    /// `/api/sbom`.
+    Sbom,
+
+    /// `/api/render.md` and `/api/render` with `Accept: text/markdown`.
+    Markdown,
+
+    /// `/api/trivy/github-annotations`.
+    #[serde(rename = "github-annotations")]
+    GithubAnnotations,
+}
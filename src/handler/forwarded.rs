@@ -0,0 +1,182 @@
+use std::{
+    convert::Infallible,
+    net::{
+        IpAddr,
+        SocketAddr,
+    },
+};
+
+use axum::{
+    extract::{
+        ConnectInfo,
+        FromRef,
+        FromRequestParts,
+    },
+    http::{
+        HeaderMap,
+        request::Parts,
+    },
+};
+use ipnet::IpNet;
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+
+/// Networks of reverse proxies whose `X-Forwarded-*` headers are trusted.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrustedProxies(pub(crate) Vec<IpNet>);
+
+/// Client address and scheme of a request, taken from the `X-Forwarded-*`
+/// headers when the request came through a trusted proxy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ClientInfo {
+    pub(crate) ip: Option<IpAddr>,
+    pub(crate) scheme: String,
+}
+
+impl TrustedProxies {
+    fn contains(&self, ip: IpAddr) -> bool {
+        self.0.iter().any(|network| network.contains(&ip))
+    }
+}
+
+impl ClientInfo {
+    pub(crate) fn from_parts(
+        peer: Option<IpAddr>,
+        headers: &HeaderMap,
+        trusted_proxies: &TrustedProxies,
+    ) -> Self {
+        let untrusted = Self {
+            ip: peer,
+            scheme: "http".to_string(),
+        };
+
+        let Some(peer) = peer else {
+            return untrusted;
+        };
+
+        if !trusted_proxies.contains(peer) {
+            return untrusted;
+        }
+
+        let forwarded_for = headers
+            .get_all(X_FORWARDED_FOR)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+            .collect::<Vec<_>>();
+
+        // Walk the chain from the closest hop and take the first address that
+        // is not one of our proxies. When every hop is trusted the original
+        // client is the first entry.
+        let ip = forwarded_for
+            .iter()
+            .rev()
+            .find(|ip| !trusted_proxies.contains(**ip))
+            .or_else(|| forwarded_for.first())
+            .copied()
+            .unwrap_or(peer);
+
+        let scheme = headers
+            .get(X_FORWARDED_PROTO)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|scheme| matches!(*scheme, "http" | "https"))
+            .unwrap_or("http")
+            .to_string();
+
+        Self {
+            ip: Some(ip),
+            scheme,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for ClientInfo
+where
+    TrustedProxies: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let trusted_proxies = TrustedProxies::from_ref(state);
+
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(address)| address.ip());
+
+        Ok(Self::from_parts(peer, &parts.headers, &trusted_proxies))
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::net::IpAddr;
+
+    use axum::http::HeaderMap;
+    use pretty_assertions::assert_eq;
+
+    use super::{
+        ClientInfo,
+        TrustedProxies,
+    };
+
+    fn headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "203.0.113.7, 10.0.0.2".parse().unwrap());
+        headers.insert("x-forwarded-proto", "https".parse().unwrap());
+        headers
+    }
+
+    fn trusted() -> TrustedProxies {
+        TrustedProxies(vec!["10.0.0.0/8".parse().unwrap()])
+    }
+
+    #[test]
+    fn trusted_peer() {
+        let peer = "10.0.0.1".parse::<IpAddr>().unwrap();
+
+        let got = ClientInfo::from_parts(Some(peer), &headers(), &trusted());
+
+        let expected = ClientInfo {
+            ip: Some("203.0.113.7".parse().unwrap()),
+            scheme: "https".to_string(),
+        };
+
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn untrusted_peer() {
+        let peer = "198.51.100.1".parse::<IpAddr>().unwrap();
+
+        let got = ClientInfo::from_parts(Some(peer), &headers(), &trusted());
+
+        let expected = ClientInfo {
+            ip: Some(peer),
+            scheme: "http".to_string(),
+        };
+
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn spoofed_chain() {
+        let peer = "10.0.0.1".parse::<IpAddr>().unwrap();
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-forwarded-for",
+            "10.9.9.9, 198.51.100.20, 10.0.0.2".parse().unwrap(),
+        );
+
+        let got = ClientInfo::from_parts(Some(peer), &headers, &trusted());
+
+        assert_eq!(Some("198.51.100.20".parse().unwrap()), got.ip);
+    }
+}
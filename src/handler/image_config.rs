@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use docker_registry_client::{
+    ClientError as DockerClientError,
+    Image,
+    Manifest as DockerManifest,
+    manifest::Architecture,
+};
+use eyre::{
+    Context,
+    Result,
+};
+use serde::Deserialize;
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+use crate::handler::registry_client::{
+    RegistryClient,
+    repository_url,
+};
+
+/// Image config blob, only the labels are of interest.
+#[derive(Debug, Default, Deserialize)]
+struct ImageConfig {
+    #[serde(default)]
+    config: Option<ContainerConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct ContainerConfig {
+    /// Images built without labels have `null` here.
+    #[serde(default)]
+    labels: Option<BTreeMap<String, String>>,
+}
+
+impl ImageConfig {
+    fn labels(self) -> BTreeMap<String, String> {
+        self.config
+            .and_then(|config| config.labels)
+            .unwrap_or_default()
+    }
+}
+
+/// Parses the labels from the body of an image config blob.
+pub(crate) fn parse_labels(body: &str) -> Result<BTreeMap<String, String>> {
+    serde_json::from_str::<ImageConfig>(body)
+        .map(ImageConfig::labels)
+        .context("failed to parse image config")
+}
+
+/// Digest of the image config, manifest lists are resolved to the image of
+/// the `amd64` platform or the first platform when there is none.
+async fn config_digest(
+    client: &RegistryClient,
+    image: &Image,
+    manifest: &DockerManifest,
+) -> Result<Option<String>> {
+    let list = match manifest {
+        DockerManifest::Image(manifest) => return Ok(Some(manifest.config.digest.clone())),
+        DockerManifest::Single(_) => return Ok(None),
+        DockerManifest::List(list) => list,
+    };
+
+    let Some(entry) = list
+        .manifests
+        .iter()
+        .find(|entry| matches!(entry.platform.architecture, Architecture::Amd64))
+        .or_else(|| list.manifests.first())
+    else {
+        return Ok(None);
+    };
+
+    let url = repository_url(image, "manifests", &entry.digest)
+        .context("failed to parse repository url")?;
+
+    let response = client
+        .get_manifest_url(&url, image)
+        .instrument(info_span!("get platform manifest"))
+        .await
+        .context("failed to get platform manifest")?;
+
+    match response.manifest {
+        DockerManifest::Image(manifest) => Ok(Some(manifest.config.digest)),
+        DockerManifest::List(_) | DockerManifest::Single(_) => Ok(None),
+    }
+}
+
+/// Labels of the image config, e.g. `org.opencontainers.image.source`.
+/// Images without labels return an empty map.
+#[tracing::instrument(skip(manifest))]
+pub(crate) async fn config_labels(
+    client: &RegistryClient,
+    image: &Image,
+    manifest: &DockerManifest,
+) -> Result<BTreeMap<String, String>> {
+    // schema v1 manifests carry the config of the image in their history
+    if let DockerManifest::Single(single) = manifest {
+        return Ok(single
+            .history
+            .first()
+            .and_then(|history| history.v1_compatibility.container_config.as_ref())
+            .and_then(|config| config.labels.clone())
+            .unwrap_or_default());
+    }
+
+    let Some(digest) = config_digest(client, image, manifest).await? else {
+        return Ok(BTreeMap::new());
+    };
+
+    let url = repository_url(image, "blobs", &digest).context("failed to parse repository url")?;
+
+    // the registry client only knows manifests, the config blob is returned
+    // as the body it failed to deserialize
+    match client
+        .get_manifest_url(&url, image)
+        .instrument(info_span!("get image config"))
+        .await
+    {
+        Err(DockerClientError::DeserializeManifestBody(_, body)) => parse_labels(&body),
+        Err(err) => Err(err).context("failed to get image config"),
+        Ok(_) => Ok(BTreeMap::new()),
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    #[test]
+    fn parse_labels() {
+        let labels =
+            super::parse_labels(include_str!("resources/tests/image_config.json")).unwrap();
+
+        assert_eq!(
+            Some(&"https://github.com/aquasecurity/trivy".to_string()),
+            labels.get("org.opencontainers.image.source")
+        );
+        assert_eq!(4, labels.len());
+    }
+
+    #[test]
+    fn parse_missing_labels() {
+        let labels = super::parse_labels(r#"{"config": {"Labels": null}}"#).unwrap();
+        assert!(labels.is_empty());
+
+        let labels = super::parse_labels(r#"{"architecture": "amd64"}"#).unwrap();
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn repository_url() {
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        assert_eq!(
+            "https://ghcr.io/v2/aquasecurity/trivy/blobs/sha256:abc",
+            super::repository_url(&image, "blobs", "sha256:abc")
+                .unwrap()
+                .as_str()
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+};
+
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use utoipa::ToSchema;
+
+/// Free-form `key=value` labels attached to a scan, e.g. the owning team or
+/// the environment the image runs in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub(crate) struct Labels(pub(crate) BTreeMap<String, String>);
+
+/// A scanned image and the labels it was last scanned with.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, ToSchema)]
+pub(crate) struct LabeledScan {
+    pub(crate) image: String,
+    pub(crate) labels: Labels,
+}
+
+impl Labels {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns true when every label of the filter is set to the same value.
+    pub(crate) fn matches(&self, filter: &Labels) -> bool {
+        filter
+            .0
+            .iter()
+            .all(|(key, value)| self.0.get(key) == Some(value))
+    }
+}
+
+impl FromStr for Labels {
+    type Err = eyre::Error;
+
+    /// Parses comma separated `key=value` pairs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(|label| {
+                let (key, value) = label
+                    .split_once('=')
+                    .ok_or_else(|| eyre::eyre!("label {label} is not in key=value format"))?;
+
+                let key = key.trim();
+
+                if key.is_empty() {
+                    return Err(eyre::eyre!("label {label} has an empty key"));
+                }
+
+                Ok((key.to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Labels)
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use super::Labels;
+
+    #[test]
+    fn parse() {
+        let labels = " team=payments, env = prod ,".parse::<Labels>().unwrap();
+
+        assert_eq!(Some(&"payments".to_string()), labels.0.get("team"));
+        assert_eq!(Some(&"prod".to_string()), labels.0.get("env"));
+
+        assert!("team".parse::<Labels>().is_err());
+        assert!("=prod".parse::<Labels>().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let labels = "team=payments,env=prod".parse::<Labels>().unwrap();
+
+        assert!(labels.matches(&Labels::default()));
+        assert!(labels.matches(&"team=payments".parse().unwrap()));
+        assert!(!labels.matches(&"team=search".parse().unwrap()));
+        assert!(!labels.matches(&"owner=alice".parse().unwrap()));
+    }
+}
@@ -0,0 +1,111 @@
+use eyre::{
+    Context,
+    Result,
+};
+use metrics_exporter_prometheus::{
+    Matcher,
+    PrometheusBuilder,
+    PrometheusHandle,
+};
+
+use super::trivy::SeverityCount;
+
+const SCANS: &str = "trivy_web_scans_total";
+const CACHE_REQUESTS: &str = "trivy_web_cache_requests_total";
+const SCAN_DURATION: &str = "trivy_web_trivy_scan_duration_seconds";
+const VULNERABILITIES: &str = "trivy_web_vulnerabilities_total";
+const SCAN_QUEUE_DEPTH: &str = "trivy_web_scan_queue_depth";
+
+/// Trivy scans take seconds to minutes depending on the image size.
+const SCAN_DURATION_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+fn builder() -> Result<PrometheusBuilder> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(SCAN_DURATION.to_string()),
+            SCAN_DURATION_BUCKETS,
+        )
+        .context("invalid scan duration buckets")
+}
+
+/// Installs the global recorder, the handle renders the metrics for the
+/// metrics endpoint.
+pub(crate) fn install() -> Result<PrometheusHandle> {
+    builder()?
+        .install_recorder()
+        .context("failed to install metrics recorder")
+}
+
+/// Counts a scan request of the given handler, e.g. `image` or `trivy`.
+pub(super) fn record_scan(handler: &'static str) {
+    metrics::counter!(SCANS, "handler" => handler).increment(1);
+}
+
+pub(super) fn record_cache(hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+
+    metrics::counter!(CACHE_REQUESTS, "result" => result).increment(1);
+}
+
+pub(super) fn record_scan_duration(duration: std::time::Duration) {
+    metrics::histogram!(SCAN_DURATION).record(duration.as_secs_f64());
+}
+
+/// Number of scans waiting for a scan slot.
+#[expect(
+    clippy::cast_precision_loss,
+    reason = "queue depths stay far below the precision of f64"
+)]
+pub(super) fn record_scan_queue_depth(depth: usize) {
+    metrics::gauge!(SCAN_QUEUE_DEPTH).set(depth as f64);
+}
+
+/// Counts the vulnerabilities of a fresh scan by severity, cached scans are
+/// not counted again.
+pub(super) fn record_vulnerabilities(count: &SeverityCount) {
+    for (severity, count) in [
+        ("critical", count.critical),
+        ("high", count.high),
+        ("medium", count.medium),
+        ("low", count.low),
+        ("unknown", count.unknown),
+    ] {
+        metrics::counter!(VULNERABILITIES, "severity" => severity).increment(count as u64);
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use crate::handler::trivy::SeverityCount;
+
+    #[test]
+    fn render() {
+        let recorder = super::builder().unwrap().build_recorder();
+        let handle = recorder.handle();
+
+        metrics::with_local_recorder(&recorder, || {
+            super::record_scan("trivy");
+            super::record_cache(true);
+            super::record_cache(false);
+            super::record_cache(false);
+            super::record_scan_duration(std::time::Duration::from_secs(7));
+            super::record_scan_queue_depth(3);
+            super::record_vulnerabilities(&SeverityCount {
+                critical: 2,
+                high: 3,
+                ..SeverityCount::default()
+            });
+        });
+
+        let rendered = handle.render();
+
+        assert!(rendered.contains(r#"trivy_web_scans_total{handler="trivy"} 1"#));
+        assert!(rendered.contains(r#"trivy_web_cache_requests_total{result="hit"} 1"#));
+        assert!(rendered.contains(r#"trivy_web_cache_requests_total{result="miss"} 2"#));
+        assert!(rendered.contains(r#"trivy_web_trivy_scan_duration_seconds_bucket{le="10"} 1"#));
+        assert!(rendered.contains(r#"trivy_web_vulnerabilities_total{severity="critical"} 2"#));
+        assert!(rendered.contains(r#"trivy_web_vulnerabilities_total{severity="high"} 3"#));
+        assert!(rendered.contains("trivy_web_scan_queue_depth 3"));
+    }
+}
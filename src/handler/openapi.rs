@@ -0,0 +1,22 @@
+use axum::Json;
+use utoipa::OpenApi;
+
+/// Contract of the JSON API. Field names are shown in `snake_case`, they are
+/// returned in `camelCase` when the server runs with `--api-json-case
+/// camelCase`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "trivy-web"),
+    paths(
+        super::trivy_api,
+        super::scan_tags,
+        super::compare_images,
+        super::scans,
+        super::trend,
+    )
+)]
+pub(super) struct ApiDoc;
+
+pub(super) async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -0,0 +1,62 @@
+use tokio::sync::mpsc;
+
+/// Phases of a scan that are streamed to clients while the scan runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ScanPhase {
+    PullingManifest,
+    RunningTrivy,
+}
+
+impl std::fmt::Display for ScanPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PullingManifest => write!(f, "pulling manifest"),
+            Self::RunningTrivy => write!(f, "running trivy"),
+        }
+    }
+}
+
+/// Reports the phases of a scan to a listener, nothing is reported by
+/// default.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ScanProgress(Option<mpsc::UnboundedSender<ScanPhase>>);
+
+impl ScanProgress {
+    pub(crate) fn channel() -> (Self, mpsc::UnboundedReceiver<ScanPhase>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        (Self(Some(sender)), receiver)
+    }
+
+    /// Phases reported after the listener went away are dropped.
+    pub(crate) fn report(&self, phase: ScanPhase) {
+        if let Some(sender) = &self.0 {
+            let _ = sender.send(phase);
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use super::{
+        ScanPhase,
+        ScanProgress,
+    };
+
+    #[tokio::test]
+    async fn report() {
+        let (progress, mut phases) = ScanProgress::channel();
+
+        progress.report(ScanPhase::PullingManifest);
+        progress.clone().report(ScanPhase::RunningTrivy);
+        drop(progress);
+
+        assert_eq!(Some(ScanPhase::PullingManifest), phases.recv().await);
+        assert_eq!(ScanPhase::RunningTrivy, phases.recv().await.unwrap());
+        assert_eq!(None, phases.recv().await);
+
+        // without a listener reporting does nothing
+        ScanProgress::default().report(ScanPhase::RunningTrivy);
+    }
+}
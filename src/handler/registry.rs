@@ -0,0 +1,177 @@
+use std::{
+    num::NonZeroUsize,
+    sync::Arc,
+    time::Duration,
+};
+
+use docker_registry_client::Image;
+use eyre::{
+    Context,
+    Result,
+};
+use tokio::sync::Semaphore;
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+/// Limits applied to requests against docker registries so a slow registry
+/// can not hang a request and rate-limited registries are not overwhelmed.
+#[derive(Debug, Clone)]
+pub(crate) struct RegistryLimits {
+    timeout: Option<Duration>,
+    permits: Arc<Semaphore>,
+}
+
+impl Default for RegistryLimits {
+    fn default() -> Self {
+        Self::new(None, None)
+    }
+}
+
+impl RegistryLimits {
+    pub(crate) fn new(timeout: Option<Duration>, concurrency: Option<NonZeroUsize>) -> Self {
+        Self {
+            timeout,
+            permits: Arc::new(Semaphore::new(
+                concurrency.map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get),
+            )),
+        }
+    }
+
+    /// Runs a registry request once a slot is free and fails when it does
+    /// not finish within the timeout. Waiting for a slot does not count
+    /// towards the timeout.
+    pub(crate) async fn run<T>(&self, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let _permit = self
+            .permits
+            .acquire()
+            .instrument(info_span!("wait for registry slot"))
+            .await
+            .context("registry semaphore closed")?;
+
+        let Some(timeout) = self.timeout else {
+            return request.await;
+        };
+
+        tokio::time::timeout(timeout, request).await.map_err(|_| {
+            eyre::eyre!(
+                "docker registry did not respond within {} seconds",
+                timeout.as_secs_f64()
+            )
+        })?
+    }
+}
+
+/// Registry domains images may be scanned from, images of every registry
+/// are scanned when empty.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AllowedRegistries(pub(crate) Vec<String>);
+
+/// Returned for images of registries missing from the allowlist.
+#[derive(Debug)]
+pub(crate) struct RegistryNotAllowed(pub(crate) String);
+
+impl std::fmt::Display for RegistryNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "images from registry {} are not allowed", self.0)
+    }
+}
+
+impl std::error::Error for RegistryNotAllowed {}
+
+impl AllowedRegistries {
+    pub(crate) fn check(&self, image: &Image) -> Result<(), RegistryNotAllowed> {
+        let domain = image.registry.registry_domain();
+
+        if self.0.is_empty()
+            || self
+                .0
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+        {
+            Ok(())
+        } else {
+            Err(RegistryNotAllowed(domain.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        num::NonZeroUsize,
+        time::Duration,
+    };
+
+    use super::{
+        AllowedRegistries,
+        RegistryLimits,
+    };
+
+    async fn slow_registry() -> eyre::Result<&'static str> {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        Ok("manifest")
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let limits = RegistryLimits::new(Some(Duration::from_millis(50)), None);
+
+        let err = limits.run(slow_registry()).await.unwrap_err();
+
+        assert_eq!(
+            "docker registry did not respond within 0.05 seconds",
+            err.to_string()
+        );
+
+        let got = RegistryLimits::default()
+            .run(slow_registry())
+            .await
+            .unwrap();
+
+        assert_eq!("manifest", got);
+    }
+
+    #[tokio::test]
+    async fn concurrency() {
+        let limits = RegistryLimits::new(None, Some(NonZeroUsize::MIN));
+
+        let held = limits.clone();
+        let first = tokio::spawn(async move { held.run(slow_registry()).await });
+
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let blocked =
+            tokio::time::timeout(Duration::from_millis(50), limits.run(async { Ok(()) })).await;
+
+        assert!(blocked.is_err(), "second request should wait for a slot");
+
+        first.await.unwrap().unwrap();
+        limits.run(async { Ok(()) }).await.unwrap();
+    }
+
+    #[test]
+    fn allowed_registries() {
+        let allowed = AllowedRegistries(vec!["ghcr.io".to_string()]);
+
+        allowed
+            .check(&"ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap())
+            .unwrap();
+
+        let err = allowed
+            .check(&"quay.io/prometheus/prometheus:v2.53.0".parse().unwrap())
+            .unwrap_err();
+
+        assert_eq!(
+            "images from registry quay.io are not allowed",
+            err.to_string()
+        );
+
+        AllowedRegistries::default()
+            .check(&"quay.io/prometheus/prometheus:v2.53.0".parse().unwrap())
+            .unwrap();
+    }
+}
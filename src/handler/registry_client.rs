@@ -0,0 +1,399 @@
+use std::sync::LazyLock;
+
+use base64::{
+    Engine,
+    engine::general_purpose::STANDARD,
+};
+use docker_registry_client::{
+    Client as DockerRegistryClient,
+    ClientError,
+    Image,
+    Response,
+};
+use serde::Deserialize;
+use tracing::{
+    Instrument,
+    info_span,
+};
+use url::Url;
+
+use crate::handler::credentials::Credentials;
+
+/// Manifest media types requested from registries, the same ones the
+/// anonymous registry client accepts.
+const MANIFEST_ACCEPT: &str = "application/vnd.docker.container.image.v1+json, \
+                               application/vnd.docker.distribution.manifest.list.v2+json, \
+                               application/vnd.docker.distribution.manifest.v2+json, \
+                               application/vnd.oci.image.index.v1+json, \
+                               application/vnd.oci.image.manifest.v1+json";
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(reqwest::Client::new);
+
+/// Docker registry client that authenticates with registry credentials,
+/// e.g. from a credential helper. Without credentials requests go through
+/// the anonymous registry client.
+#[derive(Debug, Clone)]
+pub(crate) struct RegistryClient {
+    client: DockerRegistryClient,
+    credentials: Option<Credentials>,
+}
+
+/// Token handed out by the token service of a registry, registries use
+/// either field.
+#[derive(Deserialize)]
+struct Token {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl RegistryClient {
+    pub(crate) fn new(client: DockerRegistryClient, credentials: Option<Credentials>) -> Self {
+        Self {
+            client,
+            credentials,
+        }
+    }
+
+    /// User requests are made as. Part of cache keys so manifests fetched
+    /// with credentials are not served to requests without them.
+    pub(crate) fn identity(&self) -> Option<&str> {
+        self.credentials
+            .as_ref()
+            .map(|credentials| credentials.username.as_str())
+    }
+
+    pub(crate) async fn get_manifest(&self, image: &Image) -> Result<Response, ClientError> {
+        if self.credentials.is_none() {
+            return self.client.get_manifest(image).await;
+        }
+
+        let url = repository_url(image, "manifests", &image.image_name.identifier.to_string())
+            .map_err(ClientError::InvalidManifestUrl)?;
+
+        self.get_manifest_url(&url, image).await
+    }
+
+    pub(crate) async fn get_manifest_url(
+        &self,
+        url: &Url,
+        image: &Image,
+    ) -> Result<Response, ClientError> {
+        let Some(credentials) = &self.credentials else {
+            return self.client.get_manifest_url(url, image).await;
+        };
+
+        authenticated_manifest(url, credentials).await
+    }
+}
+
+/// `https://<registry>/v2/<name>/<kind>/<reference>` for manifests and blobs
+/// of the repository of the image.
+pub(crate) fn repository_url(
+    image: &Image,
+    kind: &str,
+    reference: &str,
+) -> Result<Url, url::ParseError> {
+    format!(
+        "https://{registry}/v2/{namespace}{repository}{name}/{kind}/{reference}",
+        registry = image.registry.registry_domain(),
+        namespace = match &image.namespace {
+            Some(namespace) => format!("{namespace}/"),
+            None => String::new(),
+        },
+        repository = match &image.repository {
+            Some(repository) => format!("{repository}/"),
+            None => String::new(),
+        },
+        name = image.image_name.name,
+    )
+    .parse()
+}
+
+/// Fetches the manifest anonymously first and authenticates when the
+/// registry asks for it, the credentials are only sent to registries that
+/// require them.
+async fn authenticated_manifest(
+    url: &Url,
+    credentials: &Credentials,
+) -> Result<Response, ClientError> {
+    let mut response = manifest_request(url, None).await?;
+
+    if response.status() == reqwest::StatusCode::UNAUTHORIZED {
+        let challenge = response
+            .headers()
+            .get(reqwest::header::WWW_AUTHENTICATE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+
+        let authorization = authorization(&challenge, credentials).await?;
+
+        response = manifest_request(url, Some(&authorization)).await?;
+    }
+
+    let digest = response
+        .headers()
+        .get("Docker-Content-Digest")
+        .map(|header| {
+            header
+                .to_str()
+                .map(String::from)
+                .map_err(ClientError::ParseDockerContentDigestHeader)
+        })
+        .transpose()?;
+
+    let status = response.status();
+
+    let body = response
+        .text()
+        .await
+        .map_err(ClientError::ExtractManifestBody)?;
+
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(ClientError::ManifestNotFound(url.clone()));
+    }
+
+    if !status.is_success() {
+        return Err(ClientError::FailedManifestRequest(status, body));
+    }
+
+    let manifest = serde_json::from_str(&body)
+        .map_err(|err| ClientError::DeserializeManifestBody(err, body))?;
+
+    Ok(Response { digest, manifest })
+}
+
+async fn manifest_request(
+    url: &Url,
+    authorization: Option<&str>,
+) -> Result<reqwest::Response, ClientError> {
+    let mut request = HTTP_CLIENT
+        .get(url.as_str())
+        .header(reqwest::header::ACCEPT, MANIFEST_ACCEPT);
+
+    if let Some(authorization) = authorization {
+        request = request.header(reqwest::header::AUTHORIZATION, authorization);
+    }
+
+    request
+        .send()
+        .instrument(info_span!("get manifest request"))
+        .await
+        .map_err(ClientError::GetManifest)
+}
+
+/// `Authorization` header answering the challenge of the registry. Bearer
+/// challenges are answered with a token from the token service of the
+/// registry, all others with the credentials as Basic auth.
+async fn authorization(challenge: &str, credentials: &Credentials) -> Result<String, ClientError> {
+    let Some(parameters) = challenge
+        .split_once(' ')
+        .filter(|(scheme, _)| scheme.eq_ignore_ascii_case("bearer"))
+        .map(|(_, parameters)| challenge_parameters(parameters))
+    else {
+        return Ok(format!(
+            "Basic {}",
+            STANDARD.encode(format!("{}:{}", credentials.username, credentials.secret))
+        ));
+    };
+
+    let realm = parameters
+        .iter()
+        .find(|(key, _)| key == "realm")
+        .map(|(_, realm)| realm.as_str())
+        .unwrap_or_default();
+
+    let mut realm = Url::parse(realm).map_err(ClientError::InvalidTokenUrl)?;
+
+    for (key, value) in &parameters {
+        if key == "service" || key == "scope" {
+            realm.query_pairs_mut().append_pair(key, value);
+        }
+    }
+
+    let response = HTTP_CLIENT
+        .get(realm)
+        .basic_auth(&credentials.username, Some(&credentials.secret))
+        .send()
+        .instrument(info_span!("get token request"))
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(ClientError::GetToken)?;
+
+    let body = response
+        .text()
+        .await
+        .map_err(ClientError::ExtractTokenBody)?;
+
+    // the body holds the token and is left out of the error
+    let token = serde_json::from_str::<Token>(&body)
+        .map_err(|err| ClientError::DeserializeToken(err, String::new()))?;
+
+    let token = token.token.or(token.access_token).unwrap_or_default();
+
+    Ok(format!("Bearer {token}"))
+}
+
+/// `key="value"` pairs of a challenge, quoted values may contain commas,
+/// e.g. a scope with several actions.
+fn challenge_parameters(parameters: &str) -> Vec<(String, String)> {
+    let mut parsed = Vec::new();
+    let mut rest = parameters.trim();
+
+    while let Some((key, value)) = rest.split_once('=') {
+        let key = key
+            .trim()
+            .trim_start_matches(',')
+            .trim()
+            .to_ascii_lowercase();
+
+        let (value, remaining) = match value.strip_prefix('"') {
+            Some(quoted) => quoted.split_once('"').unwrap_or((quoted, "")),
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+
+        parsed.push((key, value.to_string()));
+        rest = remaining.trim_start_matches(',').trim();
+    }
+
+    parsed
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use axum::{
+        Router,
+        http::{
+            HeaderMap,
+            StatusCode,
+            header::{
+                AUTHORIZATION,
+                WWW_AUTHENTICATE,
+            },
+        },
+        response::IntoResponse,
+        routing::get,
+    };
+
+    use super::RegistryClient;
+    use crate::handler::credentials::Credentials;
+
+    const MANIFEST: &str = include_str!("resources/tests/trivy-manifest-response.json");
+
+    /// Registry that only serves the manifest for a token issued to
+    /// `ci:s3cr3t`.
+    async fn fake_registry() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = format!("http://{}", listener.local_addr().unwrap());
+
+        let realm = format!("{address}/token");
+
+        let router = Router::new()
+            .route(
+                "/v2/app/manifests/latest",
+                get(move |headers: HeaderMap| async move {
+                    if headers.get(AUTHORIZATION).is_some_and(|value| value == "Bearer t0ken") {
+                        return ([("Docker-Content-Digest", "sha256:abc")], MANIFEST)
+                            .into_response();
+                    }
+
+                    (
+                        StatusCode::UNAUTHORIZED,
+                        [(
+                            WWW_AUTHENTICATE,
+                            format!(
+                                r#"Bearer realm="{realm}",service="registry",scope="repository:app:pull,push""#
+                            ),
+                        )],
+                    )
+                        .into_response()
+                }),
+            )
+            .route(
+                "/token",
+                get(|headers: HeaderMap| async move {
+                    // base64 of ci:s3cr3t
+                    if headers
+                        .get(AUTHORIZATION)
+                        .is_some_and(|value| value == "Basic Y2k6czNjcjN0")
+                    {
+                        r#"{"token": "t0ken"}"#.into_response()
+                    } else {
+                        StatusCode::UNAUTHORIZED.into_response()
+                    }
+                }),
+            );
+
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+        address
+    }
+
+    fn client(secret: &str) -> RegistryClient {
+        RegistryClient::new(
+            docker_registry_client::Client::default(),
+            Some(Credentials {
+                username: "ci".to_string(),
+                secret: secret.to_string(),
+            }),
+        )
+    }
+
+    #[tokio::test]
+    async fn token_authentication() {
+        let address = fake_registry().await;
+        let url = format!("{address}/v2/app/manifests/latest")
+            .parse()
+            .unwrap();
+        let image = "registry.example.com/app:latest".parse().unwrap();
+
+        let response = client("s3cr3t")
+            .get_manifest_url(&url, &image)
+            .await
+            .unwrap();
+
+        assert_eq!(Some("sha256:abc"), response.digest.as_deref());
+        assert!(matches!(
+            response.manifest,
+            docker_registry_client::Manifest::List(_)
+        ));
+
+        let err = client("wrong")
+            .get_manifest_url(&url, &image)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            docker_registry_client::ClientError::GetToken(_)
+        ));
+        assert!(!err.to_string().contains("wrong"));
+    }
+
+    #[test]
+    fn challenge_parameters() {
+        assert_eq!(
+            vec![
+                (
+                    "realm".to_string(),
+                    "https://auth.example.com/token".to_string()
+                ),
+                ("service".to_string(), "registry".to_string()),
+                ("scope".to_string(), "repository:app:pull,push".to_string()),
+            ],
+            super::challenge_parameters(
+                r#"realm="https://auth.example.com/token",service=registry,scope="repository:app:pull,push""#
+            )
+        );
+    }
+
+    #[test]
+    fn identity() {
+        assert_eq!(Some("ci"), client("s3cr3t").identity());
+        assert_eq!(
+            None,
+            RegistryClient::new(docker_registry_client::Client::default(), None).identity()
+        );
+    }
+}
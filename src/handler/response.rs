@@ -1,7 +1,16 @@
-use std::collections::BTreeSet;
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+        btree_map::Entry,
+    },
+    fmt::Write,
+    path::PathBuf,
+};
 
 use askama::Template;
 use cache::{
+    Cache,
     CosignInformationFetcher,
     DockerInformationFetcher,
     Fetch,
@@ -12,7 +21,6 @@ use chrono::{
     Utc,
 };
 use docker_registry_client::{
-    Client as DockerRegistryClient,
     Image,
     Manifest as DockerManifest,
     Response as DockerResponse,
@@ -21,6 +29,10 @@ use eyre::{
     Result,
     WrapErr,
 };
+use maud::{
+    Markup,
+    html,
+};
 use redis_macros::{
     FromRedisValue,
     ToRedisArgs,
@@ -29,23 +41,43 @@ use serde::{
     Deserialize,
     Serialize,
 };
-use tokio::task;
 use tracing::{
     Instrument,
     error,
     info_span,
 };
+use utoipa::ToSchema;
 
 pub(crate) mod cache;
+pub(crate) mod sarif;
 
 use crate::{
     filters,
     handler::{
-        cosign,
-        response::cache::REDIS_TTL,
+        cosign::{
+            self,
+            CosignFetches,
+        },
+        registry::RegistryLimits,
+        registry_client::RegistryClient,
+        trend::TrendPoint,
         trivy::{
+            ComplianceReport,
+            CvssVersion,
+            Metadata,
+            Misconfiguration,
+            Results,
+            Scanner,
+            ScannerStatus,
+            Severity,
             SeverityCount,
+            SeverityMapping,
+            SeverityWeights,
+            TrivyResult,
             Vulnerability,
+            VulnerabilityStatus,
+            get_vulnerabilities_count,
+            scanner_status,
         },
     },
 };
@@ -60,22 +92,88 @@ use super::{
 #[template(path = "response_image.html")]
 pub(crate) struct ImageResponse {
     pub(crate) image: Image,
-    pub(crate) docker_information: Result<DockerInformation>,
-    pub(crate) cosign_information: Result<CosignInformation>,
+    pub(crate) docker_information: Option<Result<DockerInformation>>,
+    pub(crate) cosign_information: Option<Result<CosignInformation>>,
     pub(crate) cosign_verify: Option<Result<cosign::CosignVerify>>,
+
+    /// Platform of a multi-arch image whose digest is shown.
+    pub(crate) platform: Option<String>,
+
+    /// How long the fetched information is cached.
+    pub(crate) cache_ttl: Duration,
 }
 
 #[derive(Debug, Template)]
 #[template(path = "response_trivy.html")]
 pub(crate) struct TrivyResponse {
     pub(crate) information: Result<TrivyInformation>,
+
+    /// References shown per vulnerability, the rest are collapsed.
+    pub(crate) max_references: usize,
+
+    /// How long the scan is cached.
+    pub(crate) cache_ttl: Duration,
+
+    pub(crate) severity_weights: SeverityWeights,
+
+    /// CVSS version whose score is shown first.
+    pub(crate) cvss_version: CvssVersion,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRedisValue, ToRedisArgs, PartialEq)]
+/// Markdown summary of a scan for pasting into pull requests or issues.
+#[derive(Debug, Template)]
+#[template(path = "trivy.md")]
+pub(crate) struct TrivyMarkdown<'a> {
+    pub(crate) information: &'a TrivyInformation,
+    pub(crate) severity_weights: SeverityWeights,
+}
+
+#[derive(
+    Debug, Clone, Serialize, Deserialize, FromRedisValue, ToRedisArgs, PartialEq, ToSchema,
+)]
 pub(crate) struct TrivyInformation {
     vulnerabilities: BTreeSet<Vulnerability>,
     severity_count: SeverityCount,
+
+    #[serde(default)]
+    misconfigurations: BTreeSet<Misconfiguration>,
+
+    #[serde(default)]
+    scanners: Vec<ScannerStatus>,
+
+    #[serde(default)]
+    digest: Option<String>,
+
     fetch_time: DateTime<Utc>,
+
+    /// The digest of the image is trusted and the image was not scanned.
+    #[serde(default)]
+    trusted: bool,
+
+    /// Served from the cache as a rescan was requested within the refresh
+    /// cooldown.
+    #[serde(skip)]
+    cooling_down: bool,
+
+    /// Served from the cache as the trivy server could not rescan the image.
+    #[serde(skip)]
+    server_unavailable: bool,
+}
+
+/// Controls of a compliance spec checked against an image.
+#[derive(Debug, Template)]
+#[template(path = "response_compliance.html")]
+pub(crate) struct ComplianceResponse {
+    pub(crate) information: Result<ComplianceInformation>,
+
+    /// How long the report is cached.
+    pub(crate) cache_ttl: Duration,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRedisValue, ToRedisArgs, PartialEq)]
+pub(crate) struct ComplianceInformation {
+    pub(crate) report: ComplianceReport,
+    pub(crate) fetch_time: DateTime<Utc>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRedisValue, ToRedisArgs, PartialEq)]
@@ -87,86 +185,278 @@ pub(crate) struct CosignInformation {
 #[derive(Debug, Serialize, Deserialize)]
 pub(crate) struct DockerInformation {
     response: DockerResponse,
+
+    /// Labels of the image config, empty when the image has none.
+    #[serde(default)]
+    pub(crate) labels: BTreeMap<String, String>,
+
     fetch_time: DateTime<Utc>,
 }
 
+/// A single row of the docker details panel, either a platform of a manifest
+/// list or a blob of an image manifest.
+#[derive(Debug, PartialEq)]
+pub(crate) struct ManifestDetail {
+    pub(crate) name: String,
+    pub(crate) media_type: String,
+    pub(crate) digest: String,
+    pub(crate) size: u64,
+}
+
 #[tracing::instrument]
 pub(crate) async fn image(
     state: &AppState,
     form: SubmitFormImage,
 ) -> Result<ImageResponse, eyre::Error> {
     let image: Image = form.image.trim().parse()?;
+    state.allowed_registries.check(&image)?;
 
-    let docker_and_cosign_manifest = {
-        let redis_client = state.redis_client.clone();
-
-        task::spawn(
+    let docker_and_cosign_manifest = if state.disable_docker_info {
+        None
+    } else {
+        Some(
             fetch_docker_and_cosign_manifest(
-                state.docker_registry_client.clone(),
+                super::registry_client(state, &image).await,
+                state.registry_limits.clone(),
+                state.cosign_fetches.clone(),
                 image.clone(),
-                redis_client,
+                state.cache.clone(),
+                form.refresh,
             )
             .instrument(info_span!("fetch_docker_and_cosign_manifest")),
         )
     };
 
-    let cosign_verify = task::spawn(
-        fetch_cosign_verify(form.cosign_key, image.clone())
-            .instrument(info_span!("fetch_cosign_verify")),
-    );
+    let lookups = {
+        let image = image.clone();
+        let binary = state.cosign_binary.clone();
+        let private_infrastructure = state.cosign_private_infrastructure;
+        let timeout = state.trivy_config.timeout;
+
+        Box::pin(async move {
+            let (docker_information, cosign_information) = match docker_and_cosign_manifest {
+                Some(docker_and_cosign_manifest) => {
+                    let (docker_information, cosign_information) = docker_and_cosign_manifest.await;
+
+                    (Some(docker_information), Some(cosign_information))
+                }
+
+                None => (None, None),
+            };
+
+            // verify the digest the other panels describe instead of the tag
+            // which might have moved in the meantime
+            let reference = match docker_information
+                .as_ref()
+                .and_then(|docker_information| docker_information.as_ref().ok())
+                .and_then(|docker_information| docker_information.response.digest.as_deref())
+            {
+                Some(digest) => cosign::digest_reference(&image, digest),
+                None => image.to_string(),
+            };
+
+            let cosign_verify = fetch_cosign_verify(
+                form.cosign_key,
+                form.cosign_identity,
+                form.cosign_issuer,
+                reference,
+                binary,
+                private_infrastructure,
+                timeout,
+            )
+            .instrument(info_span!("fetch_cosign_verify"))
+            .await;
+
+            (docker_information, cosign_information, cosign_verify)
+        })
+    };
 
-    let (docker_information, cosign_information) = docker_and_cosign_manifest.await?;
-    let cosign_verify = cosign_verify.await?;
+    // the request waits here while the fan-out limit is reached, the slot is
+    // held until cosign verified the image
+    let (docker_information, cosign_information, cosign_verify) =
+        state.fan_out_limit.spawn(lookups).await?.await?;
 
     let response = ImageResponse {
         image,
         docker_information,
         cosign_information,
         cosign_verify,
+        platform: Some(form.platform.trim())
+            .filter(|platform| !platform.is_empty())
+            .map(ToString::to_string),
+        cache_ttl: state.cache.ttl,
     };
 
     Ok(response)
 }
 
 #[tracing::instrument]
-async fn fetch_docker_and_cosign_manifest(
-    docker_registry_client: DockerRegistryClient,
+pub(super) async fn fetch_docker_and_cosign_manifest(
+    registry_client: RegistryClient,
+    registry_limits: RegistryLimits,
+    cosign_fetches: CosignFetches,
     image: Image,
-    redis_client: Option<redis::Client>,
+    cache: Cache,
+    force: bool,
 ) -> (Result<DockerInformation>, Result<CosignInformation>) {
     let docker_manifest = DockerInformationFetcher {
-        docker_registry_client: &docker_registry_client,
-        image: &image,
+        registry_client: registry_client.clone(),
+        registry_limits,
+        image: image.clone(),
     }
-    .cache_or_fetch(redis_client.as_ref())
+    .cache_or_fetch(&cache, force)
     .await
     .context("failed to fetch docker manifest");
 
-    if let Err(err) = &docker_manifest {
-        error!("{err}");
-    }
+    let cosign_manifest = match &docker_manifest {
+        Ok(docker_manifest) => CosignInformationFetcher {
+            registry_client,
+            cosign_fetches,
+            image,
+            digest: docker_manifest.response.digest.clone(),
+        }
+        .cache_or_fetch(&cache, force)
+        .await
+        .context("failed to get cosign manifest"),
 
-    let cosign_manifest = CosignInformationFetcher {
-        docker_registry_client: &docker_registry_client,
-        image: &image,
-        docker_manifest: &docker_manifest,
-    }
-    .cache_or_fetch(redis_client.as_ref())
-    .await
-    .context("failed to get cosign manifest");
+        Err(err) => {
+            error!("{err}");
+
+            Err(MissingDockerManifest).context("failed to get cosign manifest")
+        }
+    };
 
     (docker_manifest, cosign_manifest)
 }
 
+/// Returned when a requested platform is not part of the manifest list of
+/// the image.
+#[derive(Debug, PartialEq)]
+pub(crate) struct PlatformNotAvailable {
+    platform: String,
+    available: Vec<String>,
+}
+
+impl std::fmt::Display for PlatformNotAvailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.available.is_empty() {
+            return write!(
+                f,
+                "platform {} is not available, the image is not a multi-arch image",
+                self.platform
+            );
+        }
+
+        write!(
+            f,
+            "platform {} is not available, available platforms: {}",
+            self.platform,
+            self.available.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for PlatformNotAvailable {}
+
+/// Cosign manifests are looked up by the digest of the docker manifest so
+/// they can not be fetched without it.
+#[derive(Debug)]
+pub(crate) struct MissingDockerManifest;
+
+impl std::fmt::Display for MissingDockerManifest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Failed to get docker manifest")
+    }
+}
+
+impl std::error::Error for MissingDockerManifest {}
+
+/// Nothing is verified unless a key or an identity is submitted.
 #[tracing::instrument]
 async fn fetch_cosign_verify(
     cosign_key: String,
-    image: Image,
+    cosign_identity: String,
+    cosign_issuer: String,
+    reference: String,
+    binary: PathBuf,
+    private_infrastructure: bool,
+    timeout: Option<std::time::Duration>,
 ) -> Option<Result<cosign::CosignVerify, eyre::Error>> {
-    if cosign_key.is_empty() {
+    if cosign_key.is_empty() && cosign_identity.is_empty() && cosign_issuer.is_empty() {
         None
     } else {
-        Some(cosign_verify(&cosign_key, &image).await)
+        Some(
+            cosign_verify(
+                Some(&cosign_key),
+                Some(&cosign_identity),
+                Some(&cosign_issuer),
+                &reference,
+                &binary,
+                private_infrastructure,
+                timeout,
+            )
+            .await,
+        )
+    }
+}
+
+/// Renders a trivy template and falls back to a plain summary of the severity
+/// counts when the template fails so a template bug does not hide all
+/// results.
+pub(crate) fn render_trivy(
+    template: &impl Template,
+    information: Option<&TrivyInformation>,
+) -> askama::Result<String> {
+    template.render().or_else(|err| {
+        let Some(information) = information else {
+            return Err(err);
+        };
+
+        error!("failed to render trivy template, rendering fallback: {err}");
+
+        Ok(information.fallback().into_string())
+    })
+}
+
+impl ImageResponse {
+    /// Digest of the requested platform, `None` when no platform was
+    /// requested or the manifest could not be fetched.
+    pub(crate) fn platform_digest(&self) -> Option<Result<&str, PlatformNotAvailable>> {
+        let platform = self.platform.as_deref()?;
+
+        match &self.docker_information {
+            Some(Ok(docker_information)) => Some(docker_information.platform_digest(platform)),
+            _ => None,
+        }
+    }
+
+    /// Digest the image tag resolved to in the docker registry.
+    pub(crate) fn resolved_digest(&self) -> Option<&str> {
+        match &self.docker_information {
+            Some(Ok(docker_information)) => docker_information.response.digest.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Error of the docker manifest when the cosign manifest failed only
+    /// because of it, both are then shown as a single error.
+    pub(crate) fn metadata_error(&self) -> Option<&eyre::Report> {
+        match (&self.docker_information, &self.cosign_information) {
+            (Some(Err(docker_err)), Some(Err(cosign_err)))
+                if cosign_err.downcast_ref::<MissingDockerManifest>().is_some() =>
+            {
+                Some(docker_err)
+            }
+
+            _ => None,
+        }
+    }
+
+    /// Returns true when a verified signature was made for a different
+    /// digest than the one the image resolved to.
+    pub(crate) fn digest_mismatch(&self, signature: &cosign::VerifySignature) -> bool {
+        self.resolved_digest()
+            .is_some_and(|digest| digest != signature.critical.image.digest)
     }
 }
 
@@ -175,56 +465,1537 @@ impl DockerInformation {
         Utc::now().signed_duration_since(self.fetch_time)
     }
 
-    pub(crate) fn expires(&self) -> DateTime<Utc> {
-        self.fetch_time + Duration::seconds(REDIS_TTL)
+    pub(crate) fn expires(&self, ttl: Duration) -> DateTime<Utc> {
+        self.fetch_time + ttl
+    }
+
+    pub(crate) fn expires_duration(&self, ttl: Duration) -> Duration {
+        Utc::now().signed_duration_since(self.expires(ttl))
+    }
+}
+
+/// Platform in the form trivy's `--platform` flag expects, e.g.
+/// `linux/arm64`.
+fn platform_name(platform: &docker_registry_client::manifest::Platform) -> String {
+    format!(
+        "{os}/{architecture}",
+        os = platform.os,
+        architecture = platform.architecture
+    )
+}
+
+/// Platforms of a manifest list, single image manifests have none.
+pub(crate) fn platforms(manifest: &DockerManifest) -> Vec<String> {
+    match manifest {
+        DockerManifest::List(list) => list
+            .manifests
+            .iter()
+            .map(|entry| platform_name(&entry.platform))
+            .collect(),
+
+        DockerManifest::Image(_) | DockerManifest::Single(_) => Vec::new(),
+    }
+}
+
+impl DockerInformation {
+    pub(crate) fn platforms(&self) -> Vec<String> {
+        platforms(&self.response.manifest)
+    }
+
+    /// Digest the image resolved to, the digest of the manifest list for
+    /// multi-arch images.
+    pub(crate) fn digest(&self) -> Option<&str> {
+        self.response.digest.as_deref()
+    }
+
+    /// Digest of the image of the platform in the manifest list.
+    pub(crate) fn platform_digest(&self, platform: &str) -> Result<&str, PlatformNotAvailable> {
+        let entries = match &self.response.manifest {
+            DockerManifest::List(list) => list.manifests.as_slice(),
+            DockerManifest::Image(_) | DockerManifest::Single(_) => &[],
+        };
+
+        entries
+            .iter()
+            .find(|entry| platform_name(&entry.platform) == platform)
+            .map(|entry| entry.digest.as_str())
+            .ok_or_else(|| PlatformNotAvailable {
+                platform: platform.to_string(),
+                available: self.platforms(),
+            })
+    }
+
+    pub(crate) fn details(&self) -> Vec<ManifestDetail> {
+        match &self.response.manifest {
+            DockerManifest::List(list) => list
+                .manifests
+                .iter()
+                .map(|entry| ManifestDetail {
+                    name: platform_name(&entry.platform),
+                    media_type: entry.media_type.clone(),
+                    digest: entry.digest.clone(),
+                    size: entry.size,
+                })
+                .collect(),
+
+            DockerManifest::Image(image) => std::iter::once(ManifestDetail {
+                name: "config".to_string(),
+                media_type: image.config.media_type.clone(),
+                digest: image.config.digest.clone(),
+                size: image.config.size,
+            })
+            .chain(
+                image
+                    .layers
+                    .iter()
+                    .enumerate()
+                    .map(|(index, layer)| ManifestDetail {
+                        name: format!("layer {}", index + 1),
+                        media_type: layer.media_type.clone(),
+                        digest: layer.digest.clone(),
+                        size: layer.size,
+                    }),
+            )
+            .collect(),
+
+            DockerManifest::Single(_) => Vec::new(),
+        }
+    }
+
+    /// Manifest lists describe the sizes of the platform manifests, not of
+    /// the images.
+    pub(crate) fn is_manifest_list(&self) -> bool {
+        matches!(self.response.manifest, DockerManifest::List(_))
+    }
+
+    /// Size of the image, its config and layers, or of the platform
+    /// manifests for manifest lists.
+    pub(crate) fn total_size(&self) -> Option<u64> {
+        match &self.response.manifest {
+            DockerManifest::Image(image) => {
+                Some(image.config.size + image.layers.iter().map(|layer| layer.size).sum::<u64>())
+            }
+
+            DockerManifest::List(list) => Some(list.manifests.iter().map(|entry| entry.size).sum()),
+
+            DockerManifest::Single(_) => None,
+        }
     }
+}
 
-    pub(crate) fn expires_duration(&self) -> Duration {
-        Utc::now().signed_duration_since(self.expires())
+impl ManifestDetail {
+    pub(crate) fn short_digest(&self) -> &str {
+        let end = self.digest.find(':').map_or(0, |index| index + 1) + 12;
+
+        self.digest.get(..end).unwrap_or(&self.digest)
     }
 }
 
+/// Collapses findings of the same vulnerability in the same package version
+/// that trivy reports once per target, e.g. a library vendored into two
+/// binaries.
+fn dedup_vulnerabilities(
+    vulnerabilities: impl Iterator<Item = Vulnerability>,
+) -> BTreeSet<Vulnerability> {
+    let mut unique = BTreeMap::<(String, String, String), Vulnerability>::new();
+
+    for vulnerability in vulnerabilities {
+        let key = (
+            vulnerability.id.clone(),
+            vulnerability.pkg_name.clone(),
+            vulnerability.installed_version.clone(),
+        );
+
+        match unique.entry(key) {
+            Entry::Vacant(entry) => {
+                entry.insert(vulnerability);
+            }
+
+            Entry::Occupied(mut entry) => entry.get_mut().merge_duplicate(vulnerability),
+        }
+    }
+
+    unique.into_values().collect()
+}
+
 impl TrivyInformation {
+    pub(crate) fn new(trivy_result: TrivyResult, scanners: &[Scanner]) -> Self {
+        let scanners = scanner_status(
+            &trivy_result.results,
+            scanners,
+            &trivy_result.failed_scanners,
+        );
+        let digest = trivy_result.metadata.digest().map(ToString::to_string);
+
+        let misconfigurations = trivy_result
+            .results
+            .iter()
+            .filter_map(|result| result.misconfigurations.clone())
+            .flatten()
+            .collect::<BTreeSet<Misconfiguration>>();
+
+        let vulnerabilities = dedup_vulnerabilities(
+            trivy_result
+                .results
+                .into_iter()
+                .filter_map(|result| result.vulnerabilities)
+                .flatten(),
+        );
+
+        let severity_count = get_vulnerabilities_count(vulnerabilities.clone());
+
+        Self {
+            vulnerabilities,
+            severity_count,
+            misconfigurations,
+            scanners,
+            digest,
+            fetch_time: Utc::now(),
+            trusted: false,
+            cooling_down: false,
+            server_unavailable: false,
+        }
+    }
+
+    /// Stands in for the scan of an image whose digest is trusted.
+    pub(crate) fn trusted(digest: String) -> Self {
+        Self {
+            vulnerabilities: BTreeSet::new(),
+            severity_count: SeverityCount::default(),
+            misconfigurations: BTreeSet::new(),
+            scanners: Vec::new(),
+            digest: Some(digest),
+            fetch_time: Utc::now(),
+            trusted: true,
+            cooling_down: false,
+            server_unavailable: false,
+        }
+    }
+
+    pub(crate) fn trend_point(&self) -> TrendPoint {
+        TrendPoint {
+            digest: self.digest.clone(),
+            scan_time: self.fetch_time,
+            severity_count: self.severity_count.clone(),
+        }
+    }
+
+    /// Combines the outputs of separate scanner runs of the same image. The
+    /// combined output is as old as its oldest part.
+    /// The part of a scan that ran several scanners which the given scanner
+    /// reported, as if trivy had only run that scanner.
+    pub(crate) fn for_scanner(&self, scanner: Scanner) -> Self {
+        let vulnerabilities = if scanner == Scanner::Vuln {
+            self.vulnerabilities.clone()
+        } else {
+            BTreeSet::new()
+        };
+
+        let misconfigurations = if scanner == Scanner::Misconfig {
+            self.misconfigurations.clone()
+        } else {
+            BTreeSet::new()
+        };
+
+        Self {
+            severity_count: get_vulnerabilities_count(vulnerabilities.clone()),
+            vulnerabilities,
+            misconfigurations,
+            scanners: self
+                .scanners
+                .iter()
+                .filter(|status| status.scanner == scanner)
+                .cloned()
+                .collect(),
+            digest: self.digest.clone(),
+            fetch_time: self.fetch_time,
+            trusted: self.trusted,
+            cooling_down: false,
+            server_unavailable: false,
+        }
+    }
+
+    pub(crate) fn merge(parts: Vec<Self>) -> Self {
+        let mut merged = Self {
+            vulnerabilities: BTreeSet::new(),
+            severity_count: SeverityCount::default(),
+            misconfigurations: BTreeSet::new(),
+            scanners: Vec::new(),
+            digest: None,
+            fetch_time: Utc::now(),
+            trusted: false,
+            cooling_down: false,
+            server_unavailable: false,
+        };
+
+        for mut part in parts {
+            merged.vulnerabilities.append(&mut part.vulnerabilities);
+            merged.misconfigurations.append(&mut part.misconfigurations);
+            merged.scanners.append(&mut part.scanners);
+            merged.digest = merged.digest.or(part.digest);
+            merged.fetch_time = merged.fetch_time.min(part.fetch_time);
+            merged.trusted |= part.trusted;
+            merged.cooling_down |= part.cooling_down;
+            merged.server_unavailable |= part.server_unavailable;
+        }
+
+        merged.severity_count = get_vulnerabilities_count(merged.vulnerabilities.clone());
+
+        merged
+    }
+
+    /// Removes vulnerabilities with one of the given statuses, e.g. to hide
+    /// issues the vendor will not fix.
+    pub(super) fn hide_statuses(&mut self, hidden: &[VulnerabilityStatus]) {
+        if hidden.is_empty() {
+            return;
+        }
+
+        self.vulnerabilities.retain(|vulnerability| {
+            vulnerability
+                .status
+                .is_none_or(|status| !hidden.contains(&status))
+        });
+
+        self.severity_count = get_vulnerabilities_count(self.vulnerabilities.clone());
+    }
+
+    /// Removes vulnerabilities below the given severity, `Severity` orders the
+    /// most severe first.
+    pub(super) fn retain_min_severity(&mut self, min_severity: Severity) {
+        self.vulnerabilities
+            .retain(|vulnerability| vulnerability.severity <= min_severity);
+
+        self.severity_count = get_vulnerabilities_count(self.vulnerabilities.clone());
+    }
+
+    /// Rates vulnerabilities trivy reported as unknown by their CVSS score
+    /// so they are shown and counted with a derived severity.
+    pub(super) fn derive_severities(&mut self, sources: &[String], version: CvssVersion) {
+        if sources.is_empty() {
+            return;
+        }
+
+        self.vulnerabilities = std::mem::take(&mut self.vulnerabilities)
+            .into_iter()
+            .map(|mut vulnerability| {
+                vulnerability.derive_severity(sources, version);
+                vulnerability
+            })
+            .collect();
+
+        self.severity_count = get_vulnerabilities_count(self.vulnerabilities.clone());
+    }
+
+    /// Reclassifies severities according to the mapping, the counts follow
+    /// the reclassified severities.
+    pub(super) fn remap_severities(&mut self, mapping: &SeverityMapping) {
+        if mapping.is_empty() {
+            return;
+        }
+
+        self.vulnerabilities = std::mem::take(&mut self.vulnerabilities)
+            .into_iter()
+            .map(|mut vulnerability| {
+                vulnerability.remap_severity(mapping);
+                vulnerability
+            })
+            .collect();
+
+        self.severity_count = get_vulnerabilities_count(self.vulnerabilities.clone());
+    }
+
+    /// Merges findings that are reported under an advisory id and its CVE
+    /// alias for the same package so they are only shown and counted once.
+    pub(super) fn canonicalize_ids(&mut self) {
+        let mut canonical = BTreeMap::<(String, String, String, Option<String>), _>::new();
+
+        for mut vulnerability in std::mem::take(&mut self.vulnerabilities) {
+            let renamed = vulnerability.canonicalize_id();
+
+            let key = (
+                vulnerability.id.clone(),
+                vulnerability.pkg_name.clone(),
+                vulnerability.installed_version.clone(),
+                vulnerability.pkg_path.clone(),
+            );
+
+            match canonical.entry(key) {
+                Entry::Vacant(entry) => {
+                    entry.insert((vulnerability, renamed));
+                }
+
+                Entry::Occupied(mut entry) => {
+                    let (existing, existing_renamed) = entry.get_mut();
+
+                    // prefer the finding trivy reported under the CVE itself
+                    if *existing_renamed && !renamed {
+                        vulnerability.aliases.append(&mut existing.aliases);
+                        *existing = vulnerability;
+                        *existing_renamed = false;
+                    } else {
+                        existing.aliases.append(&mut vulnerability.aliases);
+                    }
+                }
+            }
+        }
+
+        self.vulnerabilities = canonical
+            .into_values()
+            .map(|(mut vulnerability, _)| {
+                vulnerability.aliases.remove(&vulnerability.id);
+                vulnerability
+            })
+            .collect();
+
+        self.severity_count = get_vulnerabilities_count(self.vulnerabilities.clone());
+    }
+
+    /// Rebuilds a minimal trivy report from the reduced information. The
+    /// original results are merged into a single result for the image.
+    pub(crate) fn to_trivy_result(&self, image: &Image) -> TrivyResult {
+        let repo_digest = self
+            .digest
+            .as_ref()
+            .map(|digest| cosign::digest_reference(image, digest));
+
+        TrivyResult {
+            schema_version: Some(2),
+            artifact_name: Some(image.to_string()),
+            results: vec![Results {
+                target: Some(image.to_string()),
+                class: None,
+                vulnerabilities: Some(self.vulnerabilities.iter().cloned().collect()),
+                misconfigurations: (!self.misconfigurations.is_empty())
+                    .then(|| self.misconfigurations.iter().cloned().collect()),
+            }],
+            metadata: Metadata {
+                repo_digests: repo_digest.into_iter().collect(),
+            },
+            failed_scanners: Vec::new(),
+        }
+    }
+
+    /// Vulnerabilities as a SARIF log, one result per vulnerability.
+    pub(crate) fn to_sarif(&self) -> sarif::Sarif {
+        let results = self
+            .vulnerabilities
+            .iter()
+            .map(|vulnerability| {
+                let package = format!(
+                    "{} {}",
+                    vulnerability.pkg_name, vulnerability.installed_version
+                );
+
+                let text = match &vulnerability.title {
+                    Some(title) => format!("{title} ({package})"),
+                    None => format!("{} ({package})", vulnerability.id),
+                };
+
+                sarif::SarifResult {
+                    rule_id: vulnerability.id.clone(),
+                    level: vulnerability.severity.into(),
+                    message: sarif::Message { text },
+                }
+            })
+            .collect();
+
+        sarif::Sarif::new(results)
+    }
+
+    /// GitHub Actions workflow commands annotating the run, one line per
+    /// vulnerability. Critical and high vulnerabilities are errors, all
+    /// others warnings.
+    pub(crate) fn to_github_annotations(&self) -> String {
+        self.vulnerabilities
+            .iter()
+            .fold(String::new(), |mut annotations, vulnerability| {
+                let command = match vulnerability.severity {
+                    Severity::Critical | Severity::High => "error",
+                    Severity::Medium | Severity::Low | Severity::Unknown => "warning",
+                };
+
+                let package = format!(
+                    "{} {}",
+                    vulnerability.pkg_name, vulnerability.installed_version
+                );
+
+                let message = match &vulnerability.title {
+                    Some(title) => format!("{title} ({package})"),
+                    None => format!("{} ({package})", vulnerability.id),
+                };
+
+                let _ = writeln!(
+                    annotations,
+                    "::{command} title={title}::{message}",
+                    title = escape_annotation_property(&format!(
+                        "{} {}",
+                        vulnerability.severity, vulnerability.id
+                    )),
+                    message = escape_annotation_data(&message),
+                );
+
+                annotations
+            })
+    }
+
+    /// Minimal summary that does not depend on the askama templates.
+    fn fallback(&self) -> Markup {
+        let count = &self.severity_count;
+
+        html! {
+            h3 { "Vulnerabilities" }
+            p { "The full report could not be rendered, showing the summary only." }
+            ul class="severity_count" {
+                li class="critical" title="Critical" { (count.critical) }
+                li class="high" title="High" { (count.high) }
+                li class="medium" title="Medium" { (count.medium) }
+                li class="low" title="Low" { (count.low) }
+                li class="unknown" title="Unknown" { (count.unknown) }
+            }
+        }
+    }
+
+    pub(crate) fn digest(&self) -> Option<&str> {
+        self.digest.as_deref()
+    }
+
+    pub(crate) fn vulnerabilities(&self) -> &BTreeSet<Vulnerability> {
+        &self.vulnerabilities
+    }
+
+    pub(crate) fn severity_count(&self) -> &SeverityCount {
+        &self.severity_count
+    }
+
+    /// Vulnerabilities grouped by the name of the affected package, sorted by
+    /// severity then ID within each package.
+    pub(crate) fn vulnerabilities_by_package(&self) -> BTreeMap<String, Vec<Vulnerability>> {
+        let mut packages = BTreeMap::<_, Vec<_>>::new();
+
+        for vulnerability in &self.vulnerabilities {
+            packages
+                .entry(vulnerability.pkg_name.clone())
+                .or_default()
+                .push(vulnerability.clone());
+        }
+
+        for vulnerabilities in packages.values_mut() {
+            vulnerabilities
+                .sort_by(|a, b| a.severity.cmp(&b.severity).then_with(|| a.id.cmp(&b.id)));
+        }
+
+        packages
+    }
+
+    /// Vulnerabilities ordered by their highest CVSS score, unscored ones
+    /// last. Equal scores are ordered by severity.
+    pub(crate) fn vulnerabilities_by_score(&self) -> Vec<&Vulnerability> {
+        let mut vulnerabilities = self.vulnerabilities.iter().collect::<Vec<_>>();
+
+        vulnerabilities.sort_by(|a, b| {
+            b.highest_score()
+                .cmp(&a.highest_score())
+                .then_with(|| a.severity.cmp(&b.severity))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        vulnerabilities
+    }
+
+    /// Vulnerabilities grouped by the image layer trivy attributes them to.
+    /// Vulnerabilities without a layer are left out.
+    pub(crate) fn vulnerabilities_by_layer(&self) -> BTreeMap<&str, Vec<&Vulnerability>> {
+        let mut layers = BTreeMap::<_, Vec<_>>::new();
+
+        for vulnerability in &self.vulnerabilities {
+            if let Some(layer) = vulnerability.layer.as_ref().and_then(|layer| layer.id()) {
+                layers.entry(layer).or_default().push(vulnerability);
+            }
+        }
+
+        layers
+    }
+
+    pub(crate) fn failed_scanners(&self) -> Vec<Scanner> {
+        self.scanners
+            .iter()
+            .filter(|status| !status.ran)
+            .map(|status| status.scanner)
+            .collect()
+    }
+
     pub(crate) fn fetch_duration(&self) -> Duration {
         Utc::now().signed_duration_since(self.fetch_time)
     }
 
-    pub(crate) fn expires(&self) -> DateTime<Utc> {
-        self.fetch_time + Duration::seconds(REDIS_TTL)
+    pub(crate) fn expires(&self, ttl: Duration) -> DateTime<Utc> {
+        self.fetch_time + ttl
     }
 
-    pub(crate) fn expires_duration(&self) -> Duration {
-        Utc::now().signed_duration_since(self.expires())
+    pub(crate) fn expires_duration(&self, ttl: Duration) -> Duration {
+        Utc::now().signed_duration_since(self.expires(ttl))
     }
 }
 
 impl CosignInformation {
+    pub(crate) fn cosign(&self) -> Option<&cosign::Cosign> {
+        self.cosign.as_ref()
+    }
+
+    pub(crate) fn fetch_duration(&self) -> Duration {
+        Utc::now().signed_duration_since(self.fetch_time)
+    }
+
+    pub(crate) fn expires(&self, ttl: Duration) -> DateTime<Utc> {
+        self.fetch_time + ttl
+    }
+
+    pub(crate) fn expires_duration(&self, ttl: Duration) -> Duration {
+        Utc::now().signed_duration_since(self.expires(ttl))
+    }
+}
+
+impl ComplianceInformation {
     pub(crate) fn fetch_duration(&self) -> Duration {
         Utc::now().signed_duration_since(self.fetch_time)
     }
 
-    pub(crate) fn expires(&self) -> DateTime<Utc> {
-        self.fetch_time + Duration::seconds(REDIS_TTL)
+    pub(crate) fn expires(&self, ttl: Duration) -> DateTime<Utc> {
+        self.fetch_time + ttl
     }
 
-    pub(crate) fn expires_duration(&self) -> Duration {
-        Utc::now().signed_duration_since(self.expires())
+    pub(crate) fn expires_duration(&self, ttl: Duration) -> Duration {
+        Utc::now().signed_duration_since(self.expires(ttl))
     }
 }
 
+/// Workflow command messages end at a line break, so line breaks and the
+/// escape character itself are percent encoded.
+fn escape_annotation_data(data: &str) -> String {
+    data.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Properties additionally end at `,` and `::`.
+fn escape_annotation_property(property: &str) -> String {
+    escape_annotation_data(property)
+        .replace(':', "%3A")
+        .replace(',', "%2C")
+}
+
 #[cfg(test)]
 #[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
 mod tests {
-    use std::collections::BTreeSet;
+    use std::collections::{
+        BTreeMap,
+        BTreeSet,
+    };
 
+    use askama::Template;
+    use chrono::Duration;
+    use docker_registry_client::{
+        Manifest as DockerManifest,
+        Response as DockerResponse,
+    };
+    use eyre::WrapErr;
     use redis::AsyncCommands;
 
-    use crate::handler::trivy::{
-        TrivyResult,
-        Vulnerability,
-        get_vulnerabilities_count,
+    use super::cache::DEFAULT_REDIS_TTL;
+    use crate::handler::{
+        image_config::parse_labels,
+        trivy::{
+            CvssVersion,
+            MisconfigurationStatus,
+            Scanner,
+            Severity,
+            SeverityCount,
+            SeverityWeights,
+            TrivyResult,
+            Vulnerability,
+            VulnerabilityStatus,
+            get_vulnerabilities_count,
+        },
     };
 
+    #[test]
+    fn docker_details_multi_arch() {
+        const DATA: &str = include_str!("resources/tests/trivy-manifest-response.json");
+
+        let manifest = serde_json::from_str::<DockerManifest>(DATA).unwrap();
+
+        let response = super::ImageResponse {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            docker_information: Some(Ok(super::DockerInformation {
+                response: DockerResponse {
+                    digest: None,
+                    manifest,
+                },
+                labels: BTreeMap::new(),
+                fetch_time: chrono::Utc::now(),
+            })),
+            cosign_information: Some(Err(eyre::eyre!("not fetched"))),
+            cosign_verify: None,
+            platform: None,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+        };
+
+        let rendered = response.render().unwrap();
+
+        for platform in ["linux/amd64", "linux/arm64", "linux/ppc64le", "linux/s390x"] {
+            assert!(rendered.contains(platform), "missing platform {platform}");
+        }
+
+        assert_eq!(4, rendered.matches("1.13 KiB").count());
+        assert!(rendered.contains("sha256:4704989dd70b"));
+
+        // the sizes of the platform manifests do not add up to an image size
+        assert!(rendered.contains("Manifest Size"));
+        assert!(rendered.contains("Total manifest size"));
+        assert!(rendered.contains("4.53 KiB"));
+    }
+
+    #[test]
+    fn docker_details_total_size() {
+        const DATA: &str = r#"{
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "size": 1000,
+                "digest": "sha256:1111111111111111111111111111111111111111111111111111111111111111"
+            },
+            "layers": [
+                {
+                    "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                    "size": 2000,
+                    "digest": "sha256:2222222222222222222222222222222222222222222222222222222222222222"
+                },
+                {
+                    "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                    "size": 3000,
+                    "digest": "sha256:3333333333333333333333333333333333333333333333333333333333333333"
+                }
+            ]
+        }"#;
+
+        let information = super::DockerInformation {
+            response: DockerResponse {
+                digest: None,
+                manifest: serde_json::from_str::<DockerManifest>(DATA).unwrap(),
+            },
+            labels: BTreeMap::new(),
+            fetch_time: chrono::Utc::now(),
+        };
+
+        assert_eq!(Some(6000), information.total_size());
+        assert!(!information.is_manifest_list());
+    }
+
+    #[test]
+    fn docker_details_labels() {
+        const MANIFEST: &str = include_str!("resources/tests/trivy-manifest-response.json");
+        const CONFIG: &str = include_str!("resources/tests/image_config.json");
+
+        let response = super::ImageResponse {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            docker_information: Some(Ok(super::DockerInformation {
+                response: DockerResponse {
+                    digest: None,
+                    manifest: serde_json::from_str::<DockerManifest>(MANIFEST).unwrap(),
+                },
+                labels: parse_labels(CONFIG).unwrap(),
+                fetch_time: chrono::Utc::now(),
+            })),
+            cosign_information: None,
+            cosign_verify: None,
+            platform: None,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+        };
+
+        let rendered = response.render().unwrap();
+
+        assert!(rendered.contains("<h3>Labels</h3>"));
+        assert!(rendered.contains("<td>org.opencontainers.image.source</td>"));
+        assert!(rendered.contains("<td>https://github.com/aquasecurity/trivy</td>"));
+
+        // images without labels do not get an empty table
+        let response = super::ImageResponse {
+            docker_information: Some(Ok(super::DockerInformation {
+                response: DockerResponse {
+                    digest: None,
+                    manifest: serde_json::from_str::<DockerManifest>(MANIFEST).unwrap(),
+                },
+                labels: BTreeMap::new(),
+                fetch_time: chrono::Utc::now(),
+            })),
+            ..response
+        };
+
+        assert!(!response.render().unwrap().contains("<h3>Labels</h3>"));
+    }
+
+    #[test]
+    fn platform_digest() {
+        const MANIFEST: &str = include_str!("resources/tests/trivy-manifest-response.json");
+
+        let manifest = serde_json::from_str::<DockerManifest>(MANIFEST).unwrap();
+        let arm64 = match &manifest {
+            DockerManifest::List(list) => list.manifests[1].digest.clone(),
+            _ => unreachable!("fixture is a manifest list"),
+        };
+
+        let response = super::ImageResponse {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            docker_information: Some(Ok(super::DockerInformation {
+                response: DockerResponse {
+                    digest: None,
+                    manifest,
+                },
+                labels: BTreeMap::new(),
+                fetch_time: chrono::Utc::now(),
+            })),
+            cosign_information: None,
+            cosign_verify: None,
+            platform: Some("linux/arm64".to_string()),
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+        };
+
+        assert_eq!(Some(Ok(arm64.as_str())), response.platform_digest());
+        assert!(
+            response
+                .render()
+                .unwrap()
+                .contains(&format!("Platform Digest (linux/arm64): {arm64}"))
+        );
+
+        let response = super::ImageResponse {
+            platform: Some("windows/amd64".to_string()),
+            ..response
+        };
+
+        let err = response.platform_digest().unwrap().unwrap_err();
+        assert_eq!(
+            "platform windows/amd64 is not available, available platforms: linux/amd64, \
+             linux/arm64, linux/ppc64le, linux/s390x",
+            err.to_string()
+        );
+        assert!(response.render().unwrap().contains(&err.to_string()));
+    }
+
+    #[test]
+    fn metadata_error() {
+        let response = super::ImageResponse {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            docker_information: Some(Err(eyre::eyre!("manifest unknown"))),
+            cosign_information: Some(
+                Err(super::MissingDockerManifest).context("failed to get cosign manifest"),
+            ),
+            cosign_verify: None,
+            platform: None,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+        };
+
+        let rendered = response.render().unwrap();
+
+        assert_eq!(1, rendered.matches("<h4>Error</h4>").count());
+        assert!(rendered.contains("Could not fetch image metadata"));
+        assert!(rendered.contains("manifest unknown"));
+        assert!(!rendered.contains("failed to get cosign manifest"));
+
+        // unrelated cosign failures are still shown separately
+        let response = super::ImageResponse {
+            cosign_information: Some(Err(eyre::eyre!("cosign manifest unknown"))),
+            ..response
+        };
+
+        let rendered = response.render().unwrap();
+
+        assert_eq!(2, rendered.matches("<h4>Error</h4>").count());
+        assert!(!rendered.contains("Could not fetch image metadata"));
+    }
+
+    #[test]
+    fn cosign_verify_multiple_signatures() {
+        const MANIFEST: &str = include_str!("resources/tests/trivy-manifest-response.json");
+        const SIGNATURES: &str = include_str!("resources/tests/cosign_verify_output.json");
+
+        let resolved_digest =
+            "sha256:4704989dd70b1b8e8a87ee4e1b7b3a9f4aa5d5a7d0c0e4c4d1fa7b9e42f3a1c2";
+
+        let response = super::ImageResponse {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            docker_information: Some(Ok(super::DockerInformation {
+                response: DockerResponse {
+                    digest: Some(resolved_digest.to_string()),
+                    manifest: serde_json::from_str::<DockerManifest>(MANIFEST).unwrap(),
+                },
+                labels: BTreeMap::new(),
+                fetch_time: chrono::Utc::now(),
+            })),
+            cosign_information: None,
+            cosign_verify: Some(Ok(super::cosign::CosignVerify {
+                message: String::new(),
+                signatures: serde_json::from_str(SIGNATURES).unwrap(),
+            })),
+            platform: None,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+        };
+
+        let rendered = response.render().unwrap();
+
+        assert!(rendered.contains("<td>ghcr.io/aquasecurity/trivy</td>"));
+        assert!(rendered.contains("<td>registry.example.com/mirror/trivy</td>"));
+        assert_eq!(
+            1,
+            rendered.matches("<tr class=\"digest_mismatch\">").count()
+        );
+        assert!(rendered.contains("does not match the resolved image digest"));
+    }
+
+    #[test]
+    fn scanner_status_strip() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let render = |failed_scanners| {
+            let mut trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+            trivy_result.failed_scanners = failed_scanners;
+
+            super::TrivyResponse {
+                information: Ok(super::TrivyInformation::new(
+                    trivy_result,
+                    &[Scanner::Vuln, Scanner::Secret],
+                )),
+                max_references: 5,
+                cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+                severity_weights: SeverityWeights::default(),
+                cvss_version: CvssVersion::default(),
+            }
+            .render()
+            .unwrap()
+        };
+
+        // the secret scanner ran but found nothing
+        let rendered = render(Vec::new());
+
+        assert!(rendered.contains("<li class=\"ran\">vuln ✓</li>"));
+        assert!(rendered.contains("<li class=\"ran\">secret ✓ (no findings)</li>"));
+        assert!(!rendered.contains("failed or disabled"));
+
+        let rendered = render(vec![Scanner::Secret]);
+
+        assert!(rendered.contains("<li class=\"missing\">secret ✗</li>"));
+        assert!(
+            rendered.contains("Trivy reported requested scanners as failed or disabled: secret.")
+        );
+    }
+
+    #[test]
+    fn render_server_unavailable() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+
+        let mut information = super::TrivyInformation::new(trivy_result, &[]);
+        information.server_unavailable = true;
+
+        let response = super::TrivyResponse {
+            information: Ok(information),
+            max_references: 5,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+            severity_weights: SeverityWeights::default(),
+            cvss_version: CvssVersion::default(),
+        };
+
+        let rendered = response.render().unwrap();
+
+        assert!(rendered.contains("Trivy server is unavailable, showing the cached result."));
+        assert!(rendered.contains("<h3>Vulnerabilities</h3>"));
+    }
+
+    #[test]
+    fn references_capped() {
+        let references = (0..10)
+            .map(|index| format!("\"https://example.com/ref/{index}\""))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let data = format!(
+            r#"{{"Results": [{{"Class": "os-pkgs", "Vulnerabilities": [{{
+                "VulnerabilityID": "CVE-2024-0001",
+                "PkgName": "openssl",
+                "InstalledVersion": "3.0.0",
+                "Severity": "HIGH",
+                "PrimaryURL": "https://avd.aquasec.com/nvd/cve-2024-0001",
+                "References": [{references}]
+            }}]}}]}}"#
+        );
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(&data).unwrap();
+
+        let response = super::TrivyResponse {
+            information: Ok(super::TrivyInformation::new(trivy_result, &[])),
+            max_references: 3,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+            severity_weights: SeverityWeights::default(),
+            cvss_version: CvssVersion::default(),
+        };
+
+        let rendered = response.render().unwrap();
+
+        let (shown, collapsed) = rendered.split_once("<details>").unwrap();
+
+        assert_eq!(
+            3,
+            shown
+                .matches("<li><a href=\"https://example.com/ref/")
+                .count()
+        );
+        assert_eq!(
+            7,
+            collapsed
+                .matches("<li><a href=\"https://example.com/ref/")
+                .count()
+        );
+        assert!(collapsed.contains("Show all 10 references"));
+    }
+
+    #[test]
+    fn passed_misconfigurations() {
+        const DATA: &str = include_str!("resources/tests/trivy_output_misconfig.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+
+        let information = super::TrivyInformation::new(trivy_result, &[Scanner::Misconfig]);
+
+        let statuses = information
+            .misconfigurations
+            .iter()
+            .map(|misconfiguration| (misconfiguration.id.as_str(), misconfiguration.status))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                ("DS002", MisconfigurationStatus::Fail),
+                ("DS001", MisconfigurationStatus::Pass),
+            ],
+            statuses
+        );
+
+        let rendered = super::TrivyResponse {
+            information: Ok(information),
+            max_references: 5,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+            severity_weights: SeverityWeights::default(),
+            cvss_version: CvssVersion::default(),
+        }
+        .render()
+        .unwrap();
+
+        assert!(rendered.contains("<tr class=\"misconfiguration_fail\">"));
+        assert!(rendered.contains("<tr class=\"misconfiguration_pass\">"));
+    }
+
+    mod filters {
+        #![allow(
+            clippy::inline_always,
+            clippy::unnecessary_wraps,
+            clippy::unused_self,
+            reason = "generated helper code from askama::filter_fn intentionally triggers these \
+                      lints"
+        )]
+
+        #[askama::filter_fn]
+        pub fn fail<T: std::fmt::Display>(
+            _: T,
+            _: &dyn askama::Values,
+        ) -> ::askama::Result<String> {
+            Err(askama::Error::Fmt)
+        }
+    }
+
+    #[derive(Template)]
+    #[template(
+        source = "{{ information.severity_count().critical|fail }}",
+        ext = "html"
+    )]
+    struct BrokenTemplate<'a> {
+        information: &'a super::TrivyInformation,
+    }
+
+    #[test]
+    fn render_fallback() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let template = BrokenTemplate {
+            information: &information,
+        };
+
+        assert!(template.render().is_err());
+
+        let rendered = super::render_trivy(&template, Some(&information)).unwrap();
+
+        for (title, count) in [("Critical", 6), ("High", 16), ("Medium", 13), ("Low", 32)] {
+            assert!(
+                rendered.contains(&format!("title=\"{title}\">{count}</li>")),
+                "missing {title} count"
+            );
+        }
+
+        assert!(super::render_trivy(&template, None).is_err());
+    }
+
+    #[test]
+    fn derived_severity() {
+        let vulnerability: Vulnerability = serde_json::from_str(
+            r#"{
+                "VulnerabilityID": "CVE-2024-0001",
+                "PkgName": "openssl",
+                "InstalledVersion": "3.0.0",
+                "Severity": "UNKNOWN",
+                "References": null,
+                "PrimaryURL": null,
+                "FixedVersion": null,
+                "Title": null,
+                "CVSS": {
+                    "ghsa": { "V3Score": 5.3 },
+                    "nvd": { "V3Score": 9.5 }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut information = super::TrivyInformation {
+            vulnerabilities: BTreeSet::from([vulnerability]),
+            ..super::TrivyInformation::merge(Vec::new())
+        };
+
+        information.derive_severities(&[], CvssVersion::V3);
+        assert!(
+            information
+                .vulnerabilities
+                .iter()
+                .all(|vulnerability| vulnerability.severity == Severity::Unknown)
+        );
+
+        information.derive_severities(&["redhat".to_string(), "nvd".to_string()], CvssVersion::V3);
+
+        let vulnerability = information.vulnerabilities.first().unwrap();
+        assert_eq!(Severity::Critical, vulnerability.severity);
+        assert!(vulnerability.derived_severity);
+        assert_eq!(1, information.severity_count().critical);
+        assert_eq!(0, information.severity_count().unknown);
+
+        let response = super::TrivyResponse {
+            information: Ok(information),
+            max_references: 5,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+            severity_weights: SeverityWeights::default(),
+            cvss_version: CvssVersion::default(),
+        };
+
+        assert!(response.render().unwrap().contains("CRITICAL (derived)"));
+    }
+
+    #[test]
+    fn remap_severities() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let mut information = super::TrivyInformation::new(trivy_result, &[]);
+        let count = information.severity_count().clone();
+
+        information.remap_severities(&"medium=high".parse().unwrap());
+
+        assert_eq!(
+            SeverityCount {
+                high: count.high + count.medium,
+                medium: 0,
+                ..count
+            },
+            *information.severity_count()
+        );
+
+        let remapped = information
+            .vulnerabilities
+            .iter()
+            .filter(|vulnerability| vulnerability.original_severity.is_some())
+            .collect::<Vec<_>>();
+
+        assert_eq!(count.medium, remapped.len());
+        assert!(remapped.iter().all(|vulnerability| {
+            vulnerability.severity == Severity::High
+                && vulnerability.original_severity == Some(Severity::Medium)
+        }));
+
+        let rendered = super::TrivyResponse {
+            information: Ok(information),
+            max_references: 5,
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+            severity_weights: SeverityWeights::default(),
+            cvss_version: CvssVersion::default(),
+        }
+        .render()
+        .unwrap();
+
+        assert!(rendered.contains("(remapped from MEDIUM)"));
+    }
+
+    #[test]
+    fn hide_will_not_fix() {
+        const DATA: &str = include_str!("resources/tests/trivy_output2.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+
+        let mut information = super::TrivyInformation::new(trivy_result, &[]);
+        let total = information.vulnerabilities.len();
+
+        information.hide_statuses(&[VulnerabilityStatus::WillNotFix]);
+
+        assert_eq!(total - 4, information.vulnerabilities.len());
+        assert!(
+            information
+                .vulnerabilities
+                .iter()
+                .all(|vulnerability| vulnerability.status != Some(VulnerabilityStatus::WillNotFix))
+        );
+
+        let count = information.severity_count();
+        assert_eq!(
+            total - 4,
+            count.critical + count.high + count.medium + count.low + count.unknown
+        );
+    }
+
+    #[test]
+    fn min_severity() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+
+        let mut information = super::TrivyInformation::new(trivy_result, &[]);
+        let count = information.severity_count().clone();
+
+        information.retain_min_severity(Severity::High);
+
+        assert!(
+            information
+                .vulnerabilities
+                .iter()
+                .all(|vulnerability| matches!(
+                    vulnerability.severity,
+                    Severity::Critical | Severity::High
+                ))
+        );
+        assert_eq!(
+            SeverityCount {
+                critical: count.critical,
+                high: count.high,
+                ..SeverityCount::default()
+            },
+            *information.severity_count()
+        );
+    }
+
+    #[test]
+    fn duplicates_across_targets() {
+        const DATA: &str = include_str!("resources/tests/trivy_output_duplicates.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        // the finding of the newer package version is kept apart
+        assert_eq!(3, information.vulnerabilities.len());
+        assert_eq!(
+            SeverityCount {
+                high: 2,
+                medium: 1,
+                ..SeverityCount::default()
+            },
+            *information.severity_count()
+        );
+
+        let merged = information
+            .vulnerabilities
+            .iter()
+            .find(|vulnerability| {
+                vulnerability.id == "CVE-2023-39325" && vulnerability.installed_version == "v0.10.0"
+            })
+            .unwrap();
+
+        assert_eq!(Severity::High, merged.severity);
+        assert_eq!(
+            Some(
+                [
+                    "https://access.redhat.com/security/cve/CVE-2023-39325",
+                    "https://github.com/golang/go/issues/63417",
+                    "https://nvd.nist.gov/vuln/detail/CVE-2023-39325",
+                ]
+                .into_iter()
+                .map(ToString::to_string)
+                .collect()
+            ),
+            merged.references
+        );
+
+        let cvss = merged.cvss.as_ref().unwrap();
+
+        assert_eq!(
+            vec!["ghsa", "nvd", "redhat"],
+            cvss.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some("7.5"),
+            cvss["ghsa"].v3_score().map(ToString::to_string).as_deref()
+        );
+    }
+
+    #[test]
+    fn vulnerabilities_by_score() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let by_score = information.vulnerabilities_by_score();
+        assert_eq!(information.vulnerabilities.len(), by_score.len());
+
+        let scores = by_score
+            .iter()
+            .map(|vulnerability| vulnerability.highest_score())
+            .collect::<Vec<_>>();
+
+        assert!(scores.is_sorted_by(|a, b| a >= b));
+        assert!(
+            scores
+                .iter()
+                .any(|score| score.is_some_and(|score| score.value() >= 10.0))
+        );
+    }
+
+    #[test]
+    fn vulnerabilities_by_package() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let packages = information.vulnerabilities_by_package();
+
+        assert_eq!(
+            information.vulnerabilities.len(),
+            packages.values().map(Vec::len).sum::<usize>()
+        );
+        assert!(packages.len() < information.vulnerabilities.len());
+
+        for (package, vulnerabilities) in &packages {
+            assert!(
+                vulnerabilities
+                    .iter()
+                    .all(|vulnerability| &vulnerability.pkg_name == package)
+            );
+            assert!(vulnerabilities.is_sorted_by_key(|vulnerability| (
+                vulnerability.severity,
+                vulnerability.id.clone()
+            )));
+        }
+    }
+
+    #[test]
+    fn vulnerabilities_by_layer() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let layers = information.vulnerabilities_by_layer();
+
+        assert_eq!(3, layers.len());
+        assert_eq!(
+            information.vulnerabilities.len(),
+            layers.values().map(Vec::len).sum::<usize>()
+        );
+        assert!(
+            layers["sha256:76000b692abd282a5bf66a6ca416cc3dbf38d47ab4d222a77581a0d92da6dcbe"]
+                .iter()
+                .any(|vulnerability| vulnerability.id == "GHSA-h6ch-v84p-w6p9")
+        );
+    }
+
+    #[test]
+    fn to_github_annotations() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let annotations = information.to_github_annotations();
+        let lines = annotations.lines().collect::<Vec<_>>();
+
+        assert_eq!(information.vulnerabilities.len(), lines.len());
+        assert_eq!(
+            "::error title=CRITICAL CVE-2019-19919::nodejs-handlebars: prototype pollution \
+             leading to remote code execution via crafted payloads (handlebars 1.0.0)",
+            lines[0]
+        );
+        assert_eq!(
+            "::warning title=LOW GHSA-wx77-rp39-c6vg::Regular Expression Denial of Service in \
+             markdown (markdown 1.0.0)",
+            *lines.last().unwrap()
+        );
+
+        let count = information.severity_count;
+        assert_eq!(
+            count.critical + count.high,
+            lines
+                .iter()
+                .filter(|line| line.starts_with("::error "))
+                .count()
+        );
+        assert_eq!(
+            count.medium + count.low + count.unknown,
+            lines
+                .iter()
+                .filter(|line| line.starts_with("::warning "))
+                .count()
+        );
+
+        assert_eq!(
+            "100%25 broken%0Ain: two::lines",
+            super::escape_annotation_data("100% broken\nin: two::lines")
+        );
+        assert_eq!("a%3A%3Ab%2Cc", super::escape_annotation_property("a::b,c"));
+    }
+
+    #[test]
+    fn to_sarif() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let sarif = information.to_sarif();
+
+        assert_eq!(
+            information.vulnerabilities.len(),
+            sarif.runs[0].results.len()
+        );
+
+        for (vulnerability, result) in information
+            .vulnerabilities
+            .iter()
+            .zip(&sarif.runs[0].results)
+        {
+            assert_eq!(vulnerability.id, result.rule_id);
+            assert_eq!(
+                super::sarif::Level::from(vulnerability.severity),
+                result.level
+            );
+            assert!(result.message.text.contains(&vulnerability.pkg_name));
+            assert!(
+                result
+                    .message
+                    .text
+                    .contains(&vulnerability.installed_version)
+            );
+        }
+
+        let json = serde_json::to_value(&sarif).unwrap();
+
+        assert_eq!("2.1.0", json["version"]);
+        assert_eq!("trivy", json["runs"][0]["tool"]["driver"]["name"]);
+        assert!(
+            json["runs"][0]["results"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|result| result["level"] == "error")
+        );
+    }
+
+    #[test]
+    fn trivy_schema_roundtrip() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+        let information = super::TrivyInformation::new(trivy_result, &[]);
+
+        let image = "linuxserver/code-server:latest".parse().unwrap();
+        let json = serde_json::to_string(&information.to_trivy_result(&image)).unwrap();
+
+        let reparsed = serde_json::from_str::<TrivyResult>(&json).unwrap();
+
+        assert_eq!(Some(2), reparsed.schema_version);
+        assert_eq!(information.digest.as_deref(), reparsed.metadata.digest());
+
+        let ids = |vulnerabilities: &BTreeSet<Vulnerability>| {
+            vulnerabilities
+                .iter()
+                .map(|vulnerability| vulnerability.id.clone())
+                .collect::<BTreeSet<_>>()
+        };
+
+        let reparsed = super::TrivyInformation::new(reparsed, &[]);
+
+        assert_eq!(
+            ids(&information.vulnerabilities),
+            ids(&reparsed.vulnerabilities)
+        );
+        assert_eq!(information.severity_count(), reparsed.severity_count());
+    }
+
+    #[test]
+    fn canonical_ids() {
+        const DATA: &str = r#"{
+            "Results": [
+                {
+                    "Vulnerabilities": [
+                        {
+                            "VulnerabilityID": "GHSA-h6ch-v84p-w6p9",
+                            "Aliases": ["CVE-2017-16137"],
+                            "PkgName": "debug",
+                            "InstalledVersion": "2.6.8",
+                            "Severity": "MEDIUM"
+                        },
+                        {
+                            "VulnerabilityID": "CVE-2017-16137",
+                            "PkgName": "debug",
+                            "InstalledVersion": "2.6.8",
+                            "Severity": "MEDIUM",
+                            "Title": "nodejs-debug: Regular expression Denial of Service"
+                        }
+                    ]
+                }
+            ]
+        }"#;
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+
+        let mut information = super::TrivyInformation::new(trivy_result, &[]);
+        assert_eq!(2, information.severity_count().medium);
+
+        information.canonicalize_ids();
+
+        let vulnerabilities = information.vulnerabilities.iter().collect::<Vec<_>>();
+
+        assert_eq!(1, vulnerabilities.len());
+        assert_eq!("CVE-2017-16137", vulnerabilities[0].id);
+        assert!(vulnerabilities[0].title.is_some());
+        assert_eq!(
+            vec!["GHSA-h6ch-v84p-w6p9"],
+            vulnerabilities[0]
+                .aliases
+                .iter()
+                .map(String::as_str)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(1, information.severity_count().medium);
+    }
+
     #[tokio::test]
     #[cfg_attr(
         feature = "ci",
@@ -247,7 +2018,13 @@ mod tests {
         let information = super::TrivyInformation {
             vulnerabilities,
             severity_count,
+            misconfigurations: BTreeSet::new(),
+            scanners: Vec::new(),
+            digest: None,
             fetch_time: chrono::Utc::now(),
+            trusted: false,
+            cooling_down: false,
+            server_unavailable: false,
         };
 
         let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
@@ -268,4 +2045,25 @@ mod tests {
 
         connection.del::<_, ()>(key).await.unwrap();
     }
+
+    #[test]
+    fn render_compliance() {
+        let response = super::ComplianceResponse {
+            information: Ok(super::ComplianceInformation {
+                report: serde_json::from_str(include_str!("resources/tests/trivy_compliance.json"))
+                    .unwrap(),
+                fetch_time: chrono::Utc::now(),
+            }),
+            cache_ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+        };
+
+        let rendered = response.render().unwrap();
+
+        assert!(rendered.contains("CIS Docker Community Edition Benchmark v1.6.0"));
+        assert!(rendered.contains("Passed: 3"));
+        assert!(rendered.contains("Failed: 2"));
+        assert!(rendered.contains("Manual: 2"));
+        assert_eq!(2, rendered.matches(r#"class="compliance_fail""#).count());
+        assert_eq!(3, rendered.matches(r#"class="compliance_pass""#).count());
+    }
 }
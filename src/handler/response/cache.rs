@@ -1,15 +1,30 @@
-use std::collections::BTreeSet;
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc,
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+    },
+};
 
-use chrono::Utc;
-use docker_registry_client::{
-    Client as DockerRegistryClient,
-    Image,
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
 };
+use docker_registry_client::Image;
 use eyre::{
     Context,
     Result,
 };
-use redis::AsyncCommands;
+use redis::{
+    AsyncCommands,
+    ExistenceCheck,
+    SetExpiry,
+    SetOptions,
+};
 use serde::{
     Deserialize,
     Serialize,
@@ -20,47 +35,189 @@ use tracing::{
 };
 
 use crate::handler::{
-    cosign,
+    Password,
+    cosign::{
+        self,
+        CosignFetches,
+    },
+    events::ScanEvent,
+    image_config::config_labels,
+    labels::{
+        LabeledScan,
+        Labels,
+    },
+    metrics,
+    progress::ScanProgress,
+    registry::RegistryLimits,
+    registry_client::RegistryClient,
+    trend::TrendPoint,
     trivy::{
         self,
-        Vulnerability,
-        get_vulnerabilities_count,
+        Scanner,
+        ServerUnavailable,
+        Severity,
+        TrivyConfig,
     },
 };
 
 use super::{
+    ComplianceInformation,
     CosignInformation,
     DockerInformation,
     TrivyInformation,
 };
 
+mod batch;
+
+pub(crate) use batch::WriteBatcher;
+
 const REDIS_KEY_PREFIX: &str = "trivy-web";
-pub(crate) const REDIS_TTL: i64 = 86400;
 
-pub(crate) trait Fetch {
-    type Output: Serialize + for<'de> Deserialize<'de>;
+/// How long outputs are cached when no ttl is configured.
+pub(crate) const DEFAULT_REDIS_TTL: i64 = 86400;
 
-    fn key(&self) -> String;
-    async fn fetch(&self) -> Result<Self::Output>;
+/// Outputs kept by the in-memory cache.
+const MEMORY_CACHE_CAPACITY: u64 = 1024;
 
-    #[tracing::instrument]
-    async fn cache_or_fetch(&self, redis_client: Option<&redis::Client>) -> Result<Self::Output>
-    where
-        Self: std::fmt::Debug,
-    {
-        if redis_client.is_none() {
-            return self
-                .fetch()
-                .instrument(info_span!(
-                    "fetch output from source when redis is disabled"
-                ))
-                .await
-                .context("failed to fetch output from source when redis is disabled");
+/// Caches fetched outputs in memory as JSON, used when no redis server is
+/// configured.
+#[derive(Clone)]
+pub(crate) struct MemoryCache(moka::future::Cache<String, String>);
+
+impl std::fmt::Debug for MemoryCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryCache")
+            .field("entries", &self.0.entry_count())
+            .finish()
+    }
+}
+
+impl MemoryCache {
+    /// Outputs expire after the ttl like they do in redis.
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self(
+            moka::future::Cache::builder()
+                .max_capacity(MEMORY_CACHE_CAPACITY)
+                .time_to_live(ttl.to_std().unwrap_or_default())
+                .build(),
+        )
+    }
+}
+
+/// How fetched outputs are cached.
+#[derive(Debug, Clone)]
+pub(crate) struct Cache {
+    pub(crate) redis_client: Option<redis::Client>,
+
+    /// How long fetched outputs are kept in redis.
+    pub(crate) ttl: Duration,
+
+    /// Cached outputs older than this are still served but refreshed in the
+    /// background so the next request gets a fresh output.
+    pub(crate) fresh_for: Option<Duration>,
+
+    /// When set only cached outputs are served and nothing new is fetched,
+    /// e.g. during trivy database maintenance.
+    pub(crate) read_only: Arc<AtomicBool>,
+
+    /// How long trend points of scans are kept, they are kept indefinitely
+    /// when not set.
+    pub(crate) trend_retention: Option<Duration>,
+
+    /// Redis stream scan-completed events are published to.
+    pub(crate) event_stream: Option<String>,
+
+    /// Batches writes of fetched outputs into pipelines when set.
+    pub(crate) write_batcher: Option<WriteBatcher>,
+
+    /// Outputs are fetched again at most once per cooldown, refreshes within
+    /// the cooldown serve the cached output instead.
+    pub(crate) refresh_cooldown: Option<Duration>,
+
+    /// Used instead of redis when no redis server is configured.
+    pub(crate) memory: MemoryCache,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            redis_client: None,
+            ttl: Duration::seconds(DEFAULT_REDIS_TTL),
+            fresh_for: None,
+            read_only: Arc::default(),
+            trend_retention: None,
+            event_stream: None,
+            write_batcher: None,
+            refresh_cooldown: None,
+            memory: MemoryCache::new(Duration::seconds(DEFAULT_REDIS_TTL)),
+        }
+    }
+}
+
+/// Returned for cache misses while the cache is in read-only mode.
+#[derive(Debug)]
+pub(crate) struct ReadOnly;
+
+impl std::fmt::Display for ReadOnly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "read-only mode is enabled, only cached results are served"
+        )
+    }
+}
+
+impl std::error::Error for ReadOnly {}
+
+impl Cache {
+    pub(crate) fn read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Stores batched writes that are still pending, e.g. before shutting
+    /// down.
+    pub(crate) async fn flush(&self) {
+        if let Some(write_batcher) = &self.write_batcher {
+            write_batcher.flush().await;
         }
+    }
 
-        let redis_client = redis_client
-            .as_ref()
-            .expect("already checked if redis is none");
+    pub(crate) fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Checks that the configured redis server answers, used by the
+    /// readiness probe.
+    pub(crate) async fn ping(&self) -> Result<()> {
+        let Some(redis_client) = &self.redis_client else {
+            return Ok(());
+        };
+
+        let mut connection = redis_client
+            .get_multiplexed_async_connection()
+            .instrument(info_span!("get redis connection"))
+            .await
+            .context("failed to get redis connection")?;
+
+        let _: String = redis::cmd("PING")
+            .query_async(&mut connection)
+            .instrument(info_span!("ping redis"))
+            .await
+            .context("failed to ping redis")?;
+
+        Ok(())
+    }
+
+    fn labels_key(image: &str) -> String {
+        format!("{REDIS_KEY_PREFIX}:labels:{image}")
+    }
+
+    /// Stores the labels of a scan next to its cached trivy output. Labels
+    /// are not kept when redis is disabled.
+    pub(crate) async fn set_labels(&self, image: &Image, labels: &Labels) -> Result<()> {
+        let Some(redis_client) = &self.redis_client else {
+            return Ok(());
+        };
 
         let mut connection = redis_client
             .get_multiplexed_async_connection()
@@ -68,166 +225,2090 @@ pub(crate) trait Fetch {
             .await
             .context("failed to get redis connection")?;
 
-        let key = self.key();
+        let json = serde_json::to_string(labels).context("failed to serialize labels for redis")?;
 
-        let exists: bool = connection
-            .exists(&key)
-            .instrument(info_span!("check if key exists in redis"))
+        let _: () = connection
+            .set_ex(
+                Self::labels_key(&image.to_string()),
+                json,
+                self.ttl.num_seconds().unsigned_abs(),
+            )
+            .instrument(info_span!("set labels in redis"))
             .await
-            .context("failed to check key exists in redis")?;
+            .context("failed to set labels in redis")?;
 
-        if exists {
-            let information: String = connection
+        Ok(())
+    }
+
+    fn sbom_key(image: &str) -> String {
+        format!("{REDIS_KEY_PREFIX}:sbom:{image}")
+    }
+
+    /// Cached `CycloneDX` SBOM of the image, `None` when redis is disabled or
+    /// the SBOM is not cached.
+    pub(crate) async fn sbom(&self, image: &Image) -> Result<Option<Vec<u8>>> {
+        let Some(redis_client) = &self.redis_client else {
+            return Ok(None);
+        };
+
+        let mut connection = redis_client
+            .get_multiplexed_async_connection()
+            .instrument(info_span!("get redis connection"))
+            .await
+            .context("failed to get redis connection")?;
+
+        connection
+            .get(Self::sbom_key(&image.to_string()))
+            .instrument(info_span!("get sbom from redis"))
+            .await
+            .context("failed to get sbom from redis")
+    }
+
+    /// Stores the SBOM of the image for the cache ttl.
+    pub(crate) async fn set_sbom(&self, image: &Image, sbom: Vec<u8>) -> Result<()> {
+        let Some(redis_client) = &self.redis_client else {
+            return Ok(());
+        };
+
+        let mut connection = redis_client
+            .get_multiplexed_async_connection()
+            .instrument(info_span!("get redis connection"))
+            .await
+            .context("failed to get redis connection")?;
+
+        let _: () = connection
+            .set_ex(
+                Self::sbom_key(&image.to_string()),
+                sbom,
+                self.ttl.num_seconds().unsigned_abs(),
+            )
+            .instrument(info_span!("set sbom in redis"))
+            .await
+            .context("failed to set sbom in redis")?;
+
+        Ok(())
+    }
+
+    fn cooldown_key(key: &str) -> String {
+        let key = key
+            .strip_prefix(REDIS_KEY_PREFIX)
+            .and_then(|key| key.strip_prefix(':'))
+            .unwrap_or(key);
+
+        format!("{REDIS_KEY_PREFIX}:cooldown:{key}")
+    }
+
+    /// Claims the refresh of the key for the cooldown, returns false when it
+    /// was already refreshed within the cooldown.
+    async fn claim_refresh(
+        &self,
+        connection: &mut redis::aio::MultiplexedConnection,
+        key: &str,
+    ) -> Result<bool> {
+        let Some(cooldown) = self.refresh_cooldown else {
+            return Ok(true);
+        };
+
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(cooldown.num_seconds().unsigned_abs()));
+
+        let claimed: Option<String> = connection
+            .set_options(Self::cooldown_key(key), Utc::now().timestamp(), options)
+            .instrument(info_span!("claim refresh in redis"))
+            .await
+            .context("failed to claim refresh in redis")?;
+
+        Ok(claimed.is_some())
+    }
+
+    fn trend_key(image: &str) -> String {
+        format!("{REDIS_KEY_PREFIX}:trend:{image}")
+    }
+
+    /// Appends a trend point for the image. The points are stored in a
+    /// sorted set scored by the scan time and outlive the cached outputs.
+    async fn record_trend(
+        &self,
+        connection: &mut redis::aio::MultiplexedConnection,
+        image: &str,
+        point: &TrendPoint,
+    ) -> Result<()> {
+        let key = Self::trend_key(image);
+
+        let json =
+            serde_json::to_string(point).context("failed to serialize trend point for redis")?;
+
+        let _: () = connection
+            .zadd(&key, json, point.scan_time.timestamp_millis())
+            .instrument(info_span!("add trend point in redis"))
+            .await
+            .context("failed to add trend point in redis")?;
+
+        let Some(retention) = self.trend_retention else {
+            return Ok(());
+        };
+
+        let cutoff = (Utc::now() - retention).timestamp_millis();
+
+        let _: () = connection
+            .zrembyscore(&key, "-inf", cutoff)
+            .instrument(info_span!("remove expired trend points from redis"))
+            .await
+            .context("failed to remove expired trend points from redis")?;
+
+        let _: () = connection
+            .expire(&key, retention.num_seconds())
+            .instrument(info_span!("set trend expiration in redis"))
+            .await
+            .context("failed to set trend expiration in redis")?;
+
+        Ok(())
+    }
+
+    /// Publishes a scan-completed event in the background, failures are only
+    /// logged so the scan is not held up by the event stream.
+    fn publish_event(&self, mut connection: redis::aio::MultiplexedConnection, event: ScanEvent) {
+        let Some(stream) = self.event_stream.clone() else {
+            return;
+        };
+
+        tokio::spawn(
+            async move {
+                let result: redis::RedisResult<String> =
+                    connection.xadd(&stream, "*", &event.fields()).await;
+
+                if let Err(err) = result {
+                    tracing::error!("failed to publish scan event to {stream}: {err:?}");
+                }
+            }
+            .instrument(info_span!("publish scan event")),
+        );
+    }
+
+    /// Returns the trend points of an image ordered by scan time.
+    pub(crate) async fn trend(&self, image: &Image) -> Result<Vec<TrendPoint>> {
+        let Some(redis_client) = &self.redis_client else {
+            return Err(eyre::eyre!("trends require a redis server"));
+        };
+
+        let mut connection = redis_client
+            .get_multiplexed_async_connection()
+            .instrument(info_span!("get redis connection"))
+            .await
+            .context("failed to get redis connection")?;
+
+        let points: Vec<String> = connection
+            .zrange(Self::trend_key(&image.to_string()), 0, -1)
+            .instrument(info_span!("get trend points from redis"))
+            .await
+            .context("failed to get trend points from redis")?;
+
+        points
+            .iter()
+            .map(|point| {
+                serde_json::from_str(point)
+                    .context("failed to deserialize trend point from redis data")
+            })
+            .collect()
+    }
+
+    /// Lists labeled scans whose labels match the filter.
+    pub(crate) async fn labeled_scans(&self, filter: &Labels) -> Result<Vec<LabeledScan>> {
+        let Some(redis_client) = &self.redis_client else {
+            return Err(eyre::eyre!("listing scans requires a redis server"));
+        };
+
+        let mut connection = redis_client
+            .get_multiplexed_async_connection()
+            .instrument(info_span!("get redis connection"))
+            .await
+            .context("failed to get redis connection")?;
+
+        let prefix = Self::labels_key("");
+
+        let keys: Vec<String> = {
+            let mut iter = connection
+                .scan_match::<_, String>(format!("{prefix}*"))
+                .await
+                .context("failed to scan label keys in redis")?;
+
+            let mut keys = Vec::new();
+            while let Some(key) = iter.next_item().await {
+                keys.push(key.context("failed to read label key from redis")?);
+            }
+
+            keys
+        };
+
+        let mut scans = Vec::new();
+
+        for key in keys {
+            let json: Option<String> = connection
                 .get(&key)
-                .instrument(info_span!("get output from redis"))
+                .instrument(info_span!("get labels from redis"))
                 .await
-                .context("failed to get output from redis")?;
+                .context("failed to get labels from redis")?;
+
+            // the key expired between the scan and the get
+            let Some(json) = json else {
+                continue;
+            };
+
+            let labels: Labels = serde_json::from_str(&json)
+                .context("failed to deserialize labels from redis data")?;
+
+            if labels.matches(filter) {
+                scans.push(LabeledScan {
+                    image: key.trim_start_matches(&prefix).to_string(),
+                    labels,
+                });
+            }
+        }
+
+        scans.sort_by(|a, b| a.image.cmp(&b.image));
+
+        Ok(scans)
+    }
+}
+
+/// Outputs that remember when they were fetched.
+pub(crate) trait Fetched {
+    fn fetch_time(&self) -> DateTime<Utc>;
+
+    /// Marks a cached output served in place of a refresh within the
+    /// cooldown, nothing is marked by default.
+    fn cooling_down(&mut self) {}
+
+    /// Marks a cached output served in place of a refresh the trivy server
+    /// could not run, nothing is marked by default.
+    fn server_unavailable(&mut self) {}
+}
+
+pub(crate) trait Fetch: Clone + std::fmt::Debug + Send + Sync + 'static {
+    type Output: Serialize + for<'de> Deserialize<'de> + Fetched + Send;
+
+    fn key(&self) -> String;
+    fn fetch(&self) -> impl Future<Output = Result<Self::Output>> + Send;
+
+    /// Image and trend point recorded and published as an event for freshly
+    /// fetched outputs, nothing is recorded by default.
+    fn trend(&self, _output: &Self::Output) -> Option<(String, TrendPoint)> {
+        None
+    }
+
+    /// Fetches the output from the source and stores it in redis. The output
+    /// is still returned when redis is unavailable. Within the refresh
+    /// cooldown the cached output is returned instead.
+    fn refresh(
+        &self,
+        cache: &Cache,
+        redis_client: &redis::Client,
+    ) -> impl Future<Output = Result<Self::Output>> + Send {
+        async move {
+            let mut connection = redis_client
+                .get_multiplexed_async_connection()
+                .instrument(info_span!("get redis connection"))
+                .await
+                .context("failed to get redis connection");
+
+            let key = self.key();
+
+            if let Ok(connection) = &mut connection {
+                match cache.claim_refresh(connection, &key).await {
+                    Ok(true) => {}
 
-            let information = serde_json::from_str(&information)
-                .context("failed to deserialize output from redis data")?;
+                    // the cached output might have expired within the cooldown
+                    Ok(false) => {
+                        if let Ok(Some(mut cached)) = self.cached(redis_client).await {
+                            tracing::info!(
+                                "rescan of {key} is cooling down, serving cached output"
+                            );
 
-            Ok(information)
-        } else {
-            let response = self
+                            cached.cooling_down();
+
+                            return Ok(cached);
+                        }
+                    }
+
+                    Err(err) => tracing::warn!("refreshing without cooldown: {err:?}"),
+                }
+            }
+
+            let response = match self
                 .fetch()
                 .instrument(info_span!("fetch output from source"))
                 .await
-                .context("failed to fetch output from source")?;
+            {
+                Ok(response) => response,
+
+                // a stale output is more useful than none while the trivy
+                // server is down
+                Err(err) => {
+                    if err.downcast_ref::<ServerUnavailable>().is_some()
+                        && let Ok(Some(mut cached)) = self.cached(redis_client).await
+                    {
+                        tracing::warn!("serving cached output of {key}: {err:?}");
+
+                        cached.server_unavailable();
+
+                        return Ok(cached);
+                    }
+
+                    return Err(err.wrap_err("failed to fetch output from source"));
+                }
+            };
+
+            let mut connection = match connection {
+                Ok(connection) => connection,
 
+                Err(err) => {
+                    tracing::warn!("not caching output, redis is unavailable: {err:?}");
+
+                    return Ok(response);
+                }
+            };
+
+            // serialized up front as the output is not required to be Sync
             let json =
-                serde_json::to_string(&response).context("failed to serialize output for redis")?;
+                serde_json::to_string(&response).context("failed to serialize output for redis");
 
-            let _: () = connection
-                .set(&key, &json)
-                .instrument(info_span!("set output in redis"))
-                .await
-                .context("failed to set output in redis")?;
+            let stored: Result<()> = async {
+                let json = json?;
 
-            let _: () = connection
-                .expire(&key, REDIS_TTL)
-                .instrument(info_span!("set output expiration in redis"))
-                .await
-                .context("failed to set output expiration in redis")?;
+                if let Some(write_batcher) = &cache.write_batcher {
+                    return write_batcher
+                        .set(key, json, cache.ttl.num_seconds().unsigned_abs())
+                        .await
+                        .context("failed to set output in redis");
+                }
+
+                let _: () = connection
+                    .set(&key, &json)
+                    .instrument(info_span!("set output in redis"))
+                    .await
+                    .context("failed to set output in redis")?;
+
+                connection
+                    .expire::<_, ()>(&key, cache.ttl.num_seconds())
+                    .instrument(info_span!("set output expiration in redis"))
+                    .await
+                    .context("failed to set output expiration in redis")
+            }
+            .await;
+
+            if let Err(err) = stored {
+                tracing::warn!("failed to cache output: {err:?}");
+
+                return Ok(response);
+            }
+
+            if let Some((image, point)) = self.trend(&response) {
+                if let Err(err) = cache.record_trend(&mut connection, &image, &point).await {
+                    tracing::error!("failed to record trend point: {err:?}");
+                }
+
+                cache.publish_event(connection, ScanEvent { image, point });
+            }
 
             Ok(response)
         }
     }
-}
-
-#[derive(Debug)]
-pub(crate) struct DockerInformationFetcher<'a> {
-    pub(crate) docker_registry_client: &'a docker_registry_client::Client,
-    pub(crate) image: &'a Image,
-}
 
-impl Fetch for DockerInformationFetcher<'_> {
-    type Output = DockerInformation;
+    /// Fetches the output from the source without caching it.
+    fn fetch_uncached(&self, cache: &Cache) -> impl Future<Output = Result<Self::Output>> + Send {
+        async move {
+            if cache.read_only() {
+                return Err(ReadOnly.into());
+            }
 
-    fn key(&self) -> String {
-        format!(
-            "{REDIS_KEY_PREFIX}:docker_manifest:{image}",
-            image = self.image
-        )
+            self.fetch()
+                .instrument(info_span!("fetch output from source without cache"))
+                .await
+                .context("failed to fetch output from source without cache")
+        }
     }
 
-    async fn fetch(&self) -> Result<Self::Output> {
-        let response = self
-            .docker_registry_client
-            .get_manifest(self.image)
-            .instrument(info_span!("get docker manifest from docker registry"))
-            .await
-            .context("can not get manifest from docker registry")?;
+    /// Serves the output from the in-memory cache and fetches it on a miss.
+    /// With `force` the output is always fetched again, unless the cache is
+    /// read-only.
+    fn memory_or_fetch(
+        &self,
+        cache: &Cache,
+        force: bool,
+    ) -> impl Future<Output = Result<Self::Output>> + Send {
+        async move {
+            let key = self.key();
 
-        Ok(Self::Output {
-            response,
-            fetch_time: chrono::Utc::now(),
-        })
-    }
-}
+            if !force || cache.read_only() {
+                let cached = cache.memory.0.get(&key).await.and_then(|json| {
+                    match serde_json::from_str(&json) {
+                        Ok(output) => Some(output),
 
-#[derive(Debug)]
-pub(crate) struct TrivyInformationFetcher<'a> {
-    pub(crate) image: &'a Image,
-    pub(crate) trivy_server: Option<&'a str>,
-    pub(crate) trivy_username: Option<&'a str>,
-    pub(crate) trivy_password: Option<&'a str>,
-}
+                        Err(err) => {
+                            tracing::warn!("failed to deserialize output from memory: {err:?}");
 
-impl Fetch for TrivyInformationFetcher<'_> {
-    type Output = TrivyInformation;
+                            None
+                        }
+                    }
+                });
 
-    fn key(&self) -> String {
-        format!("{REDIS_KEY_PREFIX}:trivy:{image}", image = self.image)
+                metrics::record_cache(cached.is_some());
+
+                if let Some(output) = cached {
+                    return Ok(output);
+                }
+            }
+
+            let output = self.fetch_uncached(cache).await?;
+
+            // serialized to share the serde paths with redis
+            match serde_json::to_string(&output) {
+                Ok(json) => cache.memory.0.insert(key, json).await,
+
+                Err(err) => tracing::warn!("failed to serialize output for memory: {err:?}"),
+            }
+
+            Ok(output)
+        }
     }
 
-    async fn fetch(&self) -> Result<Self::Output> {
-        let trivy_result = trivy::scan_image(
-            self.image,
-            self.trivy_server,
-            self.trivy_username,
-            self.trivy_password,
-        )
-        .await?;
+    /// Whether an output is cached, in redis or without redis in memory. A
+    /// cache that can not be read counts as a miss.
+    fn is_cached(&self, cache: &Cache) -> impl Future<Output = bool> + Send {
+        async move {
+            let key = self.key();
 
-        let vulnerabilities = trivy_result
-            .results
-            .into_iter()
-            .filter_map(|result| result.vulnerabilities)
-            .flatten()
-            .collect::<BTreeSet<Vulnerability>>();
+            let Some(redis_client) = &cache.redis_client else {
+                return cache.memory.0.contains_key(&key);
+            };
 
-        let severity_count = get_vulnerabilities_count(vulnerabilities.clone());
+            let exists: Result<bool> = async {
+                let mut connection = redis_client
+                    .get_multiplexed_async_connection()
+                    .instrument(info_span!("get redis connection"))
+                    .await
+                    .context("failed to get redis connection")?;
 
-        Ok(TrivyInformation {
-            vulnerabilities,
-            severity_count,
-            fetch_time: Utc::now(),
-        })
+                connection
+                    .exists(&key)
+                    .instrument(info_span!("check if key exists in redis"))
+                    .await
+                    .context("failed to check key exists in redis")
+            }
+            .await;
+
+            exists.unwrap_or_else(|err| {
+                tracing::warn!("treating {key} as not cached: {err:?}");
+
+                false
+            })
+        }
     }
-}
 
-#[derive(Debug)]
-pub(crate) struct CosignInformationFetcher<'a> {
-    pub(crate) docker_registry_client: &'a DockerRegistryClient,
-    pub(crate) image: &'a Image,
-    pub(crate) docker_manifest: &'a Result<DockerInformation>,
-}
+    /// Looks up the cached output, `None` when nothing is cached.
+    fn cached(
+        &self,
+        redis_client: &redis::Client,
+    ) -> impl Future<Output = Result<Option<Self::Output>>> + Send {
+        async move {
+            let mut connection = redis_client
+                .get_multiplexed_async_connection()
+                .instrument(info_span!("get redis connection"))
+                .await
+                .context("failed to get redis connection")?;
 
-impl Fetch for CosignInformationFetcher<'_> {
-    type Output = CosignInformation;
+            let key = self.key();
 
-    fn key(&self) -> String {
-        format!("{{ REDIS_KEY_PREFIX }}:cosign:{}", self.image)
+            let exists: bool = connection
+                .exists(&key)
+                .instrument(info_span!("check if key exists in redis"))
+                .await
+                .context("failed to check key exists in redis")?;
+
+            if !exists {
+                return Ok(None);
+            }
+
+            let information: String = connection
+                .get(&key)
+                .instrument(info_span!("get output from redis"))
+                .await
+                .context("failed to get output from redis")?;
+
+            serde_json::from_str(&information)
+                .map(Some)
+                .context("failed to deserialize output from redis data")
+        }
     }
 
-    async fn fetch(&self) -> Result<Self::Output> {
-        if self.docker_manifest.is_err() {
-            return Err(eyre::eyre!("Failed to get docker manifest"));
+    /// Serves the cached output and fetches it on a miss. With `force` the
+    /// output is always fetched again and the cached output overwritten,
+    /// unless the cache is read-only.
+    #[tracing::instrument]
+    async fn cache_or_fetch(&self, cache: &Cache, force: bool) -> Result<Self::Output> {
+        let Some(redis_client) = &cache.redis_client else {
+            return self.memory_or_fetch(cache, force).await;
+        };
+
+        if force && !cache.read_only() {
+            return self.refresh(cache, redis_client).await;
         }
 
-        let docker_manifest = self
-            .docker_manifest
-            .as_ref()
-            .expect("already checked if its an error");
+        let cached = self.cached(redis_client).await;
+
+        metrics::record_cache(matches!(cached, Ok(Some(_))));
+
+        let information = match cached {
+            Ok(Some(information)) => information,
 
-        if docker_manifest.response.digest.is_none() {
-            return Err(eyre::eyre!("Missing docker manifest digest"));
+            Ok(None) => {
+                if cache.read_only() {
+                    return Err(ReadOnly.into());
+                }
+
+                return self.refresh(cache, redis_client).await;
+            }
+
+            Err(err) => {
+                tracing::warn!("fetching output without cache, redis is unavailable: {err:?}");
+
+                return self.fetch_uncached(cache).await;
+            }
+        };
+
+        if let Some(fresh_for) = cache.fresh_for
+            && !cache.read_only()
+            && Utc::now().signed_duration_since(information.fetch_time()) > fresh_for
+        {
+            let fetcher = self.clone();
+            let cache = cache.clone();
+            let redis_client = redis_client.clone();
+
+            tokio::spawn(
+                async move {
+                    if let Err(err) = fetcher.refresh(&cache, &redis_client).await {
+                        tracing::error!("failed to refresh stale output: {err:?}");
+                    }
+                }
+                .instrument(info_span!("refresh stale output in background")),
+            );
         }
 
-        let digest = docker_manifest
-            .response
-            .digest
-            .as_ref()
-            .expect("already checked if digest is some");
+        Ok(information)
+    }
+}
 
-        let cosign = cosign::cosign_manifest(self.docker_registry_client, self.image, digest)
-            .instrument(info_span!("get cosign manifest"))
-            .await
-            .context("failed to get cosign manifest")?;
+/// Fetches that can be split into one fetch per trivy scanner.
+pub(crate) trait ScannerFetch: Fetch<Output = TrivyInformation> {
+    fn for_scanners(&self, scanners: &[Scanner]) -> Self;
 
-        Ok(CosignInformation {
-            cosign,
-            fetch_time: Utc::now(),
-        })
+    fn for_scanner(&self, scanner: Scanner) -> Self {
+        self.for_scanners(&[scanner])
+    }
+}
+
+/// Stands in for the fetcher of a single scanner whose output came from a
+/// scan that ran several scanners, so the output is cached like the fetcher
+/// would cache it.
+#[derive(Debug, Clone)]
+struct Prefetched<F> {
+    fetcher: F,
+    output: TrivyInformation,
+}
+
+impl<F: ScannerFetch> Fetch for Prefetched<F> {
+    type Output = TrivyInformation;
+
+    fn key(&self) -> String {
+        self.fetcher.key()
+    }
+
+    async fn fetch(&self) -> Result<Self::Output> {
+        Ok(self.output.clone())
+    }
+
+    fn trend(&self, output: &Self::Output) -> Option<(String, TrendPoint)> {
+        self.fetcher.trend(output)
+    }
+}
+
+/// Caches the output of every scanner separately so enabling an additional
+/// scanner only runs the new one and reuses the cached outputs of the
+/// others. The scanners missing from the cache run in a single trivy
+/// invocation whose output is split into the outputs of each scanner.
+pub(crate) async fn cache_or_fetch_per_scanner<F: ScannerFetch>(
+    fetcher: &F,
+    scanners: &[Scanner],
+    cache: &Cache,
+    force: bool,
+) -> Result<TrivyInformation> {
+    let mut missing = Vec::new();
+
+    if !cache.read_only() {
+        for scanner in scanners {
+            if force || !fetcher.for_scanner(*scanner).is_cached(cache).await {
+                missing.push(*scanner);
+            }
+        }
+    }
+
+    let mut combined = BTreeMap::new();
+
+    // a single missing scanner is fetched like the cached ones
+    if missing.len() > 1 {
+        match fetcher.for_scanners(&missing).fetch_uncached(cache).await {
+            Ok(output) => {
+                for scanner in missing {
+                    let part = Prefetched {
+                        fetcher: fetcher.for_scanner(scanner),
+                        output: output.for_scanner(scanner),
+                    }
+                    .cache_or_fetch(cache, true)
+                    .await
+                    .with_context(|| format!("failed to cache output of scanner {scanner}"))?;
+
+                    combined.insert(scanner, part);
+                }
+            }
+
+            // cached outputs of the scanners can stand in while the trivy
+            // server is down
+            Err(err) if err.downcast_ref::<ServerUnavailable>().is_some() => {
+                tracing::warn!("falling back to fetching every scanner: {err:?}");
+            }
+
+            Err(err) => {
+                let scanners = missing
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                return Err(err.wrap_err(format!("failed to fetch output of scanners {scanners}")));
+            }
+        }
+    }
+
+    let mut parts = Vec::with_capacity(scanners.len());
+
+    for scanner in scanners {
+        let part = match combined.remove(scanner) {
+            Some(part) => part,
+
+            None => fetcher
+                .for_scanner(*scanner)
+                .cache_or_fetch(cache, force)
+                .await
+                .with_context(|| format!("failed to fetch output of scanner {scanner}"))?,
+        };
+
+        parts.push(part);
+    }
+
+    Ok(TrivyInformation::merge(parts))
+}
+
+/// `<user>@` for requests made with credentials so outputs combined with them
+/// are cached apart from anonymous ones and those of other users.
+fn identity_scope(identity: Option<&str>) -> String {
+    identity
+        .map(|identity| format!("{identity}@"))
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct DockerInformationFetcher {
+    pub(crate) registry_client: RegistryClient,
+    pub(crate) registry_limits: RegistryLimits,
+    pub(crate) image: Image,
+}
+
+impl Fetch for DockerInformationFetcher {
+    type Output = DockerInformation;
+
+    fn key(&self) -> String {
+        format!(
+            "{REDIS_KEY_PREFIX}:docker_manifest:{identity}{image}",
+            identity = identity_scope(self.registry_client.identity()),
+            image = self.image
+        )
+    }
+
+    async fn fetch(&self) -> Result<Self::Output> {
+        let response = self
+            .registry_limits
+            .run(async {
+                self.registry_client
+                    .get_manifest(&self.image)
+                    .instrument(info_span!("get docker manifest from docker registry"))
+                    .await
+                    .map_err(eyre::Report::from)
+            })
+            .await
+            .context("can not get manifest from docker registry")?;
+
+        // labels are informational only and must not hide the manifest
+        let labels = self
+            .registry_limits
+            .run(config_labels(
+                &self.registry_client,
+                &self.image,
+                &response.manifest,
+            ))
+            .await
+            .unwrap_or_else(|err| {
+                tracing::warn!("failed to get image config labels: {err:#}");
+
+                BTreeMap::new()
+            });
+
+        Ok(Self::Output {
+            response,
+            labels,
+            fetch_time: chrono::Utc::now(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TrivyInformationFetcher {
+    pub(crate) image: Image,
+    pub(crate) trivy_config: TrivyConfig,
+
+    /// Also report passed misconfiguration checks.
+    pub(crate) include_non_failures: bool,
+
+    /// Hide vulnerabilities that have no fixed version yet.
+    pub(crate) ignore_unfixed: bool,
+
+    /// Only vulnerabilities of at least this severity are kept.
+    pub(crate) min_severity: Option<Severity>,
+
+    /// Platform of a multi-arch image that is scanned instead of the one
+    /// trivy picks, e.g. `linux/arm64`.
+    pub(crate) platform: Option<String>,
+
+    /// Allowlisted trivy server used instead of the configured pool.
+    pub(crate) trivy_server: Option<String>,
+
+    /// Digest of the image when it is on the trusted digests allowlist, the
+    /// image is not scanned then.
+    pub(crate) trusted_digest: Option<String>,
+
+    pub(crate) trivy_username: Option<String>,
+    pub(crate) trivy_password: Option<Password>,
+
+    /// Listener of the phases of the scan, not part of the key.
+    pub(crate) progress: ScanProgress,
+}
+
+impl Fetch for TrivyInformationFetcher {
+    type Output = TrivyInformation;
+
+    fn key(&self) -> String {
+        let mut key = format!("{REDIS_KEY_PREFIX}:trivy");
+
+        if self.trusted_digest.is_some() {
+            key.push_str(":trusted");
+        }
+
+        if self.include_non_failures {
+            key.push_str(":include-non-failures");
+        }
+
+        if self.ignore_unfixed {
+            key.push_str(":ignore-unfixed");
+        }
+
+        if let Some(min_severity) = self.min_severity {
+            key.push_str(":min-severity-");
+            key.push_str(&min_severity.to_string());
+        }
+
+        if let Some(platform) = &self.platform {
+            key.push_str(":platform-");
+            key.push_str(platform);
+        }
+
+        if let Some(ignore_policy) = &self.trivy_config.ignore_policy {
+            key.push_str(":ignore-policy-");
+            key.push_str(&ignore_policy.hash);
+        }
+
+        if let Some(hash) = self
+            .trivy_config
+            .trivyignore
+            .as_ref()
+            .and_then(|trivyignore| trivyignore.hash.as_ref())
+        {
+            key.push_str(":trivyignore-");
+            key.push_str(hash);
+        }
+
+        if !self.trivy_config.severity_mapping.is_empty() {
+            key.push_str(":severity-mapping-");
+            key.push_str(&self.trivy_config.severity_mapping.hash());
+        }
+
+        if let Some(trivy_server) = &self.trivy_server {
+            key.push_str(":server-");
+            key.push_str(trivy_server);
+        }
+
+        if !self.trivy_config.scanners.is_empty() {
+            let scanners = self
+                .trivy_config
+                .scanners
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",");
+
+            key.push_str(":scanners-");
+            key.push_str(&scanners);
+        }
+
+        format!(
+            "{key}:{identity}{image}",
+            identity = identity_scope(self.trivy_username.as_deref()),
+            image = self.image
+        )
+    }
+
+    async fn fetch(&self) -> Result<Self::Output> {
+        if let Some(digest) = &self.trusted_digest {
+            tracing::info!("skipping scan of trusted digest {digest}");
+
+            return Ok(TrivyInformation::trusted(digest.clone()));
+        }
+
+        let trivy_result = trivy::scan_image(
+            &self.image,
+            &self.trivy_config,
+            self.trivy_server.as_deref(),
+            trivy::ScanFlags {
+                include_non_failures: self.include_non_failures,
+                ignore_unfixed: self.ignore_unfixed,
+            },
+            self.platform.as_deref(),
+            self.trivy_username.as_deref(),
+            self.trivy_password
+                .as_ref()
+                .map(|password| password.0.as_str()),
+            &self.progress,
+        )
+        .await?;
+
+        let mut information = TrivyInformation::new(trivy_result, &self.trivy_config.scanners);
+
+        information.remap_severities(&self.trivy_config.severity_mapping);
+
+        if let Some(min_severity) = self.min_severity {
+            information.retain_min_severity(min_severity);
+        }
+
+        metrics::record_vulnerabilities(information.severity_count());
+
+        Ok(information)
+    }
+
+    fn trend(&self, output: &Self::Output) -> Option<(String, TrendPoint)> {
+        // outputs without the vulnerability scanner would record zero counts
+        // and filtered outputs would record partial counts
+        let scanners = &self.trivy_config.scanners;
+
+        let filtered = self.min_severity.is_some() || self.ignore_unfixed;
+
+        (!filtered && (scanners.is_empty() || scanners.contains(&Scanner::Vuln)))
+            .then(|| (self.image.to_string(), output.trend_point()))
+    }
+}
+
+impl ScannerFetch for TrivyInformationFetcher {
+    fn for_scanners(&self, scanners: &[Scanner]) -> Self {
+        let mut fetcher = self.clone();
+        fetcher.trivy_config.scanners = scanners.to_vec();
+
+        fetcher
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ComplianceInformationFetcher {
+    pub(crate) image: Image,
+    pub(crate) trivy_config: TrivyConfig,
+
+    /// Allowlisted spec passed to trivy's `--compliance` flag.
+    pub(crate) spec: String,
+
+    pub(crate) trivy_username: Option<String>,
+    pub(crate) trivy_password: Option<Password>,
+}
+
+impl Fetch for ComplianceInformationFetcher {
+    type Output = ComplianceInformation;
+
+    fn key(&self) -> String {
+        format!(
+            "{REDIS_KEY_PREFIX}:compliance:{spec}:{identity}{image}",
+            spec = self.spec,
+            identity = identity_scope(self.trivy_username.as_deref()),
+            image = self.image
+        )
+    }
+
+    async fn fetch(&self) -> Result<Self::Output> {
+        let report = trivy::scan_compliance(
+            &self.image,
+            &self.trivy_config,
+            &self.spec,
+            self.trivy_username.as_deref(),
+            self.trivy_password
+                .as_ref()
+                .map(|password| password.0.as_str()),
+        )
+        .await?;
+
+        Ok(ComplianceInformation {
+            report,
+            fetch_time: Utc::now(),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct CosignInformationFetcher {
+    pub(crate) registry_client: RegistryClient,
+    pub(crate) cosign_fetches: CosignFetches,
+    pub(crate) image: Image,
+    pub(crate) digest: Option<String>,
+}
+
+impl Fetch for CosignInformationFetcher {
+    type Output = CosignInformation;
+
+    fn key(&self) -> String {
+        format!(
+            "{REDIS_KEY_PREFIX}:cosign:{identity}{image}",
+            identity = identity_scope(self.registry_client.identity()),
+            image = self.image
+        )
+    }
+
+    async fn fetch(&self) -> Result<Self::Output> {
+        let digest = self
+            .digest
+            .as_ref()
+            .ok_or_else(|| eyre::eyre!("Missing docker manifest digest"))?;
+
+        let cosign = self
+            .cosign_fetches
+            .fetch(
+                &self.image,
+                digest,
+                cosign::cosign_manifest(&self.registry_client, &self.image, digest)
+                    .instrument(info_span!("get cosign manifest")),
+            )
+            .await
+            .context("failed to get cosign manifest")?;
+
+        Ok(CosignInformation {
+            cosign,
+            fetch_time: Utc::now(),
+        })
+    }
+}
+
+impl Fetched for DockerInformation {
+    fn fetch_time(&self) -> DateTime<Utc> {
+        self.fetch_time
+    }
+}
+
+impl Fetched for TrivyInformation {
+    fn fetch_time(&self) -> DateTime<Utc> {
+        self.fetch_time
+    }
+
+    fn cooling_down(&mut self) {
+        self.cooling_down = true;
+    }
+
+    fn server_unavailable(&mut self) {
+        self.server_unavailable = true;
+    }
+}
+
+impl Fetched for ComplianceInformation {
+    fn fetch_time(&self) -> DateTime<Utc> {
+        self.fetch_time
+    }
+}
+
+impl Fetched for CosignInformation {
+    fn fetch_time(&self) -> DateTime<Utc> {
+        self.fetch_time
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod tests {
+    use std::sync::{
+        Arc,
+        atomic::{
+            AtomicBool,
+            AtomicUsize,
+            Ordering,
+        },
+    };
+
+    use chrono::{
+        DateTime,
+        Duration,
+        Utc,
+    };
+    use eyre::Result;
+    use redis::AsyncCommands;
+    use serde::{
+        Deserialize,
+        Serialize,
+    };
+
+    use super::{
+        Cache,
+        ComplianceInformationFetcher,
+        CosignFetches,
+        CosignInformationFetcher,
+        DEFAULT_REDIS_TTL,
+        DockerInformationFetcher,
+        Fetch,
+        Fetched,
+        MemoryCache,
+        ScannerFetch,
+        TrivyInformationFetcher,
+        cache_or_fetch_per_scanner,
+    };
+    use crate::handler::{
+        credentials::Credentials,
+        progress::ScanProgress,
+        registry::RegistryLimits,
+        registry_client::RegistryClient,
+        response::TrivyInformation,
+        trend::TrendPoint,
+        trivy::{
+            IgnorePolicy,
+            Scanner,
+            ServerUnavailable,
+            Severity,
+            TrivyConfig,
+            TrivyIgnore,
+            TrivyResult,
+        },
+    };
+
+    #[derive(Debug, Clone)]
+    struct CountingFetcher {
+        key: String,
+        fetches: Arc<AtomicUsize>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Output {
+        value: usize,
+        fetch_time: DateTime<Utc>,
+    }
+
+    impl Fetched for Output {
+        fn fetch_time(&self) -> DateTime<Utc> {
+            self.fetch_time
+        }
+    }
+
+    impl Fetch for CountingFetcher {
+        type Output = Output;
+
+        fn key(&self) -> String {
+            self.key.clone()
+        }
+
+        async fn fetch(&self) -> Result<Self::Output> {
+            Ok(Output {
+                value: self.fetches.fetch_add(1, Ordering::SeqCst) + 1,
+                fetch_time: Utc::now(),
+            })
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct ScanFetcher {
+        image: String,
+    }
+
+    impl Fetch for ScanFetcher {
+        type Output = TrivyInformation;
+
+        fn key(&self) -> String {
+            format!("trivy-web:test:trivy:{}", self.image)
+        }
+
+        async fn fetch(&self) -> Result<Self::Output> {
+            let trivy_result: TrivyResult =
+                serde_json::from_str(include_str!("../resources/tests/trivy_output.json"))?;
+
+            Ok(TrivyInformation::new(trivy_result, &[]))
+        }
+
+        fn trend(&self, output: &Self::Output) -> Option<(String, TrendPoint)> {
+            Some((self.image.clone(), output.trend_point()))
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct ServerDownFetcher {
+        image: String,
+        down: Arc<AtomicBool>,
+    }
+
+    impl Fetch for ServerDownFetcher {
+        type Output = TrivyInformation;
+
+        fn key(&self) -> String {
+            format!("trivy-web:test:server-down:{}", self.image)
+        }
+
+        async fn fetch(&self) -> Result<Self::Output> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(ServerUnavailable(
+                    "trivy server localhost:4954 is unreachable".to_string(),
+                )
+                .into());
+            }
+
+            ScanFetcher {
+                image: self.image.clone(),
+            }
+            .fetch()
+            .await
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct ScannerFetcher {
+        scanners: Vec<Scanner>,
+        runs: Arc<AtomicUsize>,
+    }
+
+    impl Fetch for ScannerFetcher {
+        type Output = TrivyInformation;
+
+        fn key(&self) -> String {
+            format!("trivy-web:test:scanner:{:?}", self.scanners)
+        }
+
+        async fn fetch(&self) -> Result<Self::Output> {
+            self.runs.fetch_add(1, Ordering::SeqCst);
+
+            let trivy_result: TrivyResult =
+                serde_json::from_str(include_str!("../resources/tests/trivy_output.json"))?;
+
+            Ok(TrivyInformation::new(trivy_result, &self.scanners))
+        }
+    }
+
+    impl ScannerFetch for ScannerFetcher {
+        fn for_scanners(&self, scanners: &[Scanner]) -> Self {
+            Self {
+                scanners: scanners.to_vec(),
+                runs: self.runs.clone(),
+            }
+        }
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn per_scanner_cache() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let fetcher = ScannerFetcher {
+            scanners: Vec::new(),
+            runs: Arc::new(AtomicUsize::new(0)),
+        };
+        let keys =
+            [Scanner::Vuln, Scanner::Secret].map(|scanner| fetcher.for_scanner(scanner).key());
+
+        connection.del::<_, ()>(&keys).await.unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client),
+            ..Cache::default()
+        };
+
+        let both =
+            cache_or_fetch_per_scanner(&fetcher, &[Scanner::Vuln, Scanner::Secret], &cache, false)
+                .await
+                .unwrap();
+        assert_eq!(1, fetcher.runs.load(Ordering::SeqCst));
+
+        let vuln = cache_or_fetch_per_scanner(&fetcher, &[Scanner::Vuln], &cache, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            1,
+            fetcher.runs.load(Ordering::SeqCst),
+            "vuln output is cached"
+        );
+        assert_eq!(both.severity_count, vuln.severity_count);
+
+        connection.del::<_, ()>(&keys).await.unwrap();
+    }
+
+    fn ran_scanners(information: &TrivyInformation) -> Vec<Scanner> {
+        information
+            .scanners
+            .iter()
+            .map(|status| status.scanner)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn per_scanner_single_run() {
+        let fetcher = ScannerFetcher {
+            scanners: Vec::new(),
+            runs: Arc::new(AtomicUsize::new(0)),
+        };
+        let cache = Cache::default();
+
+        let both = cache_or_fetch_per_scanner(
+            &fetcher,
+            &[Scanner::Vuln, Scanner::Misconfig],
+            &cache,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            1,
+            fetcher.runs.load(Ordering::SeqCst),
+            "missing scanners run together"
+        );
+
+        // only the newly enabled scanner runs
+        let all = cache_or_fetch_per_scanner(
+            &fetcher,
+            &[Scanner::Vuln, Scanner::Misconfig, Scanner::Secret],
+            &cache,
+            false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(2, fetcher.runs.load(Ordering::SeqCst));
+
+        let secret = cache
+            .memory
+            .0
+            .get(&fetcher.for_scanner(Scanner::Secret).key())
+            .await
+            .unwrap();
+        assert_eq!(
+            vec![Scanner::Secret],
+            ran_scanners(&serde_json::from_str(&secret).unwrap())
+        );
+
+        let vuln = cache_or_fetch_per_scanner(&fetcher, &[Scanner::Vuln], &cache, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            2,
+            fetcher.runs.load(Ordering::SeqCst),
+            "vuln output is cached"
+        );
+
+        assert_eq!(both.severity_count, vuln.severity_count);
+        assert_eq!(both.severity_count, all.severity_count);
+        assert_eq!(vec![Scanner::Vuln], ran_scanners(&vuln));
+        assert_eq!(
+            vec![Scanner::Vuln, Scanner::Misconfig, Scanner::Secret],
+            ran_scanners(&all)
+        );
+
+        cache_or_fetch_per_scanner(&fetcher, &[Scanner::Vuln, Scanner::Secret], &cache, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            3,
+            fetcher.runs.load(Ordering::SeqCst),
+            "forced scans run together"
+        );
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn trend_retained_past_ttl() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let image = "ghcr.io/test/trend:1.0.0";
+        let fetcher = ScanFetcher {
+            image: image.to_string(),
+        };
+        let trend_key = Cache::trend_key(image);
+
+        connection.del::<_, ()>(&trend_key).await.unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client.clone()),
+            ..Cache::default()
+        };
+
+        fetcher.refresh(&cache, &client).await.unwrap();
+        fetcher.refresh(&cache, &client).await.unwrap();
+
+        let points = cache.trend(&image.parse().unwrap()).await.unwrap();
+
+        assert_eq!(2, points.len());
+        assert!(points[0].scan_time <= points[1].scan_time);
+        assert_eq!(6, points[1].severity_count.critical);
+        assert_eq!(
+            Some("sha256:db900338f383082b1674249348a0d73e9aa25c62fd5d7d4c85e6cf3c83c0f9a6"),
+            points[1].digest.as_deref()
+        );
+
+        let output_ttl: i64 = connection.ttl(fetcher.key()).await.unwrap();
+        let trend_ttl: i64 = connection.ttl(&trend_key).await.unwrap();
+
+        assert!(output_ttl > 0 && output_ttl <= DEFAULT_REDIS_TTL);
+        assert_eq!(-1, trend_ttl, "trend points are kept indefinitely");
+
+        let cache = Cache {
+            trend_retention: Some(Duration::days(30)),
+            ..cache
+        };
+
+        fetcher.refresh(&cache, &client).await.unwrap();
+
+        let trend_ttl: i64 = connection.ttl(&trend_key).await.unwrap();
+
+        assert!(trend_ttl > DEFAULT_REDIS_TTL);
+        assert_eq!(3, cache.trend(&image.parse().unwrap()).await.unwrap().len());
+
+        connection
+            .del::<_, ()>(&[fetcher.key(), trend_key])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn event_after_fresh_scan() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let fetcher = ScanFetcher {
+            image: "ghcr.io/test/events:1.0.0".to_string(),
+        };
+        let stream = "trivy-web:test:events";
+
+        connection
+            .del::<_, ()>(&[fetcher.key(), stream.to_string()])
+            .await
+            .unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client),
+            event_stream: Some(stream.to_string()),
+            ..Cache::default()
+        };
+
+        let events = async |connection: &mut redis::aio::MultiplexedConnection| {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+            connection.xlen::<_, usize>(stream).await.unwrap()
+        };
+
+        fetcher.cache_or_fetch(&cache, false).await.unwrap();
+        assert_eq!(1, events(&mut connection).await);
+
+        // served from the cache, nothing new was scanned
+        fetcher.cache_or_fetch(&cache, false).await.unwrap();
+        assert_eq!(1, events(&mut connection).await);
+
+        let entries: redis::streams::StreamRangeReply =
+            connection.xrange_all(stream).await.unwrap();
+        let image: String = entries.ids[0].get("image").unwrap();
+        assert_eq!(fetcher.image, image);
+
+        connection
+            .del::<_, ()>(&[
+                fetcher.key(),
+                stream.to_string(),
+                Cache::trend_key(&fetcher.image),
+            ])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn read_only_hit() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let fetcher = CountingFetcher {
+            key: "trivy-web:test:read-only".to_string(),
+            fetches: Arc::new(AtomicUsize::new(0)),
+        };
+
+        let cached = Output {
+            value: 7,
+            fetch_time: Utc::now(),
+        };
+
+        connection
+            .set::<_, _, ()>(fetcher.key(), serde_json::to_string(&cached).unwrap())
+            .await
+            .unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client),
+            ..Cache::default()
+        };
+        cache.set_read_only(true);
+
+        let got = fetcher.cache_or_fetch(&cache, false).await.unwrap();
+        assert_eq!(cached, got);
+
+        connection.del::<_, ()>(fetcher.key()).await.unwrap();
+
+        let err = fetcher.cache_or_fetch(&cache, false).await.unwrap_err();
+        assert!(err.downcast_ref::<super::ReadOnly>().is_some());
+        assert_eq!(0, fetcher.fetches.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn trivy_key_ignore_policy() {
+        let fetcher = |hash: Option<&str>| TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig {
+                ignore_policy: hash.map(|hash| IgnorePolicy {
+                    path: "policy.rego".into(),
+                    hash: hash.to_string(),
+                }),
+                ..TrivyConfig::default()
+            },
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(None).key()
+        );
+        assert_eq!(
+            "trivy-web:trivy:ignore-policy-abc123:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(Some("abc123")).key()
+        );
+        assert_ne!(fetcher(Some("abc123")).key(), fetcher(Some("def456")).key());
+    }
+
+    #[test]
+    fn trivy_key_trivyignore() {
+        let fetcher = |hash: Option<&str>| TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig {
+                trivyignore: Some(TrivyIgnore {
+                    path: ".trivyignore".into(),
+                    hash: hash.map(ToString::to_string),
+                }),
+                ..TrivyConfig::default()
+            },
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(None).key()
+        );
+        assert_eq!(
+            "trivy-web:trivy:trivyignore-abc123:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(Some("abc123")).key()
+        );
+        assert_ne!(fetcher(Some("abc123")).key(), fetcher(Some("def456")).key());
+    }
+
+    #[tokio::test]
+    async fn redis_unreachable() {
+        let fetcher = CountingFetcher {
+            key: "trivy-web:test:unreachable".to_string(),
+            fetches: Arc::new(AtomicUsize::new(0)),
+        };
+
+        // nothing listens on port 1
+        let cache = Cache {
+            redis_client: Some(redis::Client::open("redis://127.0.0.1:1").unwrap()),
+            ..Cache::default()
+        };
+
+        assert_eq!(
+            1,
+            fetcher.cache_or_fetch(&cache, false).await.unwrap().value
+        );
+        assert_eq!(1, fetcher.fetches.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn keys_prefixed() {
+        let image: docker_registry_client::Image =
+            "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let keys = [
+            DockerInformationFetcher {
+                registry_client: RegistryClient::new(
+                    docker_registry_client::Client::default(),
+                    None,
+                ),
+                registry_limits: RegistryLimits::default(),
+                image: image.clone(),
+            }
+            .key(),
+            TrivyInformationFetcher {
+                image: image.clone(),
+                trivy_config: TrivyConfig::default(),
+                include_non_failures: false,
+                ignore_unfixed: false,
+                min_severity: None,
+                platform: None,
+                trivy_server: None,
+                trusted_digest: None,
+                trivy_username: None,
+                trivy_password: None,
+                progress: ScanProgress::default(),
+            }
+            .key(),
+            ComplianceInformationFetcher {
+                image: image.clone(),
+                trivy_config: TrivyConfig::default(),
+                spec: "docker-cis-1.6.0".to_string(),
+                trivy_username: None,
+                trivy_password: None,
+            }
+            .key(),
+            CosignInformationFetcher {
+                registry_client: RegistryClient::new(
+                    docker_registry_client::Client::default(),
+                    None,
+                ),
+                cosign_fetches: CosignFetches::default(),
+                image: image.clone(),
+                digest: None,
+            }
+            .key(),
+        ];
+
+        for key in keys {
+            assert!(key.starts_with("trivy-web:"), "unprefixed key {key}");
+        }
+    }
+
+    #[test]
+    fn docker_key_identity() {
+        let image: docker_registry_client::Image =
+            "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let registry_client = |credentials| {
+            RegistryClient::new(docker_registry_client::Client::default(), credentials)
+        };
+
+        let anonymous = DockerInformationFetcher {
+            registry_client: registry_client(None),
+            registry_limits: RegistryLimits::default(),
+            image: image.clone(),
+        };
+
+        let authenticated = DockerInformationFetcher {
+            registry_client: registry_client(Some(Credentials {
+                username: "ci".to_string(),
+                secret: "s3cr3t".to_string(),
+            })),
+            ..anonymous.clone()
+        };
+
+        assert_eq!(
+            "trivy-web:docker_manifest:ghcr.io/aquasecurity/trivy:0.52.0",
+            anonymous.key()
+        );
+        assert_eq!(
+            "trivy-web:docker_manifest:ci@ghcr.io/aquasecurity/trivy:0.52.0",
+            authenticated.key()
+        );
+
+        let cosign = CosignInformationFetcher {
+            registry_client: authenticated.registry_client,
+            cosign_fetches: CosignFetches::default(),
+            image,
+            digest: None,
+        };
+
+        assert_eq!(
+            "trivy-web:cosign:ci@ghcr.io/aquasecurity/trivy:0.52.0",
+            cosign.key()
+        );
+    }
+
+    #[test]
+    fn trivy_key_identity() {
+        let fetcher = |username: Option<&str>| TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig::default(),
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: username.map(ToString::to_string),
+            trivy_password: username.map(|_| crate::handler::Password("s3cr3t".to_string())),
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(None).key()
+        );
+        assert_eq!(
+            "trivy-web:trivy:ci@ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(Some("ci")).key()
+        );
+        assert_ne!(fetcher(Some("ci")).key(), fetcher(Some("release")).key());
+        assert!(!fetcher(Some("ci")).key().contains("s3cr3t"));
+
+        let compliance = |username: Option<&str>| ComplianceInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig::default(),
+            spec: "docker-cis-1.6.0".to_string(),
+            trivy_username: username.map(ToString::to_string),
+            trivy_password: None,
+        };
+
+        assert_ne!(compliance(None).key(), compliance(Some("ci")).key());
+        assert_ne!(
+            compliance(Some("ci")).key(),
+            compliance(Some("release")).key()
+        );
+    }
+
+    #[test]
+    fn trivy_key_server() {
+        let fetcher = TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig::default(),
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: Some("internal:4954".to_string()),
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:server-internal:4954:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher.key()
+        );
+    }
+
+    #[test]
+    fn trivy_key_scanners() {
+        let fetcher = TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig {
+                scanners: vec![Scanner::Vuln, Scanner::Secret],
+                ..TrivyConfig::default()
+            },
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:scanners-vuln,secret:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher.key()
+        );
+        assert_eq!(
+            "trivy-web:trivy:scanners-vuln:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher.for_scanner(Scanner::Vuln).key()
+        );
+    }
+
+    #[test]
+    fn trivy_key_platform() {
+        let fetcher = TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig::default(),
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: Some("linux/arm64".to_string()),
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:platform-linux/arm64:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher.key()
+        );
+    }
+
+    #[test]
+    fn trivy_key_severity_mapping() {
+        let fetcher = |mapping: &str| TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig {
+                severity_mapping: mapping.parse().unwrap(),
+                ..TrivyConfig::default()
+            },
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher("").key()
+        );
+        assert!(
+            fetcher("medium=high")
+                .key()
+                .starts_with("trivy-web:trivy:severity-mapping-")
+        );
+        assert_ne!(fetcher("medium=high").key(), fetcher("low=high").key());
+    }
+
+    #[test]
+    fn trivy_key_ignore_unfixed() {
+        let fetcher = |ignore_unfixed: bool| TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig::default(),
+            include_non_failures: false,
+            ignore_unfixed,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:ignore-unfixed:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(true).key()
+        );
+        assert_eq!(
+            "trivy-web:trivy:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher(false).key()
+        );
+    }
+
+    #[test]
+    fn trivy_key_min_severity() {
+        let fetcher = TrivyInformationFetcher {
+            image: "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            trivy_config: TrivyConfig::default(),
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: Some(Severity::High),
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        assert_eq!(
+            "trivy-web:trivy:min-severity-HIGH:ghcr.io/aquasecurity/trivy:0.52.0",
+            fetcher.key()
+        );
+        assert!(
+            fetcher
+                .trend(&TrivyInformation::merge(Vec::new()))
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_cache() {
+        let fetcher = CountingFetcher {
+            key: "trivy-web:test:memory".to_string(),
+            fetches: Arc::default(),
+        };
+
+        let cache = Cache::default();
+
+        let first = fetcher.cache_or_fetch(&cache, false).await.unwrap();
+        let second = fetcher.cache_or_fetch(&cache, false).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(1, fetcher.fetches.load(Ordering::SeqCst));
+
+        let forced = fetcher.cache_or_fetch(&cache, true).await.unwrap();
+        assert_eq!(2, forced.value);
+
+        cache.set_read_only(true);
+        assert_eq!(forced, fetcher.cache_or_fetch(&cache, false).await.unwrap());
+
+        let other = CountingFetcher {
+            key: "trivy-web:test:memory-miss".to_string(),
+            fetches: Arc::default(),
+        };
+        assert!(other.cache_or_fetch(&cache, false).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn memory_cache_ttl() {
+        let fetcher = CountingFetcher {
+            key: "trivy-web:test:memory-ttl".to_string(),
+            fetches: Arc::default(),
+        };
+
+        let cache = Cache {
+            memory: MemoryCache::new(Duration::milliseconds(50)),
+            ..Cache::default()
+        };
+
+        fetcher.cache_or_fetch(&cache, false).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        fetcher.cache_or_fetch(&cache, false).await.unwrap();
+
+        assert_eq!(2, fetcher.fetches.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn stale_while_revalidate() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let fetcher = CountingFetcher {
+            key: "test:stale_while_revalidate".to_string(),
+            fetches: Arc::default(),
+        };
+
+        let stale = Output {
+            value: 0,
+            fetch_time: Utc::now() - Duration::hours(2),
+        };
+
+        connection
+            .set::<_, _, ()>(fetcher.key(), serde_json::to_string(&stale).unwrap())
+            .await
+            .unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client),
+            fresh_for: Some(Duration::hours(1)),
+            ..Cache::default()
+        };
+
+        let got = fetcher.cache_or_fetch(&cache, false).await.unwrap();
+        assert_eq!(stale, got);
+
+        let mut refreshed = None;
+
+        for _ in 0..50 {
+            let cached: String = connection.get(fetcher.key()).await.unwrap();
+            let cached: Output = serde_json::from_str(&cached).unwrap();
+
+            if cached.value == 1 {
+                refreshed = Some(cached);
+                break;
+            }
+
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+
+        assert_eq!(Some(1), refreshed.map(|output| output.value));
+        assert_eq!(1, fetcher.fetches.load(Ordering::SeqCst));
+
+        connection.del::<_, ()>(fetcher.key()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn refresh_cooldown() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let fetcher = CountingFetcher {
+            key: "trivy-web:test:cooldown".to_string(),
+            fetches: Arc::default(),
+        };
+        let cooldown_key = Cache::cooldown_key(&fetcher.key());
+
+        connection
+            .del::<_, ()>(&[fetcher.key(), cooldown_key.clone()])
+            .await
+            .unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client.clone()),
+            refresh_cooldown: Some(Duration::seconds(60)),
+            ..Cache::default()
+        };
+
+        let first = fetcher.refresh(&cache, &client).await.unwrap();
+        let second = fetcher.refresh(&cache, &client).await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(1, fetcher.fetches.load(Ordering::SeqCst));
+
+        connection
+            .del::<_, ()>(&[fetcher.key(), cooldown_key])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn force_refresh() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let fetcher = CountingFetcher {
+            key: "trivy-web:test:force".to_string(),
+            fetches: Arc::default(),
+        };
+
+        connection.del::<_, ()>(fetcher.key()).await.unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client),
+            ..Cache::default()
+        };
+
+        assert_eq!(
+            1,
+            fetcher.cache_or_fetch(&cache, false).await.unwrap().value
+        );
+        assert_eq!(
+            1,
+            fetcher.cache_or_fetch(&cache, false).await.unwrap().value
+        );
+        assert_eq!(2, fetcher.cache_or_fetch(&cache, true).await.unwrap().value);
+
+        // the forced fetch replaced the cached output
+        assert_eq!(
+            2,
+            fetcher.cache_or_fetch(&cache, false).await.unwrap().value
+        );
+        assert_eq!(2, fetcher.fetches.load(Ordering::SeqCst));
+
+        connection.del::<_, ()>(fetcher.key()).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg_attr(
+        feature = "ci",
+        ignore = "requires a local redis server at 127.0.0.1:6379"
+    )]
+    async fn server_unavailable() {
+        let client = redis::Client::open("redis://127.0.0.1:6379").unwrap();
+        let mut connection = client.get_multiplexed_async_connection().await.unwrap();
+
+        let down = Arc::new(AtomicBool::new(false));
+        let fetcher = |image: &str| ServerDownFetcher {
+            image: image.to_string(),
+            down: down.clone(),
+        };
+        let cached = fetcher("ghcr.io/test/cached:1.0.0");
+        let uncached = fetcher("ghcr.io/test/uncached:1.0.0");
+
+        connection
+            .del::<_, ()>(&[cached.key(), uncached.key()])
+            .await
+            .unwrap();
+
+        let cache = Cache {
+            redis_client: Some(client),
+            ..Cache::default()
+        };
+
+        let first = cached.cache_or_fetch(&cache, false).await.unwrap();
+        assert!(!first.server_unavailable);
+
+        down.store(true, Ordering::SeqCst);
+
+        let rescanned = cached.cache_or_fetch(&cache, true).await.unwrap();
+        assert!(rescanned.server_unavailable);
+        assert_eq!(first.vulnerabilities, rescanned.vulnerabilities);
+
+        let err = uncached.cache_or_fetch(&cache, false).await.unwrap_err();
+        assert!(err.downcast_ref::<ServerUnavailable>().is_some());
+
+        connection.del::<_, ()>(cached.key()).await.unwrap();
+    }
+
+    #[test]
+    fn cooldown_key() {
+        assert_eq!(
+            "trivy-web:cooldown:trivy:ghcr.io/aquasecurity/trivy:0.52.0",
+            Cache::cooldown_key("trivy-web:trivy:ghcr.io/aquasecurity/trivy:0.52.0")
+        );
     }
 }
@@ -0,0 +1,214 @@
+use eyre::{
+    Context,
+    Result,
+};
+use tokio::sync::{
+    mpsc,
+    oneshot,
+};
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+/// A cached output that expires after `ttl` seconds.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Write {
+    key: String,
+    value: String,
+    ttl: u64,
+}
+
+#[derive(Debug)]
+enum Message {
+    Write(Write, oneshot::Sender<Result<(), String>>),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Collects cache writes for a short window and stores them in a single
+/// redis pipeline instead of a round trip per write.
+#[derive(Debug, Clone)]
+pub(crate) struct WriteBatcher {
+    sender: mpsc::UnboundedSender<Message>,
+}
+
+impl WriteBatcher {
+    pub(crate) fn new(redis_client: redis::Client, window: std::time::Duration) -> Self {
+        Self::with_flush(window, move |writes| {
+            let redis_client = redis_client.clone();
+
+            async move { set_pipelined(&redis_client, writes).await }
+        })
+    }
+
+    fn with_flush<F, Fut>(window: std::time::Duration, flush: F) -> Self
+    where
+        F: Fn(Vec<Write>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        tokio::spawn(run(receiver, window, flush).instrument(info_span!("batch cache writes")));
+
+        Self { sender }
+    }
+
+    /// Queues the write and waits until its batch was flushed so the output
+    /// can be read back afterwards.
+    pub(crate) async fn set(&self, key: String, value: String, ttl: u64) -> Result<()> {
+        let (done, result) = oneshot::channel();
+
+        self.sender
+            .send(Message::Write(Write { key, value, ttl }, done))
+            .map_err(|_| eyre::eyre!("cache write batcher stopped"))?;
+
+        result
+            .await
+            .context("cache write batcher stopped")?
+            .map_err(eyre::Report::msg)
+    }
+
+    /// Flushes the pending writes immediately, e.g. on shutdown.
+    pub(crate) async fn flush(&self) {
+        let (done, flushed) = oneshot::channel();
+
+        if self.sender.send(Message::Flush(done)).is_ok() {
+            let _ = flushed.await;
+        }
+    }
+}
+
+async fn run<F, Fut>(
+    mut receiver: mpsc::UnboundedReceiver<Message>,
+    window: std::time::Duration,
+    flush: F,
+) where
+    F: Fn(Vec<Write>) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    while let Some(message) = receiver.recv().await {
+        let mut writes = Vec::new();
+        let mut waiting = Vec::new();
+        let mut flushed = None;
+
+        let deadline = tokio::time::sleep(window);
+        tokio::pin!(deadline);
+
+        let mut message = Some(message);
+
+        while let Some(next) = message.take() {
+            match next {
+                Message::Write(write, done) => {
+                    writes.push(write);
+                    waiting.push(done);
+                }
+
+                Message::Flush(done) => {
+                    flushed = Some(done);
+                    break;
+                }
+            }
+
+            message = tokio::select! {
+                () = &mut deadline => None,
+                next = receiver.recv() => next,
+            };
+        }
+
+        let result = if writes.is_empty() {
+            Ok(())
+        } else {
+            flush(writes).await.map_err(|err| format!("{err:#}"))
+        };
+
+        if let Err(err) = &result {
+            tracing::error!("failed to flush cache writes: {err}");
+        }
+
+        for done in waiting {
+            let _ = done.send(result.clone());
+        }
+
+        if let Some(done) = flushed {
+            let _ = done.send(());
+        }
+    }
+}
+
+async fn set_pipelined(redis_client: &redis::Client, writes: Vec<Write>) -> Result<()> {
+    let mut connection = redis_client
+        .get_multiplexed_async_connection()
+        .instrument(info_span!("get redis connection"))
+        .await
+        .context("failed to get redis connection")?;
+
+    let mut pipeline = redis::pipe();
+
+    for write in writes {
+        pipeline.set_ex(write.key, write.value, write.ttl).ignore();
+    }
+
+    pipeline
+        .query_async::<()>(&mut connection)
+        .instrument(info_span!("set outputs in redis"))
+        .await
+        .context("failed to set outputs in redis")
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        sync::{
+            Arc,
+            Mutex,
+        },
+        time::Duration,
+    };
+
+    use super::{
+        Write,
+        WriteBatcher,
+    };
+
+    #[tokio::test]
+    async fn writes_in_window_flushed_together() {
+        let flushed = Arc::new(Mutex::new(Vec::<Vec<Write>>::new()));
+
+        let batcher = WriteBatcher::with_flush(Duration::from_millis(50), {
+            let flushed = flushed.clone();
+
+            move |writes| {
+                flushed.lock().unwrap().push(writes);
+
+                async { Ok(()) }
+            }
+        });
+
+        let writes = (0..3).map(|index| {
+            let batcher = batcher.clone();
+
+            tokio::spawn(async move {
+                batcher
+                    .set(format!("key-{index}"), "value".to_string(), 60)
+                    .await
+            })
+        });
+
+        for write in writes.collect::<Vec<_>>() {
+            write.await.unwrap().unwrap();
+        }
+
+        {
+            let pipelines = flushed.lock().unwrap();
+
+            assert_eq!(1, pipelines.len());
+            assert_eq!(3, pipelines[0].len());
+            assert!(pipelines[0].iter().all(|write| write.ttl == 60));
+        }
+
+        // flushing without pending writes does not run an empty pipeline
+        batcher.flush().await;
+        assert_eq!(1, flushed.lock().unwrap().len());
+    }
+}
@@ -0,0 +1,80 @@
+use serde::Serialize;
+
+use crate::handler::trivy::Severity;
+
+const VERSION: &str = "2.1.0";
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Minimal SARIF 2.1.0 log as accepted by GitHub code scanning.
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct Sarif {
+    version: &'static str,
+
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+
+    pub(crate) runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct Run {
+    tool: Tool,
+    pub(crate) results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct Driver {
+    name: &'static str,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SarifResult {
+    pub(crate) rule_id: String,
+    pub(crate) level: Level,
+    pub(crate) message: Message,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct Message {
+    pub(crate) text: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Level {
+    Error,
+    Warning,
+    Note,
+}
+
+impl From<Severity> for Level {
+    fn from(severity: Severity) -> Self {
+        match severity {
+            Severity::Critical | Severity::High => Self::Error,
+            Severity::Medium => Self::Warning,
+            Severity::Low | Severity::Unknown => Self::Note,
+        }
+    }
+}
+
+impl Sarif {
+    /// Single trivy run with the given results.
+    pub(crate) fn new(results: Vec<SarifResult>) -> Self {
+        Self {
+            version: VERSION,
+            schema: SCHEMA,
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver { name: "trivy" },
+                },
+                results,
+            }],
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::io::Write;
+
+use docker_registry_client::Image;
+use eyre::{
+    Context,
+    Result,
+};
+use serde::Serialize;
+use tokio::io::{
+    AsyncBufRead,
+    AsyncBufReadExt,
+    BufReader,
+};
+
+use crate::{
+    args::{
+        OutputFormat,
+        ScanArgs,
+    },
+    handler::{
+        progress::ScanProgress,
+        response::{
+            TrivyInformation,
+            cache::{
+                Fetch,
+                TrivyInformationFetcher,
+            },
+        },
+        trivy::{
+            SeverityCount,
+            TrivyConfig,
+        },
+    },
+};
+
+#[derive(Debug, Serialize)]
+struct Summary<'a> {
+    image: &'a str,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity_count: Option<&'a SeverityCount>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Scans every image listed in the input and prints a summary to stdout.
+/// Fails when at least one image could not be scanned.
+pub(crate) async fn run(trivy_config: TrivyConfig, args: &ScanArgs) -> Result<()> {
+    let scan = |image: Image| {
+        let fetcher = TrivyInformationFetcher {
+            image,
+            trivy_config: trivy_config.clone(),
+            include_non_failures: false,
+            ignore_unfixed: false,
+            min_severity: None,
+            platform: None,
+            trivy_server: None,
+            trusted_digest: None,
+            trivy_username: None,
+            trivy_password: None,
+            progress: ScanProgress::default(),
+        };
+
+        async move { fetcher.fetch().await }
+    };
+
+    let mut stdout = std::io::stdout().lock();
+
+    let failed = match &args.input {
+        Some(path) => {
+            let file = tokio::fs::File::open(path)
+                .await
+                .context("failed to open image list")?;
+
+            scan_images(BufReader::new(file), &mut stdout, args.format, scan).await?
+        }
+
+        None => {
+            scan_images(
+                BufReader::new(tokio::io::stdin()),
+                &mut stdout,
+                args.format,
+                scan,
+            )
+            .await?
+        }
+    };
+
+    if failed > 0 {
+        return Err(eyre::eyre!("failed to scan {failed} images"));
+    }
+
+    Ok(())
+}
+
+async fn scan_images<R, W, F, Fut>(
+    input: R,
+    output: &mut W,
+    format: OutputFormat,
+    scan: F,
+) -> Result<usize>
+where
+    R: AsyncBufRead + Unpin,
+    W: Write,
+    F: Fn(Image) -> Fut,
+    Fut: Future<Output = Result<TrivyInformation>>,
+{
+    let mut lines = input.lines();
+    let mut results = Vec::new();
+
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .context("failed to read image list")?
+    {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let information = match line.parse::<Image>() {
+            Ok(image) => scan(image).await,
+            Err(err) => Err(eyre::eyre!("failed to parse image: {err}")),
+        };
+
+        results.push((line.to_string(), information));
+    }
+
+    let summaries = results
+        .iter()
+        .map(|(image, information)| match information {
+            Ok(information) => Summary {
+                image,
+                severity_count: Some(information.severity_count()),
+                error: None,
+            },
+
+            Err(err) => Summary {
+                image,
+                severity_count: None,
+                error: Some(format!("{err:#}")),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut *output, &summaries)
+                .context("failed to write json summary")?;
+
+            writeln!(output).context("failed to write json summary")?;
+        }
+
+        OutputFormat::Table => write_table(output, &summaries).context("failed to write table")?,
+    }
+
+    Ok(summaries
+        .iter()
+        .filter(|summary| summary.error.is_some())
+        .count())
+}
+
+fn write_table<W: Write>(output: &mut W, summaries: &[Summary<'_>]) -> std::io::Result<()> {
+    let width = summaries
+        .iter()
+        .map(|summary| summary.image.len())
+        .max()
+        .unwrap_or_default()
+        .max("IMAGE".len());
+
+    writeln!(
+        output,
+        "{:width$}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}",
+        "IMAGE", "CRITICAL", "HIGH", "MEDIUM", "LOW", "UNKNOWN"
+    )?;
+
+    for summary in summaries {
+        match (summary.severity_count, &summary.error) {
+            (Some(count), _) => writeln!(
+                output,
+                "{:width$}  {:>8}  {:>8}  {:>8}  {:>8}  {:>8}",
+                summary.image, count.critical, count.high, count.medium, count.low, count.unknown
+            )?,
+
+            (None, error) => writeln!(
+                output,
+                "{:width$}  ERROR: {}",
+                summary.image,
+                error.as_deref().unwrap_or_default()
+            )?,
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use docker_registry_client::Image;
+    use pretty_assertions::assert_eq;
+
+    use super::scan_images;
+    use crate::{
+        args::OutputFormat,
+        handler::{
+            response::TrivyInformation,
+            trivy::TrivyResult,
+        },
+    };
+
+    const INPUT: &[u8] =
+        b"# images to scan\nlinuxserver/code-server:latest\n\nghcr.io/aquasecurity/trivy:0.0.0\n";
+
+    async fn scan(image: Image) -> eyre::Result<TrivyInformation> {
+        if image.to_string().contains("code-server") {
+            let trivy_result = serde_json::from_str::<TrivyResult>(include_str!(
+                "resources/tests/trivy_output.json"
+            ))
+            .unwrap();
+
+            Ok(TrivyInformation::new(trivy_result, &[]))
+        } else {
+            Err(eyre::eyre!("manifest unknown"))
+        }
+    }
+
+    #[tokio::test]
+    async fn table() {
+        let mut output = Vec::new();
+
+        let failed = scan_images(INPUT, &mut output, OutputFormat::Table, scan)
+            .await
+            .unwrap();
+
+        assert_eq!(1, failed);
+
+        let expected = "\
+IMAGE                             CRITICAL      HIGH    MEDIUM       LOW   UNKNOWN
+linuxserver/code-server:latest           6        16        13        32         0
+ghcr.io/aquasecurity/trivy:0.0.0  ERROR: manifest unknown
+";
+
+        assert_eq!(expected, String::from_utf8(output).unwrap());
+    }
+
+    #[tokio::test]
+    async fn json() {
+        let mut output = Vec::new();
+
+        scan_images(INPUT, &mut output, OutputFormat::Json, scan)
+            .await
+            .unwrap();
+
+        let got: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        let expected = serde_json::json!([
+            {
+                "image": "linuxserver/code-server:latest",
+                "severity_count": {
+                    "critical": 6,
+                    "high": 16,
+                    "medium": 13,
+                    "low": 32,
+                    "unknown": 0
+                }
+            },
+            {
+                "image": "ghcr.io/aquasecurity/trivy:0.0.0",
+                "error": "manifest unknown"
+            }
+        ]);
+
+        assert_eq!(expected, got);
+    }
+}
@@ -0,0 +1,132 @@
+use std::{
+    fmt::Write,
+    sync::Arc,
+};
+
+use axum::{
+    body::Body,
+    http::{
+        HeaderValue,
+        Response,
+        StatusCode,
+    },
+    response::IntoResponse,
+};
+use hmac::{
+    Hmac,
+    Mac,
+};
+use sha2::Sha256;
+
+/// Header carrying the signature of a scan result.
+pub(crate) const SIGNATURE_HEADER: &str = "x-signature";
+
+/// Signs scan results with HMAC-SHA256 so consumers holding the key can
+/// verify a report came from this instance unmodified.
+#[derive(Clone)]
+pub(crate) struct ResultSigner {
+    key: Arc<[u8]>,
+}
+
+impl std::fmt::Debug for ResultSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ResultSigner").finish_non_exhaustive()
+    }
+}
+
+impl ResultSigner {
+    pub(crate) fn new(key: &str) -> Self {
+        Self {
+            key: key.as_bytes().into(),
+        }
+    }
+
+    /// `sha256=<hex>` signature of the payload.
+    pub(crate) fn sign(&self, payload: &[u8]) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("hmac accepts keys of any length");
+        mac.update(payload);
+
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .fold(String::from("sha256="), |mut signature, byte| {
+                let _ = write!(signature, "{byte:02x}");
+                signature
+            })
+    }
+
+    /// Adds the signature of the body as the `X-Signature` header, the body
+    /// is buffered to sign it.
+    pub(crate) async fn sign_response(&self, response: Response<Body>) -> Response<Body> {
+        let (mut parts, body) = response.into_parts();
+
+        let body = match axum::body::to_bytes(body, usize::MAX).await {
+            Ok(body) => body,
+
+            Err(err) => {
+                tracing::error!("failed to buffer the result to sign: {err}");
+
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+
+        match HeaderValue::from_str(&self.sign(&body)) {
+            Ok(signature) => {
+                parts.headers.insert(SIGNATURE_HEADER, signature);
+            }
+
+            Err(err) => tracing::error!("invalid signature header: {err}"),
+        }
+
+        Response::from_parts(parts, Body::from(body))
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use axum::{
+        Json,
+        response::IntoResponse,
+    };
+
+    use super::{
+        ResultSigner,
+        SIGNATURE_HEADER,
+    };
+
+    #[test]
+    fn sign() {
+        // RFC 4231 test case 2
+        let signer = ResultSigner::new("Jefe");
+
+        assert_eq!(
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            signer.sign(b"what do ya want for nothing?")
+        );
+    }
+
+    #[tokio::test]
+    async fn sign_response() {
+        let signer = ResultSigner::new("secret");
+        let payload = serde_json::json!({"vulnerabilities": [], "digest": "sha256:abc"});
+
+        let response = signer
+            .sign_response(Json(payload.clone()).into_response())
+            .await;
+
+        let signature = response.headers()[SIGNATURE_HEADER]
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        assert_eq!(payload.to_string().as_bytes(), body);
+        assert_eq!(signer.sign(&body), signature);
+        assert_ne!(ResultSigner::new("other").sign(&body), signature);
+    }
+}
@@ -0,0 +1,134 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        Mutex,
+        PoisonError,
+    },
+};
+
+use eyre::Result;
+use tokio::sync::OnceCell;
+
+type Flight<T> = Arc<OnceCell<Result<T, Arc<eyre::Report>>>>;
+
+/// Coalesces concurrent fetches of the same key, callers arriving while a
+/// fetch runs wait for it and share its output instead of fetching again.
+#[derive(Debug)]
+pub(crate) struct SingleFlight<T> {
+    in_flight: Arc<Mutex<HashMap<String, Flight<T>>>>,
+}
+
+impl<T> Clone for SingleFlight<T> {
+    fn clone(&self) -> Self {
+        Self {
+            in_flight: self.in_flight.clone(),
+        }
+    }
+}
+
+impl<T> Default for SingleFlight<T> {
+    fn default() -> Self {
+        Self {
+            in_flight: Arc::default(),
+        }
+    }
+}
+
+impl<T: Clone> SingleFlight<T> {
+    /// Runs the fetch unless one for the key is already running. Another
+    /// waiting caller takes over when the running fetch is cancelled.
+    pub(crate) async fn run(&self, key: &str, fetch: impl Future<Output = Result<T>>) -> Result<T> {
+        let flight = self
+            .in_flight
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(key.to_string())
+            .or_default()
+            .clone();
+
+        let output = flight
+            .get_or_init(|| async { fetch.await.map_err(Arc::new) })
+            .await
+            .clone();
+
+        // later callers fetch again, caching the output is up to the caller
+        {
+            let mut in_flight = self
+                .in_flight
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+
+            if in_flight
+                .get(key)
+                .is_some_and(|current| Arc::ptr_eq(current, &flight))
+            {
+                in_flight.remove(key);
+            }
+        }
+
+        output.map_err(|err| eyre::eyre!("{err:#}"))
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        sync::{
+            Arc,
+            atomic::{
+                AtomicUsize,
+                Ordering,
+            },
+        },
+        time::Duration,
+    };
+
+    use super::SingleFlight;
+
+    #[tokio::test]
+    async fn coalesce() {
+        let single_flight = SingleFlight::<usize>::default();
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let fetch = || {
+            let fetches = fetches.clone();
+
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+
+                Ok(fetches.fetch_add(1, Ordering::SeqCst) + 1)
+            }
+        };
+
+        let mut tasks = tokio::task::JoinSet::new();
+
+        for _ in 0..8 {
+            let single_flight = single_flight.clone();
+            let fetch = fetch();
+
+            tasks.spawn(async move { single_flight.run("image", fetch).await.unwrap() });
+        }
+
+        let outputs = tasks.join_all().await;
+
+        assert!(outputs.iter().all(|output| *output == 1));
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+
+        // finished fetches are not remembered
+        assert_eq!(2, single_flight.run("image", fetch()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn shared_error() {
+        let single_flight = SingleFlight::<usize>::default();
+
+        let err = single_flight
+            .run("image", async { Err(eyre::eyre!("manifest unknown")) })
+            .await
+            .unwrap_err();
+
+        assert_eq!("manifest unknown", err.to_string());
+    }
+}
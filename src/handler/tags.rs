@@ -0,0 +1,206 @@
+use docker_registry_client::Image;
+use eyre::Result;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::task::JoinSet;
+use utoipa::ToSchema;
+
+use crate::handler::{
+    response::TrivyInformation,
+    trivy::{
+        SeverityCount,
+        SeverityWeights,
+    },
+};
+
+/// Maximum number of tags scanned by a single request.
+pub(super) const MAX_TAGS: usize = 20;
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct TagsRequest {
+    pub(super) repository: String,
+    pub(super) tags: Vec<String>,
+}
+
+/// Scan summaries of several tags of the same repository.
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub(super) struct TagComparison {
+    pub(super) repository: String,
+    pub(super) tags: Vec<TagSummary>,
+
+    /// Tag with the fewest critical vulnerabilities, ties are broken by the
+    /// number of high vulnerabilities and then by the requested order.
+    pub(super) best_tag: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, ToSchema)]
+pub(super) struct TagSummary {
+    pub(super) tag: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) severity_count: Option<SeverityCount>,
+
+    /// Weighted severity count to rank tags by a single number.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) risk_score: Option<usize>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) error: Option<String>,
+}
+
+/// Scans all tags concurrently, the scan function is expected to apply the
+/// configured trivy concurrency limits.
+pub(super) async fn scan_tags<F, Fut>(
+    repository: &str,
+    tags: &[String],
+    severity_weights: SeverityWeights,
+    scan: F,
+) -> TagComparison
+where
+    F: Fn(Image) -> Fut,
+    Fut: Future<Output = Result<TrivyInformation>> + Send + 'static,
+{
+    let mut scans = JoinSet::new();
+
+    for (index, tag) in tags.iter().enumerate() {
+        match format!("{repository}:{tag}").parse::<Image>() {
+            Ok(image) => {
+                let scan = scan(image);
+                scans.spawn(async move { (index, scan.await) });
+            }
+
+            Err(err) => {
+                scans.spawn(
+                    async move { (index, Err(eyre::eyre!("failed to parse image: {err}"))) },
+                );
+            }
+        }
+    }
+
+    let mut results = Vec::with_capacity(tags.len());
+
+    while let Some(result) = scans.join_next().await {
+        match result {
+            Ok(result) => results.push(result),
+            Err(err) => tracing::error!("tag scan task failed: {err}"),
+        }
+    }
+
+    results.sort_by_key(|(index, _)| *index);
+
+    let summaries = results
+        .into_iter()
+        .map(|(index, information)| {
+            let tag = tags[index].clone();
+
+            match information {
+                Ok(information) => TagSummary {
+                    tag,
+                    severity_count: Some(information.severity_count().clone()),
+                    risk_score: Some(information.severity_count().risk_score(severity_weights)),
+                    error: None,
+                },
+
+                Err(err) => TagSummary {
+                    tag,
+                    severity_count: None,
+                    risk_score: None,
+                    error: Some(format!("{err:#}")),
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let best_tag = summaries
+        .iter()
+        .filter_map(|summary| {
+            summary
+                .severity_count
+                .as_ref()
+                .map(|count| ((count.critical, count.high), summary))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, summary)| summary.tag.clone());
+
+    TagComparison {
+        repository: repository.to_string(),
+        tags: summaries,
+        best_tag,
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use docker_registry_client::Image;
+    use pretty_assertions::assert_eq;
+
+    use crate::handler::{
+        response::TrivyInformation,
+        trivy::{
+            SeverityWeights,
+            TrivyResult,
+        },
+    };
+
+    #[expect(
+        clippy::needless_pass_by_value,
+        reason = "matches the scan function scan_tags expects"
+    )]
+    fn scan(image: Image) -> impl Future<Output = eyre::Result<TrivyInformation>> {
+        let data = match image.image_name.to_string().as_str() {
+            "trivy:0.50.0" => Some(include_str!("resources/tests/trivy_output.json")),
+            "trivy:0.51.0" => Some(include_str!("resources/tests/trivy_output2.json")),
+            "trivy:0.52.0" => Some(include_str!("resources/tests/trivy_output3.json")),
+            _ => None,
+        };
+
+        async move {
+            let data = data.ok_or_else(|| eyre::eyre!("manifest unknown"))?;
+            let trivy_result = serde_json::from_str::<TrivyResult>(data).unwrap();
+
+            Ok(TrivyInformation::new(trivy_result, &[]))
+        }
+    }
+
+    #[tokio::test]
+    async fn best_tag() {
+        let tags = ["0.50.0", "0.51.0", "0.52.0", "0.0.0"]
+            .map(ToString::to_string)
+            .to_vec();
+
+        let got = super::scan_tags(
+            "ghcr.io/aquasecurity/trivy",
+            &tags,
+            SeverityWeights::default(),
+            scan,
+        )
+        .await;
+
+        let criticals = got
+            .tags
+            .iter()
+            .map(|summary| {
+                (
+                    summary.tag.as_str(),
+                    summary.severity_count.as_ref().map(|count| count.critical),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                ("0.50.0", Some(6)),
+                ("0.51.0", Some(1)),
+                ("0.52.0", Some(0)),
+                ("0.0.0", None),
+            ],
+            criticals
+        );
+
+        assert_eq!(Some("manifest unknown"), got.tags[3].error.as_deref());
+        assert_eq!(Some("0.52.0".to_string()), got.best_tag);
+    }
+}
@@ -0,0 +1,20 @@
+use chrono::{
+    DateTime,
+    Utc,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use utoipa::ToSchema;
+
+use crate::handler::trivy::SeverityCount;
+
+/// Compact record of a single scan that is kept after the full result
+/// expired so the development of an image can be followed over time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+pub(crate) struct TrendPoint {
+    pub(super) digest: Option<String>,
+    pub(super) scan_time: DateTime<Utc>,
+    pub(super) severity_count: SeverityCount,
+}
@@ -1,6 +1,13 @@
-use std::collections::{
-    BTreeMap,
-    BTreeSet,
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+    },
+    fmt::Write,
+    path::{
+        Path,
+        PathBuf,
+    },
 };
 
 use docker_registry_client::Image;
@@ -9,27 +16,321 @@ use serde::{
     Deserialize,
     Serialize,
 };
+use sha2::{
+    Digest,
+    Sha256,
+};
 use tokio::process::Command;
 use tracing::{
     Instrument,
     info_span,
 };
 use url::Url;
+use utoipa::ToSchema;
+
+use super::{
+    command,
+    metrics,
+    progress::{
+        ScanPhase,
+        ScanProgress,
+    },
+};
+
+mod compliance;
+mod db;
+mod plugin;
+mod pool;
+mod queue;
+mod sbom;
+
+pub(crate) use compliance::{
+    ComplianceNotAllowed,
+    ComplianceReport,
+    scan_compliance,
+};
+pub(crate) use db::{
+    DbStatus,
+    check_binary,
+    db_status,
+    update_db,
+};
+pub(crate) use plugin::{
+    PluginNotAllowed,
+    run_plugin,
+};
+pub(crate) use pool::TrivyServerPool;
+pub(crate) use queue::ScanQueue;
+pub(crate) use sbom::{
+    generate_platform_sboms,
+    stream_sbom,
+};
+
+/// Operator supplied settings that apply to every trivy invocation.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrivyConfig {
+    /// Trivy executable, `trivy` is looked up in the `PATH` when unset.
+    pub(crate) binary: Option<PathBuf>,
+
+    /// Trivy servers used for client/server scanning, scans run locally when
+    /// the pool is empty.
+    pub(crate) servers: TrivyServerPool,
+
+    /// Servers a request may pick instead of the pool. Only configured
+    /// addresses are accepted so requests can not make trivy connect to
+    /// arbitrary hosts.
+    pub(crate) allowed_servers: TrivyServerPool,
+
+    /// Value passed to trivy's `--parallel` flag.
+    pub(crate) parallel: Option<usize>,
+
+    /// Scanners passed to trivy's `--scanners` flag, trivy's defaults are
+    /// used when empty.
+    pub(crate) scanners: Vec<Scanner>,
+
+    /// Rego policy passed to trivy's `--ignore-policy` flag.
+    pub(crate) ignore_policy: Option<IgnorePolicy>,
+
+    /// `.trivyignore` with accepted vulnerability IDs passed to trivy's
+    /// `--ignorefile` flag.
+    pub(crate) trivyignore: Option<TrivyIgnore>,
+
+    /// Trivy plugins requests may run against an image.
+    pub(crate) plugins: Vec<String>,
+
+    /// Compliance specs passed to trivy's `--compliance` flag that requests
+    /// may check an image against.
+    pub(crate) compliance: Vec<String>,
+
+    /// Docker config JSON with registry credentials, e.g. a mounted
+    /// `dockerconfigjson` secret. Only configured on the server so requests
+    /// can not make trivy read arbitrary files.
+    pub(crate) pull_secret: Option<PathBuf>,
+
+    /// Trivy runs longer than this are killed, e.g. a hanging DB download.
+    pub(crate) timeout: Option<std::time::Duration>,
+
+    /// Severities reclassified after scanning.
+    pub(crate) severity_mapping: SeverityMapping,
+
+    /// Limits the number of trivy scans running at once.
+    pub(crate) queue: ScanQueue,
+}
+
+/// Returned when a request asks for a trivy server that is not configured.
+#[derive(Debug)]
+pub(crate) struct ServerNotAllowed(pub(crate) String);
+
+impl std::fmt::Display for ServerNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trivy server {} is not allowed", self.0)
+    }
+}
+
+impl std::error::Error for ServerNotAllowed {}
+
+impl TrivyConfig {
+    /// Command running the configured trivy executable.
+    pub(crate) fn command(&self) -> Command {
+        Command::new(self.binary.as_deref().unwrap_or(Path::new("trivy")))
+    }
+
+    pub(crate) fn is_allowed_server(&self, address: &str) -> bool {
+        self.servers
+            .addresses()
+            .chain(self.allowed_servers.addresses())
+            .any(|allowed| allowed == address)
+    }
+
+    /// Waits for a slot on the requested server, falls back to the least
+    /// loaded server of the pool when no server was requested.
+    async fn acquire_server(
+        &self,
+        address: Option<&str>,
+    ) -> Result<Option<pool::ServerLease>, ServerNotAllowed> {
+        let Some(address) = address else {
+            return Ok(self.servers.acquire().await);
+        };
+
+        if let Some(lease) = self.servers.acquire_address(address).await {
+            return Ok(Some(lease));
+        }
+
+        self.allowed_servers
+            .acquire_address(address)
+            .await
+            .map(Some)
+            .ok_or_else(|| ServerNotAllowed(address.to_string()))
+    }
+}
+
+/// Rego policy file used by trivy to suppress findings. The hash is part of
+/// the cache key so changing the policy invalidates cached results.
+#[derive(Debug, Clone)]
+pub(crate) struct IgnorePolicy {
+    pub(crate) path: PathBuf,
+    pub(crate) hash: String,
+}
+
+impl IgnorePolicy {
+    pub(crate) async fn load(path: PathBuf) -> eyre::Result<Self> {
+        let policy = tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("failed to read ignore policy {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            hash: sha256_hex(&policy),
+        })
+    }
+}
+
+/// `.trivyignore` with accepted vulnerability IDs. The hash of its contents
+/// is part of the cache key like the hash of the ignore policy, it is
+/// missing when the file could not be read at startup.
+#[derive(Debug, Clone)]
+pub(crate) struct TrivyIgnore {
+    pub(crate) path: PathBuf,
+    pub(crate) hash: Option<String>,
+}
 
-#[derive(Debug, Deserialize)]
+impl TrivyIgnore {
+    pub(crate) async fn load(path: PathBuf) -> Self {
+        let hash = tokio::fs::read(&path)
+            .await
+            .ok()
+            .map(|trivyignore| sha256_hex(&trivyignore));
+
+        Self { path, hash }
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::new(), |mut hash, byte| {
+            let _ = write!(hash, "{byte:02x}");
+            hash
+        })
+}
+
+/// Scanners trivy runs when `--scanners` is not given.
+const DEFAULT_SCANNERS: &[Scanner] = &[Scanner::Vuln, Scanner::Secret];
+
+#[derive(
+    Debug,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Clone,
+    Copy,
+    clap::ValueEnum,
+    ToSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Scanner {
+    Vuln,
+    Misconfig,
+    Secret,
+    License,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, ToSchema)]
+pub(super) struct ScannerStatus {
+    pub(super) scanner: Scanner,
+
+    /// False when trivy reported the scanner as failed or disabled.
+    pub(super) ran: bool,
+
+    /// The scanner reported findings, a scanner that ran on a clean image
+    /// has none.
+    #[serde(default)]
+    pub(super) findings: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub(super) struct TrivyResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) schema_version: Option<u32>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) artifact_name: Option<String>,
+
     #[serde(default)]
     pub(super) results: Vec<Results>,
+
+    #[serde(default)]
+    pub(super) metadata: Metadata,
+
+    /// Scanners trivy reported as failed or disabled on stderr.
+    #[serde(skip)]
+    pub(super) failed_scanners: Vec<Scanner>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct Metadata {
+    #[serde(default)]
+    pub(super) repo_digests: Vec<String>,
+}
+
+impl Metadata {
+    /// Digest of the scanned image, e.g. `sha256:...`.
+    pub(super) fn digest(&self) -> Option<&str> {
+        self.repo_digests
+            .first()
+            .and_then(|reference| reference.split_once('@'))
+            .map(|(_, digest)| digest)
+    }
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "PascalCase")]
 pub(super) struct Results {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) target: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) class: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(super) vulnerabilities: Option<Vec<Vulnerability>>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(super) misconfigurations: Option<Vec<Misconfiguration>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct Misconfiguration {
+    pub(super) status: MisconfigurationStatus,
+    pub(super) severity: Severity,
+
+    #[serde(rename = "ID")]
+    pub(super) id: String,
+
+    pub(super) title: Option<String>,
+    pub(super) message: Option<String>,
+
+    #[serde(rename = "PrimaryURL")]
+    pub(super) primary_url: Option<Url>,
+}
+
+/// Result of a misconfiguration check, passed checks are only reported by
+/// trivy with `--include-non-failures`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, ToSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub(super) enum MisconfigurationStatus {
+    Fail,
+    Exception,
+    Pass,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, ToSchema)]
 #[serde(rename_all = "PascalCase")]
 pub(super) struct Vulnerability {
     pub(super) severity: Severity,
@@ -37,39 +338,111 @@ pub(super) struct Vulnerability {
     #[serde(rename = "VulnerabilityID")]
     pub(super) id: String,
 
+    /// Other ids of the same vulnerability, e.g. the CVE of a GHSA advisory
+    /// or the vendor advisories of a CVE.
+    #[serde(default, alias = "VendorIDs")]
+    pub(super) aliases: BTreeSet<String>,
+
     pub(super) references: Option<BTreeSet<String>>,
     pub(super) pkg_name: String,
+
+    /// Location of the package inside the image, only reported for language
+    /// packages.
+    #[serde(default)]
+    pub(super) pkg_path: Option<String>,
+
     pub(super) installed_version: String,
     pub(super) primary_url: Option<Url>,
     pub(super) fixed_version: Option<String>,
     pub(super) title: Option<String>,
 
+    #[serde(default)]
+    pub(super) status: Option<VulnerabilityStatus>,
+
     #[serde(rename = "CVSS")]
     pub(super) cvss: Option<BTreeMap<String, Cvss>>,
+
+    /// Image layer that introduced the package.
+    #[serde(default)]
+    pub(super) layer: Option<Layer>,
+
+    /// The severity was derived from a CVSS score as trivy reported none.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(super) derived_severity: bool,
+
+    /// Severity trivy reported before the severity mapping reclassified it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) original_severity: Option<Severity>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, ToSchema)]
+#[serde(rename_all = "PascalCase")]
+pub(super) struct Layer {
+    #[serde(default)]
+    pub(super) digest: Option<String>,
+
+    #[serde(default, rename = "DiffID")]
+    pub(super) diff_id: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
+impl Layer {
+    /// Digest of the layer, trivy only reports the diff id for images it
+    /// did not pull from a registry.
+    pub(super) fn id(&self) -> Option<&str> {
+        self.digest.as_deref().or(self.diff_id.as_deref())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, ToSchema)]
 pub(super) struct Cvss {
     #[serde(rename = "V2Vector")]
     v2vector: Option<String>,
     #[serde(rename = "V3Vector")]
     v3vector: Option<String>,
     #[serde(rename = "V2Score")]
+    #[schema(value_type = Option<f64>)]
     v2score: Option<Score>,
     #[serde(rename = "V3Score")]
+    #[schema(value_type = Option<f64>)]
     v3score: Option<Score>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
-pub(super) struct Score(String);
+/// CVSS score, compared by its numeric value so `10` ranks above `9.8`.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl Score {
+    pub(super) fn value(self) -> f64 {
+        self.0
+    }
+}
 
 impl Serialize for Score {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
-        let value = self.0.parse::<f64>().map_err(serde::ser::Error::custom)?;
-        f64::serialize(&value, serializer)
+        f64::serialize(&self.0, serializer)
     }
 }
 
@@ -78,8 +451,7 @@ impl<'de> Deserialize<'de> for Score {
     where
         D: serde::Deserializer<'de>,
     {
-        let value = f64::deserialize(deserializer)?;
-        Ok(Score(value.to_string()))
+        f64::deserialize(deserializer).map(Score)
     }
 }
 
@@ -89,15 +461,42 @@ impl std::fmt::Display for Score {
     }
 }
 
+/// CVSS version whose score is shown when a source reports both.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CvssVersion {
+    #[default]
+    V3,
+    V2,
+}
+
 impl Cvss {
-    pub(super) fn score(&self) -> Option<&Score> {
-        self.v2score.as_ref().or(self.v3score.as_ref())
+    /// Score of the preferred version, the other version is only used as a
+    /// fallback.
+    pub(super) fn score(&self, preferred: CvssVersion) -> Option<&Score> {
+        match preferred {
+            CvssVersion::V3 => self.v3score.as_ref().or(self.v2score.as_ref()),
+            CvssVersion::V2 => self.v2score.as_ref().or(self.v3score.as_ref()),
+        }
+    }
+
+    pub(super) fn v2_score(&self) -> Option<&Score> {
+        self.v2score.as_ref()
+    }
+
+    pub(super) fn v3_score(&self) -> Option<&Score> {
+        self.v3score.as_ref()
+    }
+
+    /// Highest score of both versions.
+    fn highest_score(&self) -> Option<Score> {
+        self.v3score.max(self.v2score)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, ToSchema)]
 #[serde(rename_all = "UPPERCASE")]
-pub(super) enum Severity {
+pub(crate) enum Severity {
     Critical,
     High,
     Medium,
@@ -105,7 +504,23 @@ pub(super) enum Severity {
     Unknown,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+/// Vendor status of a vulnerability as reported by trivy.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(super) enum VulnerabilityStatus {
+    NotAffected,
+    Affected,
+    Fixed,
+    UnderInvestigation,
+    WillNotFix,
+    FixDeferred,
+    EndOfLife,
+
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone, ToSchema)]
 pub(super) struct SeverityCount {
     pub(super) critical: usize,
     pub(super) high: usize,
@@ -114,6 +529,189 @@ pub(super) struct SeverityCount {
     pub(super) unknown: usize,
 }
 
+/// Weight of every severity in the risk score of a scan.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct SeverityWeights {
+    critical: usize,
+    high: usize,
+    medium: usize,
+    low: usize,
+    unknown: usize,
+}
+
+impl Default for SeverityWeights {
+    fn default() -> Self {
+        Self {
+            critical: 10,
+            high: 5,
+            medium: 2,
+            low: 1,
+            unknown: 0,
+        }
+    }
+}
+
+impl std::str::FromStr for SeverityWeights {
+    type Err = eyre::Error;
+
+    /// Parses comma separated `severity=weight` pairs, severities that are
+    /// not given keep their default weight.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut weights = Self::default();
+
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (severity, weight) = pair
+                .split_once('=')
+                .ok_or_else(|| eyre::eyre!("weight {pair} is not in severity=weight format"))?;
+
+            let weight = weight
+                .trim()
+                .parse()
+                .map_err(|err| eyre::eyre!("invalid weight {pair}: {err}"))?;
+
+            match severity.trim() {
+                "critical" => weights.critical = weight,
+                "high" => weights.high = weight,
+                "medium" => weights.medium = weight,
+                "low" => weights.low = weight,
+                "unknown" => weights.unknown = weight,
+                severity => return Err(eyre::eyre!("unknown severity {severity}")),
+            }
+        }
+
+        Ok(weights)
+    }
+}
+
+/// Severities reclassified after scanning, e.g. `medium=high` to gate on
+/// medium vulnerabilities as if they were high.
+#[derive(Debug, Default, Serialize, PartialEq, Eq, Clone)]
+#[serde(transparent)]
+pub(crate) struct SeverityMapping(BTreeMap<Severity, Severity>);
+
+impl std::str::FromStr for SeverityMapping {
+    type Err = eyre::Error;
+
+    /// Parses comma separated `severity=severity` pairs.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mapping = BTreeMap::new();
+
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (from, to) = pair
+                .split_once('=')
+                .ok_or_else(|| eyre::eyre!("mapping {pair} is not in severity=severity format"))?;
+
+            mapping.insert(from.trim().parse()?, to.trim().parse()?);
+        }
+
+        Ok(Self(mapping))
+    }
+}
+
+impl std::fmt::Display for SeverityMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self
+            .0
+            .iter()
+            .map(|(from, to)| format!("{from}={to}"))
+            .collect::<Vec<_>>();
+
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+impl SeverityMapping {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Hash of the mapping, part of the cache key so changing the mapping
+    /// invalidates cached results.
+    pub(crate) fn hash(&self) -> String {
+        sha256_hex(self.to_string().as_bytes())
+    }
+
+    /// Severity the given severity is reclassified as, if any.
+    pub(super) fn get(&self, severity: Severity) -> Option<Severity> {
+        self.0
+            .get(&severity)
+            .copied()
+            .filter(|mapped| *mapped != severity)
+    }
+}
+
+impl SeverityCount {
+    /// Weighted sum of the counts to rank scans by a single number.
+    pub(super) fn risk_score(&self, weights: SeverityWeights) -> usize {
+        self.critical * weights.critical
+            + self.high * weights.high
+            + self.medium * weights.medium
+            + self.low * weights.low
+            + self.unknown * weights.unknown
+    }
+}
+
+impl std::fmt::Display for Scanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scanner::Vuln => write!(f, "vuln"),
+            Scanner::Misconfig => write!(f, "misconfig"),
+            Scanner::Secret => write!(f, "secret"),
+            Scanner::License => write!(f, "license"),
+        }
+    }
+}
+
+impl Scanner {
+    /// Result classes trivy emits for findings of this scanner.
+    fn classes(self) -> &'static [&'static str] {
+        match self {
+            Scanner::Vuln => &["os-pkgs", "lang-pkgs"],
+            Scanner::Misconfig => &["config"],
+            Scanner::Secret => &["secret"],
+            Scanner::License => &["license", "license-file"],
+        }
+    }
+
+    /// The result holds findings of this scanner. Trivy reports package
+    /// and config results even when they are clean, secret and license
+    /// results only with findings.
+    fn found(self, result: &Results) -> bool {
+        if !result
+            .class
+            .as_deref()
+            .is_some_and(|class| self.classes().contains(&class))
+        {
+            return false;
+        }
+
+        match self {
+            Scanner::Vuln => result
+                .vulnerabilities
+                .as_ref()
+                .is_some_and(|vulnerabilities| !vulnerabilities.is_empty()),
+            Scanner::Misconfig => result
+                .misconfigurations
+                .as_ref()
+                .is_some_and(|misconfigurations| !misconfigurations.is_empty()),
+            Scanner::Secret | Scanner::License => true,
+        }
+    }
+}
+
+impl Severity {
+    /// Qualitative rating of a CVSS score, scores of 0 have no rating.
+    pub(super) fn from_cvss_score(score: f64) -> Self {
+        match score {
+            9.0.. => Severity::Critical,
+            7.0.. => Severity::High,
+            4.0.. => Severity::Medium,
+            0.1.. => Severity::Low,
+            _ => Severity::Unknown,
+        }
+    }
+}
+
 impl std::fmt::Display for Severity {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -126,6 +724,64 @@ impl std::fmt::Display for Severity {
     }
 }
 
+impl std::str::FromStr for Severity {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_uppercase().as_str() {
+            "CRITICAL" => Ok(Severity::Critical),
+            "HIGH" => Ok(Severity::High),
+            "MEDIUM" => Ok(Severity::Medium),
+            "LOW" => Ok(Severity::Low),
+            "UNKNOWN" => Ok(Severity::Unknown),
+            _ => Err(eyre::eyre!("unknown severity {s}")),
+        }
+    }
+}
+
+impl std::fmt::Display for MisconfigurationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MisconfigurationStatus::Fail => write!(f, "FAIL"),
+            MisconfigurationStatus::Exception => write!(f, "EXCEPTION"),
+            MisconfigurationStatus::Pass => write!(f, "PASS"),
+        }
+    }
+}
+
+impl std::fmt::Display for VulnerabilityStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VulnerabilityStatus::Unknown => write!(f, "unknown"),
+            VulnerabilityStatus::NotAffected => write!(f, "not_affected"),
+            VulnerabilityStatus::Affected => write!(f, "affected"),
+            VulnerabilityStatus::Fixed => write!(f, "fixed"),
+            VulnerabilityStatus::UnderInvestigation => write!(f, "under_investigation"),
+            VulnerabilityStatus::WillNotFix => write!(f, "will_not_fix"),
+            VulnerabilityStatus::FixDeferred => write!(f, "fix_deferred"),
+            VulnerabilityStatus::EndOfLife => write!(f, "end_of_life"),
+        }
+    }
+}
+
+impl std::str::FromStr for VulnerabilityStatus {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "unknown" => Ok(VulnerabilityStatus::Unknown),
+            "not_affected" => Ok(VulnerabilityStatus::NotAffected),
+            "affected" => Ok(VulnerabilityStatus::Affected),
+            "fixed" => Ok(VulnerabilityStatus::Fixed),
+            "under_investigation" => Ok(VulnerabilityStatus::UnderInvestigation),
+            "will_not_fix" => Ok(VulnerabilityStatus::WillNotFix),
+            "fix_deferred" => Ok(VulnerabilityStatus::FixDeferred),
+            "end_of_life" => Ok(VulnerabilityStatus::EndOfLife),
+            _ => Err(eyre::eyre!("unknown vulnerability status {s}")),
+        }
+    }
+}
+
 pub(super) fn get_vulnerabilities_count(vulnerabilities: BTreeSet<Vulnerability>) -> SeverityCount {
     let mut vulnerabilities_count = SeverityCount::default();
 
@@ -142,7 +798,161 @@ pub(super) fn get_vulnerabilities_count(vulnerabilities: BTreeSet<Vulnerability>
     vulnerabilities_count
 }
 
+/// Status of the requested scanners. A scanner ran unless trivy reported it
+/// as failed or disabled, the result classes only tell whether it found
+/// anything as trivy omits sections without findings for some scanners.
+pub(super) fn scanner_status(
+    results: &[Results],
+    requested: &[Scanner],
+    failed: &[Scanner],
+) -> Vec<ScannerStatus> {
+    let requested = if requested.is_empty() {
+        DEFAULT_SCANNERS
+    } else {
+        requested
+    };
+
+    requested
+        .iter()
+        .map(|scanner| ScannerStatus {
+            scanner: *scanner,
+            ran: !failed.contains(scanner),
+            findings: results.iter().any(|result| scanner.found(result)),
+        })
+        .collect()
+}
+
+/// Trivy tags its log lines with the scanner they are about, e.g.
+/// `ERROR [secret] ...`. Errors and notices that a scanner is disabled mark
+/// it as failed.
+fn failed_scanners(stderr: &str) -> Vec<Scanner> {
+    <Scanner as clap::ValueEnum>::value_variants()
+        .iter()
+        .copied()
+        .filter(|scanner| {
+            let tag = format!("[{scanner}]");
+
+            stderr.lines().any(|line| {
+                line.contains(&tag)
+                    && (line.contains("ERROR") || line.to_lowercase().contains("disabled"))
+            })
+        })
+        .collect()
+}
+
 impl Vulnerability {
+    /// Derives the severity of a vulnerability trivy reported as unknown
+    /// from the CVSS score of the first source of the fallback chain that has
+    /// one. Returns true when the severity changed.
+    pub(super) fn derive_severity(&mut self, sources: &[String], version: CvssVersion) -> bool {
+        if self.severity != Severity::Unknown {
+            return false;
+        }
+
+        let Some(cvss) = &self.cvss else {
+            return false;
+        };
+
+        let severity = sources
+            .iter()
+            .filter_map(|source| cvss.get(source)?.score(version).copied().map(Score::value))
+            .map(Severity::from_cvss_score)
+            .find(|severity| *severity != Severity::Unknown);
+
+        let Some(severity) = severity else {
+            return false;
+        };
+
+        self.severity = severity;
+        self.derived_severity = true;
+
+        true
+    }
+
+    /// Reclassifies the severity according to the mapping and keeps the
+    /// reported severity. Returns true when the severity changed.
+    pub(super) fn remap_severity(&mut self, mapping: &SeverityMapping) -> bool {
+        let Some(severity) = mapping.get(self.severity) else {
+            return false;
+        };
+
+        self.original_severity = Some(self.severity);
+        self.severity = severity;
+
+        true
+    }
+
+    /// Folds a finding of the same vulnerability in the same package version
+    /// reported under another target into this one. References and aliases
+    /// are merged, the higher CVSS score of every source and the higher
+    /// severity are kept.
+    pub(super) fn merge_duplicate(&mut self, other: Self) {
+        self.severity = self.severity.min(other.severity);
+        self.aliases.extend(other.aliases);
+
+        if let Some(references) = other.references {
+            self.references
+                .get_or_insert_with(BTreeSet::new)
+                .extend(references);
+        }
+
+        if let Some(other_cvss) = other.cvss {
+            let cvss = self.cvss.get_or_insert_with(BTreeMap::new);
+
+            for (source, other_score) in other_cvss {
+                match cvss.get(&source) {
+                    Some(score) if score.highest_score() >= other_score.highest_score() => {}
+                    _ => {
+                        cvss.insert(source, other_score);
+                    }
+                }
+            }
+        }
+
+        self.fixed_version = self.fixed_version.take().or(other.fixed_version);
+        self.primary_url = self.primary_url.take().or(other.primary_url);
+        self.title = self.title.take().or(other.title);
+        self.status = self.status.or(other.status);
+        self.layer = self.layer.take().or(other.layer);
+    }
+
+    /// Reports advisories that have a CVE alias under the CVE and keeps the
+    /// advisory id as an alias. Returns true when the id changed.
+    pub(super) fn canonicalize_id(&mut self) -> bool {
+        if self.id.starts_with("CVE-") {
+            return false;
+        }
+
+        let Some(cve) = self
+            .aliases
+            .iter()
+            .find(|alias| alias.starts_with("CVE-"))
+            .cloned()
+        else {
+            return false;
+        };
+
+        self.aliases.remove(&cve);
+        self.aliases.insert(std::mem::replace(&mut self.id, cve));
+
+        true
+    }
+
+    /// Splits the references into the ones shown by default and the ones
+    /// collapsed behind a "show all" expansion.
+    pub(super) fn references(&self, limit: usize) -> (Vec<&str>, Vec<&str>) {
+        let mut references = self
+            .references
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        let hidden = references.split_off(limit.min(references.len()));
+
+        (references, hidden)
+    }
+
     pub(super) fn primary_url(&self) -> Option<&str> {
         self.primary_url.as_ref().map(url::Url::as_str).or_else(|| {
             self.references
@@ -151,62 +961,488 @@ impl Vulnerability {
                 .map(String::as_str)
         })
     }
+
+    /// Highest V2 or V3 score any source reported.
+    pub(super) fn highest_score(&self) -> Option<Score> {
+        self.cvss
+            .as_ref()?
+            .values()
+            .filter_map(Cvss::highest_score)
+            .max()
+    }
 }
 
-#[tracing::instrument]
-pub(super) async fn scan_image(
+/// Flags of a scan that change which findings trivy reports.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct ScanFlags {
+    /// Also report passed misconfiguration checks.
+    pub(crate) include_non_failures: bool,
+
+    /// Hide vulnerabilities that have no fixed version yet.
+    pub(crate) ignore_unfixed: bool,
+}
+
+pub(super) fn command(
     image: &Image,
+    config: &TrivyConfig,
     server: Option<&str>,
+    flags: ScanFlags,
+    platform: Option<&str>,
     username: Option<&str>,
     password: Option<&str>,
-) -> Result<TrivyResult, eyre::Error> {
+) -> Command {
     // run following command trivy image --format json
     // linuxserver/code-server:latest
 
-    let mut command = Command::new("trivy");
-
-    let mut command = command.arg("image").arg("--format").arg("json");
+    let mut command = image_command(config, "json");
 
     if let Some(server) = server {
-        command = command.arg("--server").arg(server);
+        command.arg("--server").arg(server);
     }
 
-    command = command.arg(image.to_string());
+    if let Some(parallel) = config.parallel {
+        command.arg("--parallel").arg(parallel.to_string());
+    }
+
+    if !config.scanners.is_empty() {
+        let scanners = config
+            .scanners
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",");
+
+        command.arg("--scanners").arg(scanners);
+    }
+
+    if flags.include_non_failures {
+        command.arg("--include-non-failures");
+    }
+
+    if flags.ignore_unfixed {
+        command.arg("--ignore-unfixed");
+    }
 
+    if let Some(ignore_policy) = &config.ignore_policy {
+        command.arg("--ignore-policy").arg(&ignore_policy.path);
+    }
+
+    if let Some(trivyignore) = &config.trivyignore {
+        command.arg("--ignorefile").arg(&trivyignore.path);
+    }
+
+    if let Some(platform) = platform {
+        command.arg("--platform").arg(platform);
+    }
+
+    set_image(&mut command, image, config, username, password);
+
+    command
+}
+
+/// Builds `trivy image --format <format>`, format specific flags follow
+/// before [`set_image`] adds the image.
+fn image_command(config: &TrivyConfig, format: &str) -> Command {
+    let mut command = config.command();
+
+    command.arg("image").arg("--format").arg(format);
+
+    command
+}
+
+/// Adds the image as the last argument and the registry credentials to pull
+/// it.
+fn set_image(
+    command: &mut Command,
+    image: &Image,
+    config: &TrivyConfig,
+    username: Option<&str>,
+    password: Option<&str>,
+) {
+    command.arg(image.to_string());
+
+    set_pull_secret(command, config);
+    set_credentials(command, username, password);
+}
+
+/// Trivy reads registry credentials from `config.json` in `DOCKER_CONFIG`,
+/// so the directory of the configured file is used. Credentials entered in
+/// the form take precedence.
+fn set_pull_secret(command: &mut Command, config: &TrivyConfig) {
+    if let Some(directory) = config.pull_secret.as_ref().and_then(|path| path.parent()) {
+        command.env("DOCKER_CONFIG", directory);
+    }
+}
+
+/// Registry credentials are passed through the environment so they do not
+/// show up in the process list.
+fn set_credentials(command: &mut Command, username: Option<&str>, password: Option<&str>) {
     if let Some(username) = username
         && let Some(password) = password
     {
-        command = command
+        command
             .env("TRIVY_USERNAME", username)
             .env("TRIVY_PASSWORD", password);
     }
+}
+
+/// Problems with the configured trivy server that can be recognized from
+/// trivy's stderr.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+enum ServerProblem {
+    /// Nothing answered on the configured address.
+    Unreachable,
+
+    /// Something answered that is not a trivy server, e.g. a reverse proxy
+    /// returning an HTML error page.
+    Misconfigured,
+}
+
+const UNREACHABLE_SIGNATURES: &[&str] = &[
+    "connection refused",
+    "connection reset",
+    "no such host",
+    "i/o timeout",
+];
+
+const MISCONFIGURED_SIGNATURES: &[&str] = &["<html", "<!doctype html", "invalid character '<'"];
+
+/// Trivy reports problems with its server on stderr, any other failure
+/// (e.g. a missing image) says nothing about the server's health.
+fn server_problem(stderr: &str) -> Option<ServerProblem> {
+    let stderr = stderr.to_lowercase();
+
+    if UNREACHABLE_SIGNATURES
+        .iter()
+        .any(|signature| stderr.contains(signature))
+    {
+        Some(ServerProblem::Unreachable)
+    } else if MISCONFIGURED_SIGNATURES
+        .iter()
+        .any(|signature| stderr.contains(signature))
+    {
+        Some(ServerProblem::Misconfigured)
+    } else {
+        None
+    }
+}
+
+/// Returned when the trivy server could not be used for a scan, cached scans
+/// of the image can still be served.
+#[derive(Debug)]
+pub(crate) struct ServerUnavailable(pub(crate) String);
+
+impl std::fmt::Display for ServerUnavailable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
 
-    let output = command
-        .output()
+impl std::error::Error for ServerUnavailable {}
+
+/// Replaces trivy's error with a specific message when the failure was
+/// caused by the trivy server instead of the scanned image.
+fn server_error(address: &str, stderr: String) -> eyre::Report {
+    let message = match server_problem(&stderr) {
+        Some(ServerProblem::Unreachable) => format!("trivy server {address} is unreachable"),
+
+        Some(ServerProblem::Misconfigured) => format!(
+            "trivy server {address} is misconfigured, the address does not point to a trivy server"
+        ),
+
+        None => return eyre::Report::msg(stderr),
+    };
+
+    eyre::Report::msg(stderr).wrap_err(ServerUnavailable(message))
+}
+
+#[tracing::instrument]
+#[expect(
+    clippy::too_many_arguments,
+    reason = "the options of a scan are passed on as they are"
+)]
+pub(super) async fn scan_image(
+    image: &Image,
+    config: &TrivyConfig,
+    server: Option<&str>,
+    flags: ScanFlags,
+    platform: Option<&str>,
+    username: Option<&str>,
+    password: Option<&str>,
+    progress: &ScanProgress,
+) -> Result<TrivyResult, eyre::Error> {
+    let _slot = config
+        .queue
+        .acquire()
+        .instrument(info_span!("wait for scan slot"))
+        .await;
+
+    let lease = config
+        .acquire_server(server)
+        .instrument(info_span!("wait for trivy server"))
+        .await?;
+
+    progress.report(ScanPhase::RunningTrivy);
+
+    let mut command = command(
+        image,
+        config,
+        lease.as_ref().map(pool::ServerLease::address),
+        flags,
+        platform,
+        username,
+        password,
+    );
+
+    let started = std::time::Instant::now();
+
+    let output = command::output(&mut command, config.timeout)
         .instrument(info_span!("run trivy command"))
         .await
-        .context("Failed to run trivy")?;
+        .context("Failed to run trivy");
+
+    metrics::record_scan_duration(started.elapsed());
+
+    if let Some(lease) = &lease {
+        lease.report(output.as_ref().is_ok_and(|output| {
+            output.status.success()
+                || server_problem(&String::from_utf8_lossy(&output.stderr)).is_none()
+        }));
+    }
+
+    let output = output?;
 
     if !output.status.success() {
-        let stderr =
-            String::from_utf8(output.stderr).context("Failed to convert trivy stderr to utf8")?;
+        // stderr is only shown to humans, replace invalid utf8 instead of
+        // hiding trivy's message
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
 
-        return Err(eyre::Report::msg(stderr));
+        return Err(match &lease {
+            Some(lease) => server_error(lease.address(), stderr),
+            None => eyre::Report::msg(stderr),
+        });
     }
 
+    let failed_scanners = failed_scanners(&String::from_utf8_lossy(&output.stderr));
+
     let stdout =
         String::from_utf8(output.stdout).context("Failed to convert trivy stdout to utf8")?;
 
-    let output = serde_json::from_str::<TrivyResult>(&stdout)
+    let mut output = serde_json::from_str::<TrivyResult>(&stdout)
         .context("Failed to parse trivy output json")?;
 
+    check_artifact(image, &output)?;
+
+    output.failed_scanners = failed_scanners;
+
     Ok(output)
 }
 
+/// Returned when trivy reports results for another image than the one it was
+/// asked to scan, e.g. behind a misconfigured registry mirror. The results
+/// are discarded so they are not cached for the requested image.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct ArtifactMismatch {
+    requested: String,
+    reported: String,
+}
+
+impl std::fmt::Display for ArtifactMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "trivy reported results for {reported} instead of the requested image {requested}",
+            reported = self.reported,
+            requested = self.requested
+        )
+    }
+}
+
+impl std::error::Error for ArtifactMismatch {}
+
+/// Trivy names the artifact after the reference it was given, both are
+/// normalized before comparing them. Reports without an artifact name are
+/// accepted.
+fn check_artifact(image: &Image, output: &TrivyResult) -> Result<(), ArtifactMismatch> {
+    let requested = image.to_string();
+
+    let matches = |reported: &str| {
+        reported == requested
+            || reported
+                .parse::<Image>()
+                .is_ok_and(|reported| reported.to_string() == requested)
+    };
+
+    match &output.artifact_name {
+        Some(reported) if !matches(reported) => {
+            tracing::warn!(
+                requested,
+                reported,
+                "trivy scanned a different image than requested, discarding the results"
+            );
+
+            Err(ArtifactMismatch {
+                requested,
+                reported: reported.clone(),
+            })
+        }
+
+        _ => Ok(()),
+    }
+}
+
 #[cfg(test)]
 #[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
 mod test {
-    use super::TrivyResult;
+    use super::{
+        ArtifactMismatch,
+        Cvss,
+        CvssVersion,
+        IgnorePolicy,
+        ScanFlags,
+        ScanProgress,
+        Scanner,
+        ScannerStatus,
+        Score,
+        Severity,
+        SeverityMapping,
+        SeverityWeights,
+        TrivyConfig,
+        TrivyIgnore,
+        TrivyResult,
+        TrivyServerPool,
+        Vulnerability,
+        VulnerabilityStatus,
+    };
+    use crate::handler::response::TrivyInformation;
+
+    #[test]
+    fn cvss_score_prefers_v3() {
+        let both: Cvss = serde_json::from_str(r#"{"V2Score": 5.0, "V3Score": 7.5}"#).unwrap();
+        let v2_only: Cvss = serde_json::from_str(r#"{"V2Score": 5.0}"#).unwrap();
+
+        assert_eq!(
+            "7.5",
+            both.score(CvssVersion::default()).unwrap().to_string()
+        );
+        assert_eq!("5", both.score(CvssVersion::V2).unwrap().to_string());
+        assert_eq!(
+            "5",
+            v2_only.score(CvssVersion::default()).unwrap().to_string()
+        );
+        assert!(v2_only.v3_score().is_none());
+    }
+
+    #[test]
+    fn score_ordering() {
+        let score = |value: &str| serde_json::from_str::<Score>(value).unwrap();
+
+        let mut scores = [score("9.8"), score("10.0"), score("2.1"), score("7.5")];
+        scores.sort();
+
+        assert_eq!(
+            ["2.1", "7.5", "9.8", "10"],
+            scores
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+        assert!(score("10.0") > score("9.8"));
+        assert_eq!(score("5"), score("5.0"));
+    }
+
+    #[test]
+    fn vulnerability_highest_score() {
+        let vulnerability: Vulnerability = serde_json::from_str(
+            r#"{
+                "VulnerabilityID": "CVE-2024-0001",
+                "PkgName": "openssl",
+                "InstalledVersion": "3.0.0",
+                "Severity": "HIGH",
+                "CVSS": {
+                    "nvd": {"V2Score": 9.3, "V3Score": 7.5},
+                    "redhat": {"V3Score": 10.0}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("10"),
+            vulnerability
+                .highest_score()
+                .map(|score| score.to_string())
+                .as_deref()
+        );
+
+        let unscored = Vulnerability {
+            cvss: None,
+            ..vulnerability
+        };
+        assert!(unscored.highest_score().is_none());
+    }
+
+    fn args(config: &TrivyConfig, flags: ScanFlags) -> Vec<String> {
+        super::command(
+            &"ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            config,
+            None,
+            flags,
+            None,
+            None,
+            None,
+        )
+        .as_std()
+        .get_args()
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+    }
+
+    #[test]
+    fn risk_score() {
+        let trivy_result: TrivyResult =
+            serde_json::from_str(include_str!("resources/tests/trivy_output.json")).unwrap();
+        let information = TrivyInformation::new(trivy_result, &[]);
+
+        // 6 critical, 16 high, 13 medium and 32 low
+        assert_eq!(
+            6 * 10 + 16 * 5 + 13 * 2 + 32,
+            information
+                .severity_count()
+                .risk_score(SeverityWeights::default())
+        );
+
+        let weights = "critical=100, low=0".parse::<SeverityWeights>().unwrap();
+        assert_eq!(
+            6 * 100 + 16 * 5 + 13 * 2,
+            information.severity_count().risk_score(weights)
+        );
+
+        assert!("critical".parse::<SeverityWeights>().is_err());
+        assert!("negligible=1".parse::<SeverityWeights>().is_err());
+    }
+
+    #[test]
+    fn severity_mapping() {
+        let mapping = "medium=high, LOW=medium"
+            .parse::<SeverityMapping>()
+            .unwrap();
+
+        assert_eq!("MEDIUM=HIGH,LOW=MEDIUM", mapping.to_string());
+        assert_eq!(Some(Severity::High), mapping.get(Severity::Medium));
+        assert_eq!(None, mapping.get(Severity::Critical));
+        assert_eq!(
+            None,
+            "high=high"
+                .parse::<SeverityMapping>()
+                .unwrap()
+                .get(Severity::High)
+        );
+
+        assert!("medium".parse::<SeverityMapping>().is_err());
+        assert!("medium=negligible".parse::<SeverityMapping>().is_err());
+    }
 
     #[test]
     fn deserialize() {
@@ -218,14 +1454,382 @@ mod test {
             serde_json::from_str(include_str!("resources/tests/trivy_output3.json")).unwrap();
     }
 
+    #[test]
+    fn status() {
+        let out: TrivyResult =
+            serde_json::from_str(include_str!("resources/tests/trivy_output2.json")).unwrap();
+
+        let statuses = out
+            .results
+            .into_iter()
+            .filter_map(|result| result.vulnerabilities)
+            .flatten()
+            .map(|vulnerability| vulnerability.status)
+            .collect::<Vec<_>>();
+
+        assert!(statuses.contains(&Some(VulnerabilityStatus::WillNotFix)));
+        assert!(statuses.contains(&Some(VulnerabilityStatus::FixDeferred)));
+
+        let unknown: Option<VulnerabilityStatus> = serde_json::from_str("\"new_status\"").unwrap();
+        assert_eq!(Some(VulnerabilityStatus::Unknown), unknown);
+    }
+
+    #[test]
+    fn package_location() {
+        let out: TrivyResult =
+            serde_json::from_str(include_str!("resources/tests/trivy_output.json")).unwrap();
+
+        let vulnerability = out
+            .results
+            .into_iter()
+            .filter_map(|result| result.vulnerabilities)
+            .flatten()
+            .find(|vulnerability| vulnerability.id == "GHSA-h6ch-v84p-w6p9")
+            .unwrap();
+
+        assert_eq!(
+            Some("app/code-server/lib/vscode/extensions/diff/package.json"),
+            vulnerability.pkg_path.as_deref()
+        );
+
+        assert_eq!(
+            Some("sha256:76000b692abd282a5bf66a6ca416cc3dbf38d47ab4d222a77581a0d92da6dcbe"),
+            vulnerability
+                .layer
+                .as_ref()
+                .and_then(|layer| layer.digest.as_deref())
+        );
+    }
+
+    #[test]
+    fn server_connection_refused() {
+        let stderr = "FATAL\tFatal error\trun error: image scan error: scan error: \
+                      twirp error internal: failed to do request: Post \
+                      \"http://localhost:4954/twirp/trivy.scanner.v1.Scanner/Scan\": dial tcp \
+                      127.0.0.1:4954: connect: connection refused";
+
+        let err = super::server_error("localhost:4954", stderr.to_string());
+
+        assert_eq!(
+            "trivy server localhost:4954 is unreachable",
+            err.to_string()
+        );
+        assert!(err.downcast_ref::<super::ServerUnavailable>().is_some());
+        assert_eq!(
+            Some(stderr.to_string()),
+            err.chain().nth(1).map(ToString::to_string)
+        );
+    }
+
+    #[test]
+    fn failed_scanners() {
+        let stderr = "2024-06-03T10:00:00Z\tINFO\t[vuln] Vulnerability scanning is \
+                      enabled\n2024-06-03T10:00:00Z\tINFO\t[secret] Secret scanning is \
+                      enabled\n2024-06-03T10:00:00Z\tINFO\t[secret] If your scanning is slow, \
+                      please try '--scanners vuln' to disable secret \
+                      scanning\n2024-06-03T10:00:01Z\tERROR\t[misconfig] Failed to load \
+                      policies\n2024-06-03T10:00:01Z\tWARN\t[license] License scanning is disabled";
+
+        assert_eq!(
+            vec![Scanner::Misconfig, Scanner::License],
+            super::failed_scanners(stderr)
+        );
+        assert!(super::failed_scanners("").is_empty());
+    }
+
+    #[test]
+    fn scanner_status() {
+        const DATA: &str = include_str!("resources/tests/trivy_output.json");
+
+        let trivy_result = serde_json::from_str::<TrivyResult>(DATA).unwrap();
+
+        let status = super::scanner_status(&trivy_result.results, &[], &[Scanner::Secret]);
+
+        assert_eq!(
+            vec![
+                ScannerStatus {
+                    scanner: Scanner::Vuln,
+                    ran: true,
+                    findings: true,
+                },
+                ScannerStatus {
+                    scanner: Scanner::Secret,
+                    ran: false,
+                    findings: false,
+                },
+            ],
+            status
+        );
+
+        // clean images have no findings but the scanners still ran
+        let status = super::scanner_status(&[], &[Scanner::Vuln, Scanner::Secret], &[]);
+        assert!(status.iter().all(|status| status.ran && !status.findings));
+    }
+
+    #[test]
+    fn server_html_response() {
+        let err = super::server_error(
+            "localhost:8080",
+            "twirp error malformed: invalid character '<' looking for beginning of value"
+                .to_string(),
+        );
+
+        assert!(err.to_string().contains("misconfigured"));
+
+        let err = super::server_error("localhost:4954", "manifest unknown".to_string());
+        assert_eq!("manifest unknown", err.to_string());
+        assert!(err.downcast_ref::<super::ServerUnavailable>().is_none());
+    }
+
+    #[test]
+    fn parallel() {
+        let got = args(
+            &TrivyConfig {
+                parallel: Some(2),
+                ..TrivyConfig::default()
+            },
+            ScanFlags::default(),
+        );
+
+        let position = got.iter().position(|arg| arg == "--parallel").unwrap();
+        assert_eq!(Some("2"), got.get(position + 1).map(String::as_str));
+
+        let got = args(&TrivyConfig::default(), ScanFlags::default());
+        assert!(!got.iter().any(|arg| arg == "--parallel"));
+    }
+
+    #[tokio::test]
+    async fn server_override() {
+        let config = TrivyConfig {
+            servers: TrivyServerPool::new(vec!["default:4954".to_string()], None),
+            allowed_servers: TrivyServerPool::new(vec!["internal:4954".to_string()], None),
+            ..TrivyConfig::default()
+        };
+
+        let lease = config.acquire_server(None).await.unwrap().unwrap();
+        assert_eq!("default:4954", lease.address());
+
+        let lease = config
+            .acquire_server(Some("internal:4954"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!("internal:4954", lease.address());
+
+        assert!(config.is_allowed_server("default:4954"));
+        assert!(!config.is_allowed_server("169.254.169.254:80"));
+
+        let err = config
+            .acquire_server(Some("169.254.169.254:80"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            "trivy server 169.254.169.254:80 is not allowed",
+            err.to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn ignore_policy() {
+        let directory =
+            std::env::temp_dir().join(format!("trivy-web-ignore-policy-{}", std::process::id()));
+        tokio::fs::create_dir_all(&directory).await.unwrap();
+
+        let path = directory.join("policy.rego");
+        tokio::fs::write(&path, "package trivy\n\ndefault ignore = false\n")
+            .await
+            .unwrap();
+
+        let policy = IgnorePolicy::load(path.clone()).await.unwrap();
+
+        assert_eq!(64, policy.hash.len());
+
+        let got = args(
+            &TrivyConfig {
+                ignore_policy: Some(policy),
+                ..TrivyConfig::default()
+            },
+            ScanFlags::default(),
+        );
+
+        let position = got.iter().position(|arg| arg == "--ignore-policy").unwrap();
+        assert_eq!(
+            Some(path.to_string_lossy().as_ref()),
+            got.get(position + 1).map(String::as_str)
+        );
+
+        assert!(
+            IgnorePolicy::load(directory.join("missing.rego"))
+                .await
+                .is_err()
+        );
+
+        tokio::fs::remove_dir_all(&directory).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn trivyignore() {
+        let trivyignore = TrivyIgnore::load("/etc/trivy-web/.trivyignore".into()).await;
+        assert_eq!(None, trivyignore.hash);
+
+        let got = args(
+            &TrivyConfig {
+                trivyignore: Some(trivyignore),
+                ..TrivyConfig::default()
+            },
+            ScanFlags::default(),
+        );
+
+        let position = got.iter().position(|arg| arg == "--ignorefile").unwrap();
+        assert_eq!(
+            Some("/etc/trivy-web/.trivyignore"),
+            got.get(position + 1).map(String::as_str)
+        );
+
+        let got = args(&TrivyConfig::default(), ScanFlags::default());
+        assert!(!got.iter().any(|arg| arg == "--ignorefile"));
+    }
+
+    #[test]
+    fn pull_secret() {
+        let config = TrivyConfig {
+            pull_secret: Some("/var/run/secrets/registry/config.json".into()),
+            ..TrivyConfig::default()
+        };
+
+        let command = super::command(
+            &"ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            &config,
+            None,
+            ScanFlags::default(),
+            None,
+            None,
+            None,
+        );
+
+        let docker_config = command
+            .as_std()
+            .get_envs()
+            .find(|(key, _)| *key == "DOCKER_CONFIG")
+            .and_then(|(_, value)| value);
+
+        assert_eq!(
+            Some(std::ffi::OsStr::new("/var/run/secrets/registry")),
+            docker_config
+        );
+
+        let command = super::command(
+            &"ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            &TrivyConfig::default(),
+            None,
+            ScanFlags::default(),
+            None,
+            None,
+            None,
+        );
+
+        assert!(
+            !command
+                .as_std()
+                .get_envs()
+                .any(|(key, _)| key == "DOCKER_CONFIG")
+        );
+    }
+
+    #[test]
+    fn platform() {
+        let command = super::command(
+            &"ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            &TrivyConfig::default(),
+            None,
+            ScanFlags::default(),
+            Some("linux/arm64"),
+            None,
+            None,
+        );
+
+        let args = command
+            .as_std()
+            .get_args()
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            [
+                "--platform",
+                "linux/arm64",
+                "ghcr.io/aquasecurity/trivy:0.52.0"
+            ],
+            args[args.len() - 3..]
+        );
+    }
+
+    #[test]
+    fn include_non_failures() {
+        let flags = ScanFlags {
+            include_non_failures: true,
+            ..ScanFlags::default()
+        };
+
+        let got = args(&TrivyConfig::default(), flags);
+        assert!(got.iter().any(|arg| arg == "--include-non-failures"));
+
+        let got = args(&TrivyConfig::default(), ScanFlags::default());
+        assert!(!got.iter().any(|arg| arg == "--include-non-failures"));
+    }
+
+    #[test]
+    fn ignore_unfixed() {
+        let flags = ScanFlags {
+            ignore_unfixed: true,
+            ..ScanFlags::default()
+        };
+
+        let got = args(&TrivyConfig::default(), flags);
+        assert_eq!(
+            1,
+            got.iter().filter(|arg| *arg == "--ignore-unfixed").count()
+        );
+
+        let got = args(&TrivyConfig::default(), ScanFlags::default());
+        assert!(!got.iter().any(|arg| arg == "--ignore-unfixed"));
+    }
+
+    #[test]
+    fn check_artifact() {
+        let image = "linuxserver/code-server:latest".parse().unwrap();
+        let mut trivy_result: TrivyResult =
+            serde_json::from_str(include_str!("resources/tests/trivy_output.json")).unwrap();
+
+        assert_eq!(Ok(()), super::check_artifact(&image, &trivy_result));
+
+        let other = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+        let err = super::check_artifact(&other, &trivy_result).unwrap_err();
+
+        assert_eq!(
+            ArtifactMismatch {
+                requested: other.to_string(),
+                reported: "linuxserver/code-server:latest".to_string(),
+            },
+            err
+        );
+
+        trivy_result.artifact_name = None;
+        assert_eq!(Ok(()), super::check_artifact(&other, &trivy_result));
+    }
+
     #[tokio::test]
     #[should_panic(expected = "should fail")]
     async fn missing() {
         let _got = super::scan_image(
             &"ghcr.io/aquasecurity/trivy:0.0.0".parse().unwrap(),
+            &TrivyConfig::default(),
+            None,
+            ScanFlags::default(),
             None,
             None,
             None,
+            &ScanProgress::default(),
         )
         .await
         .expect("should fail");
@@ -239,9 +1843,13 @@ mod test {
     async fn exists() {
         let _got = super::scan_image(
             &"ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap(),
+            &TrivyConfig::default(),
+            None,
+            ScanFlags::default(),
             None,
             None,
             None,
+            &ScanProgress::default(),
         )
         .await
         .unwrap();
@@ -0,0 +1,196 @@
+use docker_registry_client::Image;
+use eyre::WrapErr;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tokio::process::Command;
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+use super::{
+    Severity,
+    TrivyConfig,
+    image_command,
+    set_image,
+};
+use crate::handler::command;
+
+/// Returned when a request asks for a compliance spec that is not configured.
+#[derive(Debug)]
+pub(crate) struct ComplianceNotAllowed(pub(crate) String);
+
+impl std::fmt::Display for ComplianceNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trivy compliance spec {} is not allowed", self.0)
+    }
+}
+
+impl std::error::Error for ComplianceNotAllowed {}
+
+/// Summary of a `trivy image --compliance` run as reported with
+/// `--report summary`.
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ComplianceReport {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+
+    pub(crate) title: String,
+
+    #[serde(default)]
+    pub(crate) summary_controls: Vec<ComplianceControl>,
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ComplianceControl {
+    #[serde(rename = "ID")]
+    pub(crate) id: String,
+
+    pub(crate) name: String,
+    pub(crate) severity: Severity,
+
+    /// Failed checks of the control, trivy omits it for controls that can
+    /// only be checked manually.
+    #[serde(default)]
+    pub(crate) total_fail: Option<usize>,
+}
+
+impl ComplianceControl {
+    pub(crate) fn passed(&self) -> bool {
+        self.total_fail == Some(0)
+    }
+
+    pub(crate) fn manual(&self) -> bool {
+        self.total_fail.is_none()
+    }
+}
+
+impl ComplianceReport {
+    pub(crate) fn passed(&self) -> usize {
+        self.summary_controls
+            .iter()
+            .filter(|control| control.passed())
+            .count()
+    }
+
+    pub(crate) fn failed(&self) -> usize {
+        self.summary_controls
+            .iter()
+            .filter(|control| !control.passed() && !control.manual())
+            .count()
+    }
+
+    pub(crate) fn manual(&self) -> usize {
+        self.summary_controls
+            .iter()
+            .filter(|control| control.manual())
+            .count()
+    }
+}
+
+/// Builds `trivy image --compliance <spec>`, only configured specs are run
+/// as custom specs are read from arbitrary paths by trivy.
+fn command(
+    image: &Image,
+    config: &TrivyConfig,
+    spec: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Result<Command, ComplianceNotAllowed> {
+    if !config.compliance.iter().any(|allowed| allowed == spec) {
+        return Err(ComplianceNotAllowed(spec.to_string()));
+    }
+
+    let mut command = image_command(config, "json");
+
+    command
+        .arg("--compliance")
+        .arg(spec)
+        .arg("--report")
+        .arg("summary");
+
+    set_image(&mut command, image, config, username, password);
+
+    Ok(command)
+}
+
+#[tracing::instrument(skip(password))]
+pub(crate) async fn scan_compliance(
+    image: &Image,
+    config: &TrivyConfig,
+    spec: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> eyre::Result<ComplianceReport> {
+    let output = command::output(
+        &mut command(image, config, spec, username, password)?,
+        config.timeout,
+    )
+    .instrument(info_span!("run trivy compliance"))
+    .await
+    .context("Failed to run trivy")?;
+
+    if !output.status.success() {
+        return Err(eyre::Report::msg(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout).context("Failed to parse trivy compliance json")
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use super::{
+        ComplianceReport,
+        TrivyConfig,
+    };
+
+    #[test]
+    fn command() {
+        let config = TrivyConfig {
+            compliance: vec!["docker-cis-1.6.0".to_string()],
+            ..TrivyConfig::default()
+        };
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let command = super::command(&image, &config, "docker-cis-1.6.0", None, None).unwrap();
+
+        assert_eq!(
+            vec![
+                "image",
+                "--format",
+                "json",
+                "--compliance",
+                "docker-cis-1.6.0",
+                "--report",
+                "summary",
+                "ghcr.io/aquasecurity/trivy:0.52.0"
+            ],
+            command.as_std().get_args().collect::<Vec<_>>()
+        );
+
+        let err = super::command(&image, &config, "@/etc/spec.yaml", None, None).unwrap_err();
+        assert_eq!(
+            "trivy compliance spec @/etc/spec.yaml is not allowed",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let report: ComplianceReport =
+            serde_json::from_str(include_str!("../resources/tests/trivy_compliance.json")).unwrap();
+
+        assert_eq!("docker-cis-1.6.0", report.id);
+        assert_eq!(7, report.summary_controls.len());
+        assert_eq!(3, report.passed());
+        assert_eq!(2, report.failed());
+        assert_eq!(2, report.manual());
+    }
+}
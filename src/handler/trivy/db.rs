@@ -0,0 +1,249 @@
+use chrono::{
+    DateTime,
+    Duration,
+    Utc,
+};
+use eyre::WrapErr;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+use super::TrivyConfig;
+use crate::handler::command;
+
+/// Output of `trivy version --format json`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Version {
+    version: String,
+
+    /// Missing when trivy has not downloaded the vulnerability DB yet.
+    #[serde(default, rename = "VulnerabilityDB")]
+    vulnerability_db: Option<VulnerabilityDb>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct VulnerabilityDb {
+    updated_at: DateTime<Utc>,
+}
+
+/// Freshness of the local trivy vulnerability DB as reported by the health
+/// endpoint.
+#[derive(Debug, Serialize, PartialEq)]
+pub(crate) struct DbStatus {
+    pub(crate) trivy_version: String,
+    pub(crate) updated_at: Option<DateTime<Utc>>,
+    pub(crate) age_seconds: Option<i64>,
+
+    /// The DB is missing or older than the configured maximum age.
+    pub(crate) stale: bool,
+}
+
+impl DbStatus {
+    fn new(version: Version, max_age: Duration, now: DateTime<Utc>) -> Self {
+        let updated_at = version.vulnerability_db.map(|db| db.updated_at);
+        let age = updated_at.map(|updated_at| now.signed_duration_since(updated_at));
+
+        Self {
+            trivy_version: version.version,
+            updated_at,
+            age_seconds: age.map(|age| age.num_seconds()),
+            stale: age.is_none_or(|age| age > max_age),
+        }
+    }
+}
+
+/// Checks that the trivy executable can be run, used by the readiness
+/// probe.
+#[tracing::instrument]
+pub(crate) async fn check_binary(config: &TrivyConfig) -> eyre::Result<()> {
+    let mut command = config.command();
+
+    command.arg("--version");
+
+    let output = command::output(&mut command, config.timeout)
+        .instrument(info_span!("run trivy --version"))
+        .await
+        .context("Failed to run trivy")?;
+
+    if !output.status.success() {
+        return Err(eyre::Report::msg(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Downloads the vulnerability DB into the trivy cache dir without scanning
+/// anything, used to warm up the DB before serving.
+#[tracing::instrument]
+pub(crate) async fn update_db(config: &TrivyConfig) -> eyre::Result<()> {
+    let mut command = config.command();
+
+    command.arg("image").arg("--download-db-only");
+
+    let output = command::output(&mut command, config.timeout)
+        .instrument(info_span!("run trivy image --download-db-only"))
+        .await
+        .context("Failed to run trivy")?;
+
+    if !output.status.success() {
+        return Err(eyre::Report::msg(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument]
+pub(crate) async fn db_status(config: &TrivyConfig, max_age: Duration) -> eyre::Result<DbStatus> {
+    let mut command = config.command();
+
+    command.arg("version").arg("--format").arg("json");
+
+    let output = command::output(&mut command, config.timeout)
+        .instrument(info_span!("run trivy version"))
+        .await
+        .context("Failed to run trivy")?;
+
+    if !output.status.success() {
+        return Err(eyre::Report::msg(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    let version: Version =
+        serde_json::from_slice(&output.stdout).context("Failed to parse trivy version json")?;
+
+    Ok(DbStatus::new(version, max_age, Utc::now()))
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use chrono::{
+        Duration,
+        TimeZone,
+        Utc,
+    };
+
+    use super::{
+        DbStatus,
+        TrivyConfig,
+        Version,
+    };
+    use crate::handler::command::{
+        CommandTimeout,
+        test::{
+            fake_binary,
+            sleeping_binary,
+        },
+    };
+
+    const VERSION: &str = r#"{
+        "Version": "0.52.0",
+        "VulnerabilityDB": {
+            "Version": 2,
+            "NextUpdate": "2024-06-05T12:13:58.456Z",
+            "UpdatedAt": "2024-06-05T06:13:58Z",
+            "DownloadedAt": "2024-06-05T07:35:01.123Z"
+        }
+    }"#;
+
+    #[test]
+    fn fresh() {
+        let version: Version = serde_json::from_str(VERSION).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 6, 5, 18, 13, 58).unwrap();
+
+        let status = DbStatus::new(version, Duration::hours(24), now);
+
+        assert_eq!("0.52.0", status.trivy_version);
+        assert_eq!(Some(12 * 60 * 60), status.age_seconds);
+        assert!(!status.stale);
+    }
+
+    #[test]
+    fn stale() {
+        let version: Version = serde_json::from_str(VERSION).unwrap();
+        let now = Utc.with_ymd_and_hms(2024, 6, 7, 6, 13, 58).unwrap();
+
+        let status = DbStatus::new(version, Duration::hours(24), now);
+
+        assert_eq!(Some(2 * 24 * 60 * 60), status.age_seconds);
+        assert!(status.stale);
+    }
+
+    #[tokio::test]
+    async fn timeout() {
+        let config = TrivyConfig {
+            binary: Some(sleeping_binary("trivy")),
+            timeout: Some(std::time::Duration::from_millis(100)),
+            ..TrivyConfig::default()
+        };
+
+        let err = super::db_status(&config, Duration::hours(24))
+            .await
+            .unwrap_err();
+
+        assert!(err.downcast_ref::<CommandTimeout>().is_some());
+    }
+
+    #[tokio::test]
+    async fn update_db() {
+        let arguments = std::env::temp_dir().join(format!(
+            "trivy-web-update-db-arguments-{pid}",
+            pid = std::process::id()
+        ));
+
+        let config = TrivyConfig {
+            binary: Some(fake_binary(
+                "update-db",
+                &format!("echo \"$@\" > {path}", path = arguments.display()),
+            )),
+            ..TrivyConfig::default()
+        };
+
+        super::update_db(&config).await.unwrap();
+
+        assert_eq!(
+            "image --download-db-only\n",
+            std::fs::read_to_string(&arguments).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn update_db_failed() {
+        let config = TrivyConfig {
+            binary: Some(fake_binary(
+                "update-db-failed",
+                "echo 'failed to download vulnerability DB' >&2; exit 1",
+            )),
+            ..TrivyConfig::default()
+        };
+
+        let err = super::update_db(&config).await.unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("failed to download vulnerability DB")
+        );
+    }
+
+    #[test]
+    fn missing_db() {
+        let version: Version = serde_json::from_str(r#"{"Version": "0.52.0"}"#).unwrap();
+
+        let status = DbStatus::new(version, Duration::hours(24), Utc::now());
+
+        assert_eq!(None, status.updated_at);
+        assert!(status.stale);
+    }
+}
@@ -0,0 +1,108 @@
+use docker_registry_client::Image;
+use eyre::WrapErr;
+use tokio::process::Command;
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+use super::{
+    TrivyConfig,
+    set_credentials,
+};
+use crate::handler::command;
+
+/// Returned when a request asks for a trivy plugin that is not configured.
+#[derive(Debug)]
+pub(crate) struct PluginNotAllowed(pub(crate) String);
+
+impl std::fmt::Display for PluginNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "trivy plugin {} is not allowed", self.0)
+    }
+}
+
+impl std::error::Error for PluginNotAllowed {}
+
+/// Builds `trivy <plugin> <image>`, the plugin takes the place of trivy's
+/// `image` subcommand. Only configured plugins are run as the name is passed
+/// to trivy as its subcommand.
+fn command(
+    image: &Image,
+    config: &TrivyConfig,
+    plugin: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Result<Command, PluginNotAllowed> {
+    if !config.plugins.iter().any(|allowed| allowed == plugin) {
+        return Err(PluginNotAllowed(plugin.to_string()));
+    }
+
+    let mut command = config.command();
+
+    command.arg(plugin).arg(image.to_string());
+
+    set_credentials(&mut command, username, password);
+
+    Ok(command)
+}
+
+/// Runs an allowlisted trivy plugin against the image and returns its
+/// output unmodified, plugin outputs have no common format.
+#[tracing::instrument(skip(password))]
+pub(crate) async fn run_plugin(
+    image: &Image,
+    config: &TrivyConfig,
+    plugin: &str,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> eyre::Result<String> {
+    let output = command::output(
+        &mut command(image, config, plugin, username, password)?,
+        config.timeout,
+    )
+    .instrument(info_span!("run trivy plugin"))
+    .await
+    .context("Failed to run trivy plugin")?;
+
+    if !output.status.success() {
+        return Err(eyre::Report::msg(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    String::from_utf8(output.stdout).context("Failed to convert trivy plugin stdout to utf8")
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use super::TrivyConfig;
+
+    #[test]
+    fn command() {
+        let config = TrivyConfig {
+            plugins: vec!["kubectl".to_string(), "reporter".to_string()],
+            ..TrivyConfig::default()
+        };
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let command =
+            super::command(&image, &config, "reporter", Some("user"), Some("pass")).unwrap();
+        let command = command.as_std();
+
+        assert_eq!("trivy", command.get_program());
+        assert_eq!(
+            vec!["reporter", "ghcr.io/aquasecurity/trivy:0.52.0"],
+            command.get_args().collect::<Vec<_>>()
+        );
+        assert!(
+            command
+                .get_envs()
+                .any(|(key, value)| key == "TRIVY_PASSWORD" && value == Some("pass".as_ref()))
+        );
+
+        let err = super::command(&image, &config, "image", None, None).unwrap_err();
+        assert_eq!("trivy plugin image is not allowed", err.to_string());
+    }
+}
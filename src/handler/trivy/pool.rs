@@ -0,0 +1,226 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Arc,
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+    },
+};
+
+use futures_util::future;
+use tokio::sync::{
+    OwnedSemaphorePermit,
+    Semaphore,
+};
+
+/// Trivy servers used for client/server scanning, each with its own
+/// concurrency limit.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrivyServerPool {
+    servers: Vec<TrivyServer>,
+}
+
+#[derive(Debug, Clone)]
+struct TrivyServer {
+    address: String,
+    permits: Arc<Semaphore>,
+    healthy: Arc<AtomicBool>,
+}
+
+/// A slot on a trivy server that is held for the duration of a scan.
+#[derive(Debug)]
+pub(crate) struct ServerLease {
+    server: TrivyServer,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl TrivyServerPool {
+    pub(crate) fn new(addresses: Vec<String>, concurrency: Option<NonZeroUsize>) -> Self {
+        let concurrency = concurrency.map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get);
+
+        let servers = addresses
+            .into_iter()
+            .map(|address| TrivyServer {
+                address,
+                permits: Arc::new(Semaphore::new(concurrency)),
+                healthy: Arc::new(AtomicBool::new(true)),
+            })
+            .collect();
+
+        Self { servers }
+    }
+
+    pub(crate) fn addresses(&self) -> impl Iterator<Item = &str> {
+        self.servers.iter().map(|server| server.address.as_str())
+    }
+
+    /// Takes a slot on the least loaded healthy server or, when all of them
+    /// are at their limit, waits for whichever frees a slot first. Returns
+    /// `None` when no servers are configured and trivy should scan locally.
+    pub(crate) async fn acquire(&self) -> Option<ServerLease> {
+        let healthy = self
+            .servers
+            .iter()
+            .filter(|server| server.healthy.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+
+        let candidates = if healthy.is_empty() {
+            self.servers.iter().collect()
+        } else {
+            healthy
+        };
+
+        let server = candidates
+            .iter()
+            .max_by_key(|server| server.permits.available_permits())?;
+
+        if let Ok(permit) = server.permits.clone().try_acquire_owned() {
+            return Some(ServerLease {
+                server: (*server).clone(),
+                _permit: permit,
+            });
+        }
+
+        let (permit, index, _) = future::select_all(
+            candidates
+                .iter()
+                .map(|server| Box::pin(server.permits.clone().acquire_owned())),
+        )
+        .await;
+
+        Some(ServerLease {
+            server: candidates[index].clone(),
+            _permit: permit.expect("semaphore is never closed"),
+        })
+    }
+
+    /// Waits for a slot on the server with the given address. Returns `None`
+    /// when the server is not part of the pool.
+    pub(crate) async fn acquire_address(&self, address: &str) -> Option<ServerLease> {
+        let server = self
+            .servers
+            .iter()
+            .find(|server| server.address == address)?
+            .clone();
+
+        let permit = server
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+
+        Some(ServerLease {
+            server,
+            _permit: permit,
+        })
+    }
+}
+
+impl ServerLease {
+    pub(crate) fn address(&self) -> &str {
+        &self.server.address
+    }
+
+    /// Records whether the scan on this server worked so unhealthy servers
+    /// are avoided for following scans.
+    pub(crate) fn report(&self, healthy: bool) {
+        self.server.healthy.store(healthy, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        num::NonZeroUsize,
+        time::Duration,
+    };
+
+    use super::TrivyServerPool;
+
+    #[tokio::test]
+    async fn per_server_limits() {
+        let pool = TrivyServerPool::new(
+            vec!["first:4954".to_string(), "second:4954".to_string()],
+            Some(NonZeroUsize::MIN),
+        );
+
+        let first = pool.acquire().await.unwrap();
+        let second = pool.acquire().await.unwrap();
+
+        assert_ne!(first.address(), second.address());
+
+        let blocked = tokio::time::timeout(Duration::from_millis(50), pool.acquire()).await;
+        assert!(blocked.is_err(), "both servers should be at their limit");
+
+        let released = first.address().to_string();
+        drop(first);
+
+        let third = pool.acquire().await.unwrap();
+        assert_eq!(released, third.address());
+    }
+
+    #[tokio::test]
+    async fn first_released_server() {
+        let pool = TrivyServerPool::new(
+            vec!["first:4954".to_string(), "second:4954".to_string()],
+            Some(NonZeroUsize::MIN),
+        );
+
+        let mut leases = vec![pool.acquire().await.unwrap(), pool.acquire().await.unwrap()];
+        leases.sort_by(|a, b| a.address().cmp(b.address()));
+        let second = leases.pop().unwrap();
+        let first = leases.pop().unwrap();
+
+        let waiting = tokio::spawn({
+            let pool = pool.clone();
+            async move { pool.acquire().await.unwrap() }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(
+            !waiting.is_finished(),
+            "both servers should be at their limit"
+        );
+
+        // the second server stays busy, the waiting scan takes the first one
+        drop(first);
+
+        let lease = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .expect("waiting scan should get the released server")
+            .unwrap();
+
+        assert_eq!("first:4954", lease.address());
+
+        drop(second);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_server_avoided() {
+        let pool = TrivyServerPool::new(
+            vec!["first:4954".to_string(), "second:4954".to_string()],
+            None,
+        );
+
+        let lease = pool.acquire().await.unwrap();
+        let unhealthy = lease.address().to_string();
+        lease.report(false);
+        drop(lease);
+
+        for _ in 0..3 {
+            let lease = pool.acquire().await.unwrap();
+            assert_ne!(unhealthy, lease.address());
+        }
+    }
+
+    #[tokio::test]
+    async fn no_servers() {
+        let pool = TrivyServerPool::default();
+
+        assert!(pool.acquire().await.is_none());
+    }
+}
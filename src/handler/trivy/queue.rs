@@ -0,0 +1,136 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Arc,
+        atomic::{
+            AtomicUsize,
+            Ordering,
+        },
+    },
+};
+
+use tokio::sync::{
+    OwnedSemaphorePermit,
+    Semaphore,
+};
+
+use crate::handler::metrics;
+
+/// Limits how many trivy scans run at once, scans beyond the limit wait in
+/// a queue whose depth is exported as a metric.
+#[derive(Debug, Clone)]
+pub(crate) struct ScanQueue {
+    permits: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+}
+
+/// A scan slot that is held for the duration of a scan.
+#[derive(Debug)]
+pub(crate) struct ScanSlot {
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Removes a scan from the queue depth once it got a slot or gave up
+/// waiting.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        let depth = self.0.fetch_sub(1, Ordering::SeqCst) - 1;
+        metrics::record_scan_queue_depth(depth);
+    }
+}
+
+impl Default for ScanQueue {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ScanQueue {
+    /// Without a concurrency scans never wait.
+    pub(crate) fn new(concurrency: Option<NonZeroUsize>) -> Self {
+        Self {
+            permits: Arc::new(Semaphore::new(
+                concurrency.map_or(Semaphore::MAX_PERMITS, NonZeroUsize::get),
+            )),
+            waiting: Arc::default(),
+        }
+    }
+
+    pub(crate) async fn acquire(&self) -> ScanSlot {
+        let depth = self.waiting.fetch_add(1, Ordering::SeqCst) + 1;
+        metrics::record_scan_queue_depth(depth);
+
+        let _waiting = Waiting(&self.waiting);
+
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+
+        ScanSlot { _permit: permit }
+    }
+
+    /// Number of scans waiting for a slot.
+    pub(crate) fn depth(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    /// All slots are taken so new scans have to wait.
+    pub(crate) fn saturated(&self) -> bool {
+        self.permits.available_permits() == 0
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        num::NonZeroUsize,
+        time::Duration,
+    };
+
+    use super::ScanQueue;
+
+    #[tokio::test]
+    async fn depth() {
+        let queue = ScanQueue::new(Some(NonZeroUsize::MIN));
+
+        assert!(!queue.saturated());
+
+        let slot = queue.acquire().await;
+
+        assert!(queue.saturated());
+        assert_eq!(0, queue.depth());
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.acquire().await }
+        });
+
+        while queue.depth() == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+
+        assert_eq!(1, queue.depth());
+
+        drop(slot);
+        let slot = waiting.await.unwrap();
+
+        assert_eq!(0, queue.depth());
+        drop(slot);
+
+        // cancelled waiters leave the queue
+        let slot = queue.acquire().await;
+        let cancelled = tokio::time::timeout(Duration::from_millis(10), queue.acquire()).await;
+
+        assert!(cancelled.is_err());
+        assert_eq!(0, queue.depth());
+        drop(slot);
+
+        assert!(!ScanQueue::default().saturated());
+    }
+}
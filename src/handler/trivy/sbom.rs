@@ -0,0 +1,418 @@
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    num::NonZeroUsize,
+    pin::Pin,
+    process::Stdio,
+    sync::Arc,
+    task::{
+        Context,
+        Poll,
+    },
+};
+
+use docker_registry_client::Image;
+use eyre::WrapErr;
+use tokio::{
+    io::{
+        AsyncRead,
+        AsyncReadExt,
+        Chain,
+        ReadBuf,
+    },
+    process::{
+        Child,
+        ChildStdout,
+        Command,
+    },
+    sync::Semaphore,
+    task::JoinSet,
+};
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+use super::{
+    TrivyConfig,
+    image_command,
+    set_image,
+};
+use crate::handler::command::{
+    self,
+    CommandTimeout,
+};
+
+/// Builds `trivy image --format cyclonedx`. Without a platform trivy picks
+/// the platform of the host from a multi-arch image.
+fn command(
+    image: &Image,
+    config: &TrivyConfig,
+    platform: Option<&str>,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> Command {
+    let mut command = image_command(config, "cyclonedx");
+
+    if let Some(platform) = platform {
+        command.arg("--platform").arg(platform);
+    }
+
+    set_image(&mut command, image, config, username, password);
+
+    command
+}
+
+/// Size of the first read of the trivy output.
+const FIRST_CHUNK: usize = 8 * 1024;
+
+/// SBOMs larger than this are only streamed and never handed to the
+/// completion callback.
+const MAX_COMPLETE_SIZE: usize = 32 * 1024 * 1024;
+
+type CompleteCallback = Box<dyn FnOnce(Vec<u8>) + Send>;
+
+/// Output of a running `trivy image --format cyclonedx` that is read while
+/// trivy still writes it so large SBOMs are never buffered as a whole. Trivy
+/// is killed when the reader is dropped, e.g. when the client disconnects.
+pub(crate) struct SbomReader {
+    reader: Chain<Cursor<Vec<u8>>, ChildStdout>,
+    _child: Child,
+
+    /// Copy of the SBOM read so far that is handed to the callback once the
+    /// whole SBOM was read.
+    complete: Option<(Vec<u8>, CompleteCallback)>,
+}
+
+impl std::fmt::Debug for SbomReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SbomReader")
+            .field("reader", &self.reader)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SbomReader {
+    /// Calls the callback with the whole SBOM once it was read to the end,
+    /// e.g. to cache it. Readers dropped early and SBOMs larger than
+    /// `MAX_COMPLETE_SIZE` never call it.
+    pub(crate) fn on_complete(mut self, callback: impl FnOnce(Vec<u8>) + Send + 'static) -> Self {
+        self.complete = Some((Vec::new(), Box::new(callback)));
+
+        self
+    }
+}
+
+impl AsyncRead for SbomReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let eof_possible = buf.remaining() > 0;
+
+        let poll = Pin::new(&mut self.reader).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = poll {
+            let read = &buf.filled()[filled..];
+
+            if read.is_empty() && eof_possible {
+                if let Some((sbom, callback)) = self.complete.take() {
+                    callback(sbom);
+                }
+            } else if let Some((sbom, _)) = &mut self.complete {
+                if sbom.len() + read.len() > MAX_COMPLETE_SIZE {
+                    self.complete = None;
+                } else {
+                    sbom.extend_from_slice(read);
+                }
+            }
+        }
+
+        poll
+    }
+}
+
+/// Starts generating a `CycloneDX` SBOM of the image. Trivy only writes the
+/// SBOM once the scan is done so waiting for the first chunk still reports
+/// failed scans as errors, the timeout only applies until then.
+#[tracing::instrument(skip(password))]
+pub(crate) async fn stream_sbom(
+    image: &Image,
+    config: &TrivyConfig,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> eyre::Result<SbomReader> {
+    let mut command = command(image, config, None, username, password);
+
+    command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn().context("Failed to run trivy")?;
+
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| eyre::eyre!("trivy stdout is not piped"))?;
+    let mut stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| eyre::eyre!("trivy stderr is not piped"))?;
+
+    // trivy logs to stderr while it scans, it blocks once the pipe is full
+    let stderr = tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        let _ = stderr.read_to_end(&mut buffer).await;
+
+        String::from_utf8_lossy(&buffer).into_owned()
+    });
+
+    let mut first = vec![0; FIRST_CHUNK];
+
+    let read = match config.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, stdout.read(&mut first))
+            .instrument(info_span!("wait for trivy sbom"))
+            .await
+            .map_err(|_| CommandTimeout::new(&command, timeout))?,
+
+        None => {
+            stdout
+                .read(&mut first)
+                .instrument(info_span!("wait for trivy sbom"))
+                .await
+        }
+    }
+    .context("Failed to read trivy sbom")?;
+
+    if read == 0 {
+        let status = child.wait().await.context("Failed to run trivy")?;
+        let stderr = stderr.await.context("trivy stderr task failed")?;
+
+        if status.success() {
+            return Err(eyre::eyre!("trivy returned an empty sbom"));
+        }
+
+        return Err(eyre::Report::msg(stderr));
+    }
+
+    first.truncate(read);
+
+    Ok(SbomReader {
+        reader: Cursor::new(first).chain(stdout),
+        _child: child,
+        complete: None,
+    })
+}
+
+/// Generates a `CycloneDX` SBOM of the image, the SBOM is passed through
+/// unmodified.
+#[tracing::instrument(skip(password))]
+pub(crate) async fn generate_sbom(
+    image: &Image,
+    config: &TrivyConfig,
+    platform: Option<&str>,
+    username: Option<&str>,
+    password: Option<&str>,
+) -> eyre::Result<serde_json::Value> {
+    let output = command::output(
+        &mut command(image, config, platform, username, password),
+        config.timeout,
+    )
+    .instrument(info_span!("run trivy sbom"))
+    .await
+    .context("Failed to run trivy")?;
+
+    if !output.status.success() {
+        return Err(eyre::Report::msg(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        ));
+    }
+
+    serde_json::from_slice(&output.stdout).context("Failed to parse trivy sbom json")
+}
+
+/// Generates one SBOM per platform of a multi-arch image keyed by the
+/// platform. At most `concurrency` trivy processes run at once as every run
+/// pulls the image of its platform.
+#[tracing::instrument(skip(password))]
+pub(crate) async fn generate_platform_sboms(
+    image: &Image,
+    config: &TrivyConfig,
+    platforms: Vec<String>,
+    concurrency: NonZeroUsize,
+    username: Option<String>,
+    password: Option<String>,
+) -> eyre::Result<BTreeMap<String, serde_json::Value>> {
+    let permits = Arc::new(Semaphore::new(concurrency.get()));
+    let mut sboms = JoinSet::new();
+
+    for platform in platforms {
+        let permits = permits.clone();
+        let image = image.clone();
+        let config = config.clone();
+        let username = username.clone();
+        let password = password.clone();
+
+        sboms.spawn(async move {
+            let _permit = permits
+                .acquire_owned()
+                .await
+                .context("sbom semaphore closed")?;
+
+            let sbom = generate_sbom(
+                &image,
+                &config,
+                Some(&platform),
+                username.as_deref(),
+                password.as_deref(),
+            )
+            .await
+            .with_context(|| format!("failed to generate sbom for platform {platform}"))?;
+
+            Ok::<_, eyre::Report>((platform, sbom))
+        });
+    }
+
+    let mut generated = BTreeMap::new();
+
+    while let Some(result) = sboms.join_next().await {
+        let (platform, sbom) = result.context("sbom task failed")??;
+
+        generated.insert(platform, sbom);
+    }
+
+    Ok(generated)
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use docker_registry_client::Manifest as DockerManifest;
+    use tokio::io::AsyncReadExt;
+
+    use super::TrivyConfig;
+    use crate::handler::{
+        command::test::fake_binary,
+        response::platforms,
+    };
+
+    #[tokio::test]
+    async fn stream_large_sbom() {
+        const PADDING: usize = 1024 * 1024;
+
+        // trivy hangs after writing the sbom so it can only be read when it is
+        // streamed
+        let config = TrivyConfig {
+            binary: Some(fake_binary(
+                "trivy-sbom",
+                &format!(
+                    "printf '{{\"bomFormat\":\"CycloneDX\",\"components\":['\nhead -c {PADDING} \
+                     /dev/zero | tr '\\0' ' '\nprintf ']}}'\nexec sleep 30"
+                ),
+            )),
+            timeout: Some(std::time::Duration::from_secs(5)),
+            ..TrivyConfig::default()
+        };
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let sbom = tokio::time::timeout(std::time::Duration::from_secs(10), async {
+            let mut reader = super::stream_sbom(&image, &config, None, None)
+                .await
+                .unwrap();
+
+            let prefix = r#"{"bomFormat":"CycloneDX","components":["#;
+            let mut sbom = vec![0; prefix.len() + PADDING + 2];
+            reader.read_exact(&mut sbom).await.unwrap();
+
+            sbom
+        })
+        .await
+        .unwrap();
+
+        assert!(sbom.starts_with(br#"{"bomFormat":"CycloneDX""#));
+        assert!(sbom.ends_with(b"]}"));
+    }
+
+    #[tokio::test]
+    async fn stream_complete_sbom() {
+        const SBOM: &str = r#"{"bomFormat":"CycloneDX","components":[]}"#;
+
+        let config = TrivyConfig {
+            binary: Some(fake_binary(
+                "trivy-sbom-complete",
+                &format!("printf '{SBOM}'"),
+            )),
+            ..TrivyConfig::default()
+        };
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+
+        let mut reader = super::stream_sbom(&image, &config, None, None)
+            .await
+            .unwrap()
+            .on_complete(move |sbom| {
+                let _ = sender.send(sbom);
+            });
+
+        let mut streamed = Vec::new();
+        reader.read_to_end(&mut streamed).await.unwrap();
+
+        assert_eq!(SBOM.as_bytes(), streamed);
+        assert_eq!(SBOM.as_bytes(), receiver.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn stream_failed_sbom() {
+        let config = TrivyConfig {
+            binary: Some(fake_binary(
+                "trivy-sbom-failed",
+                "echo 'unable to find the image' >&2\nexit 1",
+            )),
+            ..TrivyConfig::default()
+        };
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let err = super::stream_sbom(&image, &config, None, None)
+            .await
+            .unwrap_err();
+
+        assert_eq!("unable to find the image\n", err.to_string());
+    }
+
+    #[test]
+    fn platform_commands() {
+        let manifest: DockerManifest = serde_json::from_str(include_str!(
+            "../resources/tests/trivy-manifest-response.json"
+        ))
+        .unwrap();
+        let image = "ghcr.io/aquasecurity/trivy:0.52.0".parse().unwrap();
+
+        let platforms = platforms(&manifest);
+
+        assert_eq!(
+            vec!["linux/amd64", "linux/arm64", "linux/ppc64le", "linux/s390x"],
+            platforms
+        );
+
+        for platform in &platforms {
+            let command =
+                super::command(&image, &TrivyConfig::default(), Some(platform), None, None);
+
+            assert_eq!(
+                vec![
+                    "image",
+                    "--format",
+                    "cyclonedx",
+                    "--platform",
+                    platform.as_str(),
+                    "ghcr.io/aquasecurity/trivy:0.52.0"
+                ],
+                command.as_std().get_args().collect::<Vec<_>>()
+            );
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    collections::BTreeSet,
+    path::PathBuf,
+    sync::Arc,
+};
+
+use eyre::{
+    Context,
+    Result,
+};
+use tokio::sync::RwLock;
+
+/// Digests of images that are scanned elsewhere, e.g. centrally maintained
+/// base images, which are not scanned again.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TrustedDigests {
+    path: Option<PathBuf>,
+    digests: Arc<RwLock<BTreeSet<String>>>,
+}
+
+/// One digest per line, empty lines and lines starting with `#` are
+/// skipped.
+fn parse(content: &str) -> BTreeSet<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToString::to_string)
+        .collect()
+}
+
+impl TrustedDigests {
+    /// Reads the allowlist from the file, without a file no image is
+    /// trusted.
+    pub(crate) async fn load(path: Option<PathBuf>) -> Result<Self> {
+        let trusted = Self {
+            path,
+            digests: Arc::default(),
+        };
+
+        trusted.reload().await?;
+
+        Ok(trusted)
+    }
+
+    /// Reads the file again, the previous allowlist is kept when the file
+    /// can not be read. Returns the number of trusted digests.
+    pub(crate) async fn reload(&self) -> Result<usize> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read trusted digests from {}", path.display()))?;
+
+        let digests = parse(&content);
+        let count = digests.len();
+
+        *self.digests.write().await = digests;
+
+        Ok(count)
+    }
+
+    pub(crate) async fn is_empty(&self) -> bool {
+        self.digests.read().await.is_empty()
+    }
+
+    pub(crate) async fn contains(&self, digest: &str) -> bool {
+        self.digests.read().await.contains(digest)
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use super::TrustedDigests;
+
+    #[tokio::test]
+    async fn reload() {
+        let path = std::env::temp_dir().join(format!(
+            "trivy-web-trusted-digests-{pid}",
+            pid = std::process::id()
+        ));
+
+        std::fs::write(&path, "# base images\nsha256:aaa\n\n  sha256:bbb  \n").unwrap();
+
+        let trusted = TrustedDigests::load(Some(path.clone())).await.unwrap();
+
+        assert!(trusted.contains("sha256:aaa").await);
+        assert!(trusted.contains("sha256:bbb").await);
+        assert!(!trusted.contains("# base images").await);
+
+        std::fs::write(&path, "sha256:ccc\n").unwrap();
+        assert_eq!(1, trusted.reload().await.unwrap());
+
+        assert!(!trusted.contains("sha256:aaa").await);
+        assert!(trusted.contains("sha256:ccc").await);
+
+        // a broken reload keeps the previous allowlist
+        std::fs::remove_file(&path).unwrap();
+        assert!(trusted.reload().await.is_err());
+        assert!(trusted.contains("sha256:ccc").await);
+    }
+
+    #[tokio::test]
+    async fn without_file() {
+        let trusted = TrustedDigests::load(None).await.unwrap();
+
+        assert!(trusted.is_empty().await);
+        assert_eq!(0, trusted.reload().await.unwrap());
+    }
+}
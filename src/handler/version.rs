@@ -0,0 +1,148 @@
+use std::{
+    path::Path,
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::process::Command;
+use tracing::{
+    Instrument,
+    info_span,
+};
+
+use crate::handler::{
+    command,
+    trivy::TrivyConfig,
+};
+
+/// Version lookups are skipped when the binary does not answer within this
+/// time.
+const VERSION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Build metadata of the running binary and the versions of the executables
+/// it runs.
+#[derive(Debug, Serialize)]
+pub(crate) struct VersionInfo {
+    crate_version: &'static str,
+    git_commit: &'static str,
+    build_time: &'static str,
+
+    /// Missing when trivy could not be run.
+    trivy_version: Option<String>,
+
+    /// Missing when cosign could not be run.
+    cosign_version: Option<String>,
+}
+
+impl VersionInfo {
+    pub(crate) async fn detect(trivy_config: &TrivyConfig, cosign_binary: &Path) -> Self {
+        let (trivy_version, cosign_version) = tokio::join!(
+            binary_version(trivy_config.command()),
+            binary_version(Command::new(cosign_binary)),
+        );
+
+        Self {
+            crate_version: env!("CRATE_VERSION"),
+            git_commit: env!("GIT_COMMIT").trim(),
+            build_time: env!("BUILD_TIME"),
+            trivy_version,
+            cosign_version,
+        }
+    }
+}
+
+/// Version reported by `<binary> --version`, failures are only logged.
+async fn binary_version(mut command: Command) -> Option<String> {
+    command.arg("--version");
+
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+
+    let output = command::output(&mut command, Some(VERSION_TIMEOUT))
+        .instrument(info_span!("get binary version"))
+        .await
+        .inspect_err(|err| tracing::warn!("failed to run {program} --version: {err:#}"))
+        .ok()?;
+
+    if !output.status.success() {
+        tracing::warn!(
+            "{program} --version failed: {stderr}",
+            stderr = String::from_utf8_lossy(&output.stderr)
+        );
+
+        return None;
+    }
+
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Value of the first `Version: ...` line, e.g. `Version: 0.52.0` of trivy
+/// or `GitVersion: v2.2.4` of cosign. Falls back to the first line for
+/// executables that only print their version.
+fn parse_version(output: &str) -> Option<String> {
+    let mut lines = output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty());
+
+    let version = output
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().ends_with("Version"))
+        .map(|(_, version)| version.trim())
+        .or_else(|| lines.next())?;
+
+    Some(version.to_string())
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::path::Path;
+
+    use crate::handler::{
+        command::test::fake_binary,
+        trivy::TrivyConfig,
+    };
+
+    #[test]
+    fn parse_version() {
+        assert_eq!(
+            Some("0.52.0".to_string()),
+            super::parse_version("Version: 0.52.0\nVulnerability DB:\n  Version: 2\n")
+        );
+        assert_eq!(
+            Some("v2.2.4".to_string()),
+            super::parse_version("cosign: A tool\n\nGitVersion:    v2.2.4\nGitCommit: fb651b4\n")
+        );
+        assert_eq!(
+            Some("tool 1.2.3".to_string()),
+            super::parse_version("\ntool 1.2.3\n")
+        );
+        assert_eq!(None, super::parse_version(""));
+    }
+
+    #[tokio::test]
+    async fn missing_binaries() {
+        let trivy_config = TrivyConfig {
+            binary: Some(fake_binary("trivy-version", "echo 'Version: 0.52.0'")),
+            ..TrivyConfig::default()
+        };
+
+        let version =
+            super::VersionInfo::detect(&trivy_config, Path::new("/nonexistent/cosign")).await;
+
+        assert_eq!(Some("0.52.0"), version.trivy_version.as_deref());
+        assert_eq!(None, version.cosign_version);
+        assert_eq!(env!("CRATE_VERSION"), version.crate_version);
+        assert!(!version.git_commit.ends_with('\n'));
+
+        let version = serde_json::to_value(&version).unwrap();
+
+        assert!(version["build_time"].is_string());
+        assert!(version["cosign_version"].is_null());
+    }
+}
@@ -1,4 +1,5 @@
-use clap::Parser;
+use std::path::PathBuf;
+
 use docker_registry_client::Client as DockerRegistryClient;
 use eyre::{
     Context,
@@ -12,31 +13,31 @@ use tracing::{
 mod args;
 mod filters;
 mod handler;
+mod profile;
+mod server;
 mod signal;
+mod startup;
+mod tls;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let opt = args::Args::parse();
+    let opt = profile::parse_args(std::env::args_os())?;
+    let effective_config = handler::EffectiveConfig::new(&opt);
 
-    tracing_subscriber::fmt()
-        .with_max_level(opt.log_level)
-        .init();
+    init_tracing(opt.log_level, opt.command.is_some());
 
-    if let Some(server) = &opt.server {
-        event!(Level::INFO, server = server, "Using trivy server");
-    }
+    let trivy_config = trivy_config(&opt).await?;
 
-    let redis_client = opt
-        .redis_server
-        .map(|server| -> Result<redis::Client> {
-            event!(Level::INFO, server = server, "Using redis server");
+    if let Some(args::Command::Scan(scan)) = &opt.command {
+        return handler::scan::run(trivy_config, scan).await;
+    }
 
-            let client =
-                redis::Client::open(server).context("failed to connect to redis server")?;
+    let tls_config = tls_config(&opt)?;
+    let redis_client = redis_client(opt.redis_server)?;
 
-            Ok(client)
-        })
-        .transpose()?;
+    if opt.event_stream.is_some() && redis_client.is_none() {
+        return Err(eyre::eyre!("--event-stream requires --redis-server"));
+    }
 
     let mut registry = DockerRegistryClient::default();
 
@@ -44,10 +45,68 @@ async fn main() -> Result<()> {
         registry.set_cache_redis(redis_client.clone());
     }
 
+    if opt.startup_registry_check {
+        startup::registry_check(&registry, &opt.startup_registry_canary, opt.fail_fast).await?;
+    }
+
+    if opt.warmup_db {
+        startup::warmup_db(&trivy_config).await;
+    }
+
     let state = handler::AppState {
-        server: opt.server,
+        trivy_config,
+        credential_helpers: handler::CredentialHelpers::new(
+            opt.credential_helper.into_iter().collect(),
+            chrono::Duration::seconds(opt.credential_helper_ttl),
+        ),
         docker_registry_client: registry,
-        redis_client,
+        registry_limits: handler::RegistryLimits::new(
+            opt.registry_timeout.map(std::time::Duration::from_secs),
+            opt.registry_concurrency,
+        ),
+        cosign_fetches: handler::CosignFetches::new(opt.cosign_concurrency),
+        fan_out_limit: handler::FanOutLimit::new(opt.fan_out_limit),
+        allowed_registries: handler::AllowedRegistries(opt.allowed_registry),
+        trusted_digests: load_trusted_digests(opt.trusted_digests.clone()).await?,
+        audit_log: handler::AuditLog::open(opt.audit_log.as_deref()).await?,
+        trusted_proxies: handler::TrustedProxies(opt.trusted_proxies),
+        disable_docker_info: opt.disable_docker_info,
+        cosign_private_infrastructure: opt.cosign_private_infrastructure,
+        cosign_binary: opt.cosign_binary,
+        canonical_ids: opt.canonical_ids,
+        severity_weights: opt.severity_weights,
+        cvss_version: opt.cvss_version,
+        severity_fallback: opt.severity_fallback,
+        trivy_db_max_age: chrono::Duration::seconds(opt.trivy_db_max_age),
+        sbom_concurrency: opt.sbom_concurrency,
+        api_json_case: opt.api_json_case,
+        enabled_formats: opt.enabled_formats,
+        max_references: opt.max_references,
+        max_report_size: opt.max_report_size,
+        max_connections: opt.max_connections,
+        cache: handler::Cache {
+            write_batcher: write_batcher(redis_client.as_ref(), opt.cache_batch),
+            redis_client,
+            ttl: chrono::Duration::seconds(opt.redis_ttl),
+            memory: handler::MemoryCache::new(chrono::Duration::seconds(opt.redis_ttl)),
+            fresh_for: opt.cache_fresh_for.map(chrono::Duration::seconds),
+            read_only: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(opt.read_only)),
+            trend_retention: opt.trend_retention.map(chrono::Duration::seconds),
+            event_stream: opt.event_stream,
+            refresh_cooldown: opt.refresh_cooldown.map(chrono::Duration::seconds),
+        },
+        admin_token: opt.admin_token,
+        basic_auth: opt
+            .auth_user
+            .as_deref()
+            .zip(opt.auth_password.as_deref())
+            .map(|(user, password)| handler::BasicAuth::new(user, password)),
+        result_signer: opt
+            .result_signing_key
+            .as_deref()
+            .map(handler::ResultSigner::new),
+        effective_config: std::sync::Arc::new(effective_config),
+        metrics: Some(handler::metrics::install()?),
 
         #[cfg(not(debug_assertions))]
         minify_config: minify_html::Cfg {
@@ -58,6 +117,7 @@ async fn main() -> Result<()> {
         },
     };
 
+    let cache = state.cache.clone();
     let router = handler::router(state);
 
     let listener = tokio::net::TcpListener::bind(opt.binding)
@@ -67,13 +127,169 @@ async fn main() -> Result<()> {
     event!(
         Level::INFO,
         binding = opt.binding.to_string(),
+        tls = tls_config.is_some(),
+        http2 = opt.http2,
         "Starting trivy-web"
     );
 
-    axum::serve(listener, router)
-        .with_graceful_shutdown(signal::shutdown_signal())
-        .await
-        .context("failed to start server")?;
+    serve(listener, router, tls_config, opt.http2).await;
+
+    cache.flush().await;
 
     Ok(())
 }
+
+fn init_tracing(log_level: Level, cli: bool) {
+    // keep stdout free for the summary when running as a cli
+    if cli {
+        tracing_subscriber::fmt()
+            .with_max_level(log_level)
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_max_level(log_level).init();
+    }
+}
+
+async fn serve(
+    listener: tokio::net::TcpListener,
+    router: axum::Router,
+    tls_config: Option<rustls::ServerConfig>,
+    http2: bool,
+) {
+    match tls_config {
+        Some(config) => {
+            server::serve(
+                tls::TlsListener::new(listener, config),
+                router,
+                http2,
+                signal::shutdown_signal(),
+            )
+            .await;
+        }
+
+        None => server::serve(listener, router, http2, signal::shutdown_signal()).await,
+    }
+}
+
+fn tls_config(opt: &args::Args) -> Result<Option<rustls::ServerConfig>> {
+    match (&opt.tls_certificate, &opt.tls_key) {
+        (Some(certificate), Some(key)) => Ok(Some(tls::server_config(
+            certificate,
+            key,
+            opt.tls_min_version,
+            &opt.tls_cipher_suites,
+            opt.http2,
+        )?)),
+
+        _ => Ok(None),
+    }
+}
+
+/// Batches cache writes into redis pipelines when a batch window is
+/// configured.
+fn write_batcher(
+    redis_client: Option<&redis::Client>,
+    window: Option<u64>,
+) -> Option<handler::WriteBatcher> {
+    redis_client
+        .cloned()
+        .zip(window)
+        .map(|(redis_client, window)| {
+            handler::WriteBatcher::new(redis_client, std::time::Duration::from_millis(window))
+        })
+}
+
+fn redis_client(server: Option<String>) -> Result<Option<redis::Client>> {
+    server
+        .map(|server| -> Result<redis::Client> {
+            event!(Level::INFO, server = server, "Using redis server");
+
+            let client =
+                redis::Client::open(server).context("failed to connect to redis server")?;
+
+            Ok(client)
+        })
+        .transpose()
+}
+
+async fn trivy_config(opt: &args::Args) -> Result<handler::TrivyConfig> {
+    let servers = handler::TrivyServerPool::new(opt.server.clone(), opt.server_concurrency);
+
+    for server in servers.addresses() {
+        event!(Level::INFO, server = server, "Using trivy server");
+    }
+
+    Ok(handler::TrivyConfig {
+        binary: Some(opt.trivy_binary.clone()),
+        servers,
+        allowed_servers: handler::TrivyServerPool::new(
+            opt.allowed_trivy_server.clone(),
+            opt.server_concurrency,
+        ),
+        parallel: opt.trivy_parallel,
+        scanners: opt.trivy_scanners.clone(),
+        ignore_policy: load_ignore_policy(opt.ignore_policy.clone()).await?,
+        trivyignore: load_trivyignore(opt.trivyignore.clone()).await,
+        plugins: opt.trivy_plugin.clone(),
+        compliance: opt.trivy_compliance.clone(),
+        pull_secret: opt.pull_secret.clone(),
+        timeout: Some(std::time::Duration::from_secs(opt.scan_timeout)),
+        severity_mapping: opt.severity_mapping.clone().unwrap_or_default(),
+        queue: handler::ScanQueue::new(opt.scan_concurrency),
+    })
+}
+
+async fn load_ignore_policy(path: Option<PathBuf>) -> Result<Option<handler::IgnorePolicy>> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let policy = handler::IgnorePolicy::load(path).await?;
+
+    event!(
+        Level::INFO,
+        path = policy.path.display().to_string(),
+        hash = policy.hash,
+        "Using trivy ignore policy"
+    );
+
+    Ok(Some(policy))
+}
+
+/// A missing `.trivyignore` is only a warning as trivy scans without it, the
+/// file might be mounted later.
+async fn load_trivyignore(path: Option<PathBuf>) -> Option<handler::TrivyIgnore> {
+    let trivyignore = handler::TrivyIgnore::load(path?).await;
+
+    if let Some(hash) = &trivyignore.hash {
+        event!(
+            Level::INFO,
+            path = trivyignore.path.display().to_string(),
+            hash = hash,
+            "Using trivyignore"
+        );
+    } else {
+        event!(
+            Level::WARN,
+            path = trivyignore.path.display().to_string(),
+            "trivyignore file can not be read"
+        );
+    }
+
+    Some(trivyignore)
+}
+
+async fn load_trusted_digests(path: Option<PathBuf>) -> Result<handler::TrustedDigests> {
+    let trusted_digests = handler::TrustedDigests::load(path.clone()).await?;
+
+    if let Some(path) = path {
+        event!(
+            Level::INFO,
+            path = path.display().to_string(),
+            "Using trusted digests"
+        );
+    }
+
+    Ok(trusted_digests)
+}
@@ -0,0 +1,251 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    path::Path,
+};
+
+use clap::{
+    CommandFactory,
+    FromArgMatches,
+    Parser,
+    parser::ValueSource,
+};
+use eyre::{
+    Context,
+    Result,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::args::Args;
+
+/// Config file with base settings and named profiles that are merged over
+/// them. Settings are keyed by their flag name, e.g. `redis-ttl`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    settings: BTreeMap<String, Value>,
+
+    #[serde(default)]
+    profiles: BTreeMap<String, BTreeMap<String, Value>>,
+}
+
+impl ConfigFile {
+    fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse config file {}", path.display()))
+    }
+
+    /// Base settings with the settings of the profile merged over them.
+    fn settings(mut self, profile: Option<&str>) -> Result<BTreeMap<String, Value>> {
+        let Some(profile) = profile else {
+            return Ok(self.settings);
+        };
+
+        let overrides = self.profiles.remove(profile).ok_or_else(|| {
+            eyre::eyre!(
+                "profile {profile} is not defined in the config file, available profiles: {}",
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+
+        self.settings.extend(overrides);
+
+        Ok(self.settings)
+    }
+}
+
+/// Parses the command line and merges in the settings of the config file
+/// when `--config` is given. Flags and environment variables win over the
+/// config file.
+pub(super) fn parse_args(args: impl IntoIterator<Item = impl Into<OsString>>) -> Result<Args> {
+    let args = args.into_iter().map(Into::into).collect::<Vec<OsString>>();
+
+    let matches = Args::command().get_matches_from(&args);
+
+    let Some(path) = matches.get_one::<std::path::PathBuf>("config") else {
+        return Ok(Args::from_arg_matches(&matches).unwrap_or_else(|err| err.exit()));
+    };
+
+    let settings = ConfigFile::load(path)?
+        .settings(matches.get_one::<String>("profile").map(String::as_str))?;
+
+    let mut merged = args.into_iter();
+    let program = merged.next();
+
+    let merged = program
+        .into_iter()
+        .chain(flags(settings, &matches)?.into_iter().map(OsString::from))
+        .chain(merged);
+
+    Args::try_parse_from(merged).context("invalid setting in config file")
+}
+
+/// Turns settings into flags, settings already given as flag or environment
+/// variable are skipped.
+fn flags(settings: BTreeMap<String, Value>, matches: &clap::ArgMatches) -> Result<Vec<String>> {
+    let command = Args::command();
+    let mut flags = Vec::new();
+
+    for (name, value) in settings {
+        let Some(argument) = command
+            .get_arguments()
+            .find(|argument| argument.get_long() == Some(name.as_str()))
+        else {
+            return Err(eyre::eyre!("unknown setting {name} in config file"));
+        };
+
+        if matches!(
+            matches.value_source(argument.get_id().as_str()),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+
+        match value {
+            Value::Bool(true) => flags.push(format!("--{name}")),
+
+            Value::Bool(false) | Value::Null => {}
+
+            Value::Array(values) => {
+                for value in values {
+                    flags.push(format!("--{name}={}", scalar(&name, value)?));
+                }
+            }
+
+            value => flags.push(format!("--{name}={}", scalar(&name, value)?)),
+        }
+    }
+
+    Ok(flags)
+}
+
+fn scalar(name: &str, value: Value) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(value.to_string()),
+        _ => Err(eyre::eyre!(
+            "setting {name} in config file must be a string, number or boolean"
+        )),
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::path::PathBuf;
+
+    const CONFIG: &str = r#"{
+        "settings": {
+            "redis-ttl": 600,
+            "scan-timeout": 60,
+            "server": ["trivy-dev:4954"]
+        },
+        "profiles": {
+            "stage": {
+                "server": ["trivy-stage:4954"]
+            },
+            "prod": {
+                "scan-timeout": 300,
+                "fail-fast": true,
+                "server": ["trivy-prod-1:4954", "trivy-prod-2:4954"]
+            }
+        }
+    }"#;
+
+    fn config_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "trivy-web-config-{name}-{pid}.json",
+            pid = std::process::id()
+        ));
+
+        std::fs::write(&path, content).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn profile() {
+        let path = config_file("profile", CONFIG);
+
+        let args = super::parse_args([
+            "trivy-web".into(),
+            "--config".into(),
+            path.into_os_string(),
+            "--profile".into(),
+            "prod".into(),
+        ])
+        .unwrap();
+
+        assert_eq!(600, args.redis_ttl);
+        assert_eq!(300, args.scan_timeout);
+        assert!(args.fail_fast);
+        assert_eq!(vec!["trivy-prod-1:4954", "trivy-prod-2:4954"], args.server);
+    }
+
+    #[test]
+    fn base_settings() {
+        let path = config_file("base", CONFIG);
+
+        let args =
+            super::parse_args(["trivy-web".into(), "--config".into(), path.into_os_string()])
+                .unwrap();
+
+        assert_eq!(60, args.scan_timeout);
+        assert!(!args.fail_fast);
+        assert_eq!(vec!["trivy-dev:4954"], args.server);
+    }
+
+    #[test]
+    fn flags_override_profile() {
+        let path = config_file("override", CONFIG);
+
+        let args = super::parse_args([
+            "trivy-web".into(),
+            "--config".into(),
+            path.into_os_string(),
+            "--profile".into(),
+            "prod".into(),
+            "--scan-timeout".into(),
+            "10".into(),
+        ])
+        .unwrap();
+
+        assert_eq!(10, args.scan_timeout);
+        assert!(args.fail_fast);
+    }
+
+    #[test]
+    fn unknown_profile() {
+        let path = config_file("unknown-profile", CONFIG);
+
+        let err = super::parse_args([
+            "trivy-web".into(),
+            "--config".into(),
+            path.into_os_string(),
+            "--profile".into(),
+            "qa".into(),
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            "profile qa is not defined in the config file, available profiles: prod, stage",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn unknown_setting() {
+        let path = config_file("unknown-setting", r#"{"settings": {"redis-tll": 600}}"#);
+
+        let err = super::parse_args(["trivy-web".into(), "--config".into(), path.into_os_string()])
+            .unwrap_err();
+
+        assert_eq!("unknown setting redis-tll in config file", err.to_string());
+    }
+}
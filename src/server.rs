@@ -0,0 +1,259 @@
+use std::{
+    net::SocketAddr,
+    pin::pin,
+};
+
+use axum::{
+    Router,
+    extract::ConnectInfo,
+    http::Request,
+    serve::Listener,
+};
+use hyper::body::Incoming;
+use hyper_util::{
+    rt::{
+        TokioExecutor,
+        TokioIo,
+    },
+    server::{
+        conn::auto::Builder,
+        graceful::GracefulShutdown,
+    },
+    service::TowerToHyperService,
+};
+use tower::ServiceExt;
+
+/// Serves the router until the shutdown future completes and waits for open
+/// connections to finish. `axum::serve` always speaks HTTP/2 once it is
+/// compiled in, so connections are served with hyper directly to keep it
+/// opt-in. With HTTP/2 enabled TLS clients negotiate it through ALPN and
+/// plain HTTP clients starting with the HTTP/2 preface get h2c.
+pub(super) async fn serve<L>(
+    mut listener: L,
+    router: Router,
+    http2: bool,
+    shutdown: impl Future<Output = ()>,
+) where
+    L: Listener<Addr = SocketAddr>,
+{
+    let builder = if http2 {
+        Builder::new(TokioExecutor::new())
+    } else {
+        Builder::new(TokioExecutor::new()).http1_only()
+    };
+
+    let graceful = GracefulShutdown::new();
+    let mut shutdown = pin!(shutdown);
+
+    loop {
+        let (io, address) = tokio::select! {
+            accepted = listener.accept() => accepted,
+            () = &mut shutdown => break,
+        };
+
+        // handlers read the peer address through the ConnectInfo extractor
+        let service = router
+            .clone()
+            .map_request(move |mut request: Request<Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(address));
+                request
+            });
+
+        let connection = graceful.watch(
+            builder
+                .serve_connection(TokioIo::new(io), TowerToHyperService::new(service))
+                .into_owned(),
+        );
+
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                tracing::debug!("failed to serve connection from {address}: {err}");
+            }
+        });
+    }
+
+    graceful.shutdown().await;
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        net::SocketAddr,
+        path::Path,
+        sync::Arc,
+    };
+
+    use axum::{
+        body::Body,
+        extract::ConnectInfo,
+        http::{
+            Request,
+            StatusCode,
+            Version,
+        },
+        routing::get,
+    };
+    use hyper_util::rt::{
+        TokioExecutor,
+        TokioIo,
+    };
+    use rustls::{
+        ClientConfig,
+        RootCertStore,
+        pki_types::{
+            CertificateDer,
+            ServerName,
+            pem::PemObject,
+        },
+    };
+    use tokio::{
+        io::{
+            AsyncRead,
+            AsyncWrite,
+        },
+        net::{
+            TcpListener,
+            TcpStream,
+        },
+    };
+    use tokio_rustls::TlsConnector;
+
+    use crate::tls::{
+        TlsListener,
+        TlsVersion,
+        server_config,
+    };
+
+    const CERTIFICATE: &str = "src/resources/tests/tls_certificate.pem";
+    const KEY: &str = "src/resources/tests/tls_key.pem";
+
+    fn router() -> axum::Router {
+        axum::Router::new()
+            .route("/healthz", get(crate::handler::healthz))
+            .route(
+                "/peer",
+                get(|ConnectInfo(address): ConnectInfo<SocketAddr>| async move {
+                    address.ip().to_string()
+                }),
+            )
+    }
+
+    async fn serve(http2: bool) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(super::serve(
+            listener,
+            router(),
+            http2,
+            std::future::pending(),
+        ));
+
+        address
+    }
+
+    /// Sends a GET request with HTTP/2 prior knowledge.
+    async fn get_http2<I>(io: I, path: &str) -> hyper::Result<(StatusCode, Version, String)>
+    where
+        I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (mut sender, connection) =
+            hyper::client::conn::http2::handshake(TokioExecutor::new(), TokioIo::new(io)).await?;
+
+        tokio::spawn(connection);
+
+        let response = sender
+            .send_request(
+                Request::get(format!("https://localhost{path}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await?;
+
+        let status = response.status();
+        let version = response.version();
+        let body = axum::body::to_bytes(Body::new(response.into_body()), usize::MAX)
+            .await
+            .unwrap();
+
+        Ok((status, version, String::from_utf8(body.to_vec()).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn h2c() {
+        let address = serve(true).await;
+
+        let stream = TcpStream::connect(address).await.unwrap();
+        let (status, version, body) = get_http2(stream, "/healthz").await.unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(Version::HTTP_2, version);
+        assert_eq!("OK", body);
+
+        let stream = TcpStream::connect(address).await.unwrap();
+        let (_, _, peer) = get_http2(stream, "/peer").await.unwrap();
+
+        assert_eq!("127.0.0.1", peer);
+    }
+
+    #[tokio::test]
+    async fn h2c_disabled() {
+        let address = serve(false).await;
+
+        let stream = TcpStream::connect(address).await.unwrap();
+        assert!(get_http2(stream, "/healthz").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn http2_alpn() {
+        let config = server_config(
+            Path::new(CERTIFICATE),
+            Path::new(KEY),
+            TlsVersion::Tls12,
+            &[],
+            true,
+        )
+        .unwrap();
+
+        let listener = TlsListener::new(TcpListener::bind("127.0.0.1:0").await.unwrap(), config);
+        let address = axum::serve::Listener::local_addr(&listener).unwrap();
+
+        tokio::spawn(super::serve(
+            listener,
+            router(),
+            true,
+            std::future::pending(),
+        ));
+
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_file(CERTIFICATE).unwrap())
+            .unwrap();
+
+        let mut client = ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::aws_lc_rs::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        client.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+        let stream = TlsConnector::from(Arc::new(client))
+            .connect(
+                ServerName::try_from("localhost").unwrap(),
+                TcpStream::connect(address).await.unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(Some(&b"h2"[..]), stream.get_ref().1.alpn_protocol());
+
+        let (status, version, body) = get_http2(stream, "/healthz").await.unwrap();
+
+        assert_eq!(StatusCode::OK, status);
+        assert_eq!(Version::HTTP_2, version);
+        assert_eq!("OK", body);
+    }
+}
@@ -0,0 +1,119 @@
+use docker_registry_client::{
+    Client as DockerRegistryClient,
+    Image,
+};
+use eyre::{
+    Context,
+    Result,
+};
+use tracing::{
+    Level,
+    event,
+};
+
+use crate::handler;
+
+/// Downloads the trivy vulnerability DB before traffic is served. Failures
+/// are only logged as scans download the DB themselves when it is missing.
+pub(super) async fn warmup_db(config: &handler::TrivyConfig) {
+    if config.servers.addresses().next().is_some() {
+        event!(
+            Level::INFO,
+            "Skipping trivy DB warmup as scans run against a trivy server"
+        );
+
+        return;
+    }
+
+    let started = std::time::Instant::now();
+
+    match handler::update_db(config).await {
+        Ok(()) => event!(
+            Level::INFO,
+            duration_ms = started.elapsed().as_millis(),
+            "Trivy DB warmup finished"
+        ),
+
+        Err(err) => event!(Level::ERROR, "Trivy DB warmup failed: {err:#}"),
+    }
+}
+
+/// Looks up the manifest of the canary image so operators know registry
+/// access works before traffic is served.
+pub(super) async fn registry_check(
+    client: &DockerRegistryClient,
+    canary: &Image,
+    fail_fast: bool,
+) -> Result<()> {
+    let probe = async {
+        client
+            .get_manifest(canary)
+            .await
+            .context("can not get manifest from docker registry")
+    };
+
+    check(&canary.to_string(), probe, fail_fast).await
+}
+
+async fn check<T>(
+    canary: &str,
+    probe: impl Future<Output = Result<T>>,
+    fail_fast: bool,
+) -> Result<()> {
+    match probe.await {
+        Ok(_) => {
+            event!(Level::INFO, canary = canary, "Docker registry check passed");
+
+            Ok(())
+        }
+
+        Err(err) if fail_fast => Err(err.wrap_err("docker registry check failed")),
+
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                canary = canary,
+                "Docker registry check failed: {err:#}"
+            );
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    fn failing() -> std::future::Ready<eyre::Result<()>> {
+        std::future::ready(Err(eyre::eyre!("connection refused")))
+    }
+
+    #[tokio::test]
+    async fn failure_does_not_prevent_startup() {
+        assert!(
+            super::check("alpine:latest", failing(), false)
+                .await
+                .is_ok()
+        );
+    }
+
+    #[tokio::test]
+    async fn failure_with_fail_fast() {
+        let err = super::check("alpine:latest", failing(), true)
+            .await
+            .expect_err("should fail");
+
+        assert_eq!(
+            "docker registry check failed: connection refused",
+            format!("{err:#}")
+        );
+    }
+
+    #[tokio::test]
+    async fn success() {
+        assert!(
+            super::check("alpine:latest", async { Ok(()) }, true)
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::{
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::Duration,
+};
+
+use axum::serve::Listener;
+use clap::ValueEnum;
+use eyre::{
+    Context,
+    Result,
+};
+use rustls::{
+    ServerConfig,
+    SupportedProtocolVersion,
+    pki_types::{
+        CertificateDer,
+        PrivateKeyDer,
+        pem::PemObject,
+    },
+};
+use tokio::net::{
+    TcpListener,
+    TcpStream,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    server::TlsStream,
+};
+
+/// Clients that do not finish the handshake in time are dropped so they can
+/// not stall accepting further connections.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+static TLS13_ONLY: &[&SupportedProtocolVersion] = &[&rustls::version::TLS13];
+
+/// Lowest TLS version accepted by the server. Versions below TLS 1.2 are
+/// never negotiated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub(super) enum TlsVersion {
+    #[value(name = "1.2")]
+    Tls12,
+
+    #[value(name = "1.3")]
+    Tls13,
+}
+
+impl TlsVersion {
+    fn protocol_versions(self) -> &'static [&'static SupportedProtocolVersion] {
+        match self {
+            Self::Tls12 => rustls::ALL_VERSIONS,
+            Self::Tls13 => TLS13_ONLY,
+        }
+    }
+}
+
+/// Builds the rustls configuration from PEM files. Only the named cipher
+/// suites are offered when any are given, e.g.
+/// `TLS13_AES_256_GCM_SHA384`. HTTP/2 is offered through ALPN when enabled.
+pub(super) fn server_config(
+    certificate: &Path,
+    key: &Path,
+    min_version: TlsVersion,
+    cipher_suites: &[String],
+    http2: bool,
+) -> Result<ServerConfig> {
+    let certificates = CertificateDer::pem_file_iter(certificate)
+        .context("failed to open tls certificate")?
+        .collect::<Result<Vec<_>, _>>()
+        .context("failed to parse tls certificate")?;
+
+    let key = PrivateKeyDer::from_pem_file(key).context("failed to parse tls key")?;
+
+    let mut provider = rustls::crypto::aws_lc_rs::default_provider();
+
+    if !cipher_suites.is_empty() {
+        for name in cipher_suites {
+            if !provider
+                .cipher_suites
+                .iter()
+                .any(|suite| suite_name(*suite) == *name)
+            {
+                return Err(eyre::eyre!("unsupported tls cipher suite {name}"));
+            }
+        }
+
+        provider
+            .cipher_suites
+            .retain(|suite| cipher_suites.contains(&suite_name(*suite)));
+    }
+
+    let mut config = ServerConfig::builder_with_provider(Arc::new(provider))
+        .with_protocol_versions(min_version.protocol_versions())
+        .context("tls cipher suites do not support the minimum tls version")?
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)
+        .context("invalid tls certificate or key")?;
+
+    config.alpn_protocols = if http2 {
+        vec![b"h2".to_vec(), b"http/1.1".to_vec()]
+    } else {
+        vec![b"http/1.1".to_vec()]
+    };
+
+    Ok(config)
+}
+
+fn suite_name(suite: rustls::SupportedCipherSuite) -> String {
+    format!("{:?}", suite.suite())
+}
+
+/// Accepts TCP connections and completes the TLS handshake before handing
+/// them to axum. Failed handshakes, e.g. from clients below the minimum TLS
+/// version, are logged and dropped.
+pub(super) struct TlsListener {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+}
+
+impl TlsListener {
+    pub(super) fn new(listener: TcpListener, config: ServerConfig) -> Self {
+        Self {
+            listener,
+            acceptor: TlsAcceptor::from(Arc::new(config)),
+        }
+    }
+}
+
+impl std::fmt::Debug for TlsListener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlsListener")
+            .field("listener", &self.listener)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            let (stream, address) = Listener::accept(&mut self.listener).await;
+
+            match tokio::time::timeout(HANDSHAKE_TIMEOUT, self.acceptor.accept(stream)).await {
+                Ok(Ok(stream)) => return (stream, address),
+
+                Ok(Err(err)) => tracing::debug!("tls handshake with {address} failed: {err}"),
+
+                Err(_) => tracing::debug!("tls handshake with {address} timed out"),
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        self.listener.local_addr()
+    }
+}
+
+#[cfg(test)]
+#[expect(clippy::unwrap_used, reason = "using unwrap in tests is fine")]
+mod test {
+    use std::{
+        path::Path,
+        sync::Arc,
+    };
+
+    use axum::serve::ListenerExt;
+    use rustls::{
+        ClientConfig,
+        RootCertStore,
+        pki_types::{
+            CertificateDer,
+            ServerName,
+            pem::PemObject,
+        },
+    };
+    use tokio::{
+        io::{
+            AsyncReadExt,
+            AsyncWriteExt,
+        },
+        net::{
+            TcpListener,
+            TcpStream,
+        },
+    };
+    use tokio_rustls::TlsConnector;
+
+    use super::{
+        TlsListener,
+        TlsVersion,
+        server_config,
+    };
+
+    const CERTIFICATE: &str = "src/resources/tests/tls_certificate.pem";
+    const KEY: &str = "src/resources/tests/tls_key.pem";
+
+    /// TLS 1.1 `ClientHello` offering `TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256`.
+    #[rustfmt::skip]
+    const TLS11_CLIENT_HELLO: &[u8] = &[
+        // record: handshake, TLS 1.0 record version, length
+        0x16, 0x03, 0x01, 0x00, 0x37,
+        // handshake: client hello, length
+        0x01, 0x00, 0x00, 0x33,
+        // client version TLS 1.1
+        0x03, 0x02,
+        // random
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07,
+        0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+        0x10, 0x11, 0x12, 0x13, 0x14, 0x15, 0x16, 0x17,
+        0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f,
+        // session id
+        0x00,
+        // cipher suites
+        0x00, 0x02, 0xc0, 0x2b,
+        // compression methods
+        0x01, 0x00,
+        // extensions: signature_algorithms with ecdsa_secp256r1_sha256
+        0x00, 0x08, 0x00, 0x0d, 0x00, 0x04, 0x00, 0x02, 0x04, 0x03,
+    ];
+
+    async fn serve(min_version: TlsVersion) -> std::net::SocketAddr {
+        let config = server_config(
+            Path::new(CERTIFICATE),
+            Path::new(KEY),
+            min_version,
+            &[],
+            false,
+        )
+        .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listener = TlsListener::new(listener, config).tap_io(|_| {});
+        let address = axum::serve::Listener::local_addr(&listener).unwrap();
+
+        let router = axum::Router::new().route("/", axum::routing::get(|| async { "ok" }));
+
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+            )
+            .await
+            .unwrap();
+        });
+
+        address
+    }
+
+    async fn connect(
+        address: std::net::SocketAddr,
+        versions: &[&'static rustls::SupportedProtocolVersion],
+    ) -> std::io::Result<()> {
+        let mut roots = RootCertStore::empty();
+        roots
+            .add(CertificateDer::from_pem_file(CERTIFICATE).unwrap())
+            .unwrap();
+
+        let config = ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::aws_lc_rs::default_provider(),
+        ))
+        .with_protocol_versions(versions)
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+        let stream = TcpStream::connect(address).await.unwrap();
+
+        TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await
+            .map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn tls11_refused() {
+        let address = serve(TlsVersion::Tls12).await;
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_all(TLS11_CLIENT_HELLO).await.unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await.unwrap();
+
+        // fatal protocol_version alert
+        assert_eq!(Some(&0x15), response.first());
+        assert_eq!(Some(&[0x02, 0x46][..]), response.get(5..7));
+
+        connect(address, rustls::ALL_VERSIONS).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn min_version() {
+        let address = serve(TlsVersion::Tls13).await;
+
+        assert!(connect(address, &[&rustls::version::TLS12]).await.is_err());
+        connect(address, &[&rustls::version::TLS13]).await.unwrap();
+    }
+
+    #[test]
+    fn cipher_suites() {
+        let config = server_config(
+            Path::new(CERTIFICATE),
+            Path::new(KEY),
+            TlsVersion::Tls13,
+            &["TLS13_AES_256_GCM_SHA384".to_string()],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(1, config.crypto_provider().cipher_suites.len());
+
+        let err = server_config(
+            Path::new(CERTIFICATE),
+            Path::new(KEY),
+            TlsVersion::Tls12,
+            &["TLS_RSA_WITH_RC4_128_MD5".to_string()],
+            false,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            "unsupported tls cipher suite TLS_RSA_WITH_RC4_128_MD5",
+            err.to_string()
+        );
+    }
+}